@@ -1,37 +1,35 @@
 #import bevy_pbr::{
     mesh_functions,
-    skinning,
-    morph::morph,
     view_transformations::position_world_to_clip,
 }
 #import bevy_render::instance_index::get_instance_index
+#import "shaders/terrain_vertex.wgsl"::{
+    vertex_position,
+    vertex_normal,
+    vertex_uv,
+    vertex_voxel_id,
+    vertex_occlusion,
+    vertex_light,
+}
+
+struct VoxelMaterial {
+    color: vec4<f32>,
+    emissive: vec4<f32>,
+    roughness: f32,
+    transparency: f32,
+}
+
+@group(1) @binding(100) var<storage, read> voxel_materials: array<VoxelMaterial>;
+@group(1) @binding(101) var<storage, read> daylight: f32;
+
+const BLOCK_LIGHT_COLOR: vec3<f32> = vec3<f32>(1.0, 0.8, 0.6);
+// sky light left at night, moonlight keeps the surface from being pitch black
+const NIGHT_SKY_LIGHT: f32 = 0.2;
 
-struct ExtendedVertex {
+// Everything is packed in two words, see `terrain_vertex.wgsl`
+struct TerrainVertex {
     @builtin(instance_index) instance_index: u32,
-#ifdef VERTEX_POSITIONS
-    @location(0) position: vec3<f32>,
-#endif
-#ifdef VERTEX_NORMALS
-    @location(1) normal: vec3<f32>,
-#endif
-#ifdef VERTEX_UVS
-    @location(2) uv: vec2<f32>,
-#endif
-// (Alternate UVs are at location 3, but they're currently unused here.)
-#ifdef VERTEX_TANGENTS
-    @location(4) tangent: vec4<f32>,
-#endif
-#ifdef VERTEX_COLORS
-    @location(5) color: vec4<f32>,
-#endif
-#ifdef SKINNED
-    @location(6) joint_indices: vec4<u32>,
-    @location(7) joint_weights: vec4<f32>,
-#endif
-    @location(8) voxel_id: u32,
-#ifdef MORPH_TARGETS
-    @builtin(vertex_index) index: u32,
-#endif
+    @location(8) packed: vec2<u32>,
 };
 
 struct ExtendedVertexOutput {
@@ -40,82 +38,39 @@ struct ExtendedVertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) world_position: vec4<f32>,
     @location(1) world_normal: vec3<f32>,
-#ifdef VERTEX_UVS
     @location(2) uv: vec2<f32>,
-#endif
-#ifdef VERTEX_TANGENTS
-    @location(3) world_tangent: vec4<f32>,
-#endif
-#ifdef VERTEX_COLORS
-    @location(4) color: vec4<f32>,
-#endif
 #ifdef VERTEX_OUTPUT_INSTANCE_INDEX
     @location(5) @interpolate(flat) instance_index: u32,
 #endif
     @location(6) voxel_id: u32,
+    @location(7) occlusion: f32,
+    @location(8) light: vec2<f32>,
 }
 
 @vertex
-fn vertex(vertex_no_morph: ExtendedVertex) -> ExtendedVertexOutput {
+fn vertex(vertex: TerrainVertex) -> ExtendedVertexOutput {
     var out: ExtendedVertexOutput;
 
-#ifdef MORPH_TARGETS
-    var vertex = morph_vertex(vertex_no_morph);
-#else
-    var vertex = vertex_no_morph;
-#endif
-
-#ifdef SKINNED
-    var model = skinning::skin_model(vertex.joint_indices, vertex.joint_weights);
-#else
-    // Use vertex_no_morph.instance_index instead of vertex.instance_index to work around a wgpu dx12 bug.
-    // See https://github.com/gfx-rs/naga/issues/2416 .
-    var model = mesh_functions::get_model_matrix(vertex_no_morph.instance_index);
-#endif
+    let model = mesh_functions::get_model_matrix(vertex.instance_index);
 
-#ifdef VERTEX_NORMALS
-#ifdef SKINNED
-    out.world_normal = skinning::skin_normals(model, vertex.normal);
-#else
     out.world_normal = mesh_functions::mesh_normal_local_to_world(
-        vertex.normal,
-        // Use vertex_no_morph.instance_index instead of vertex.instance_index to work around a wgpu dx12 bug.
-        // See https://github.com/gfx-rs/naga/issues/2416
-        get_instance_index(vertex_no_morph.instance_index)
+        vertex_normal(vertex.packed),
+        get_instance_index(vertex.instance_index)
     );
-#endif
-#endif
-
-#ifdef VERTEX_POSITIONS
-    out.world_position = mesh_functions::mesh_position_local_to_world(model, vec4<f32>(vertex.position, 1.0));
-    out.position = position_world_to_clip(out.world_position.xyz);
-#endif
-
-#ifdef VERTEX_UVS
-    out.uv = vertex.uv;
-#endif
-
-#ifdef VERTEX_TANGENTS
-    out.world_tangent = mesh_functions::mesh_tangent_local_to_world(
+    out.world_position = mesh_functions::mesh_position_local_to_world(
         model,
-        vertex.tangent,
-        // Use vertex_no_morph.instance_index instead of vertex.instance_index to work around a wgpu dx12 bug.
-        // See https://github.com/gfx-rs/naga/issues/2416
-        get_instance_index(vertex_no_morph.instance_index)
+        vec4<f32>(vertex_position(vertex.packed), 1.0)
     );
-#endif
-
-#ifdef VERTEX_COLORS
-    out.color = vertex.color;
-#endif
+    out.position = position_world_to_clip(out.world_position.xyz);
+    out.uv = vertex_uv(vertex.packed);
 
 #ifdef VERTEX_OUTPUT_INSTANCE_INDEX
-    // Use vertex_no_morph.instance_index instead of vertex.instance_index to work around a wgpu dx12 bug.
-    // See https://github.com/gfx-rs/naga/issues/2416
-    out.instance_index = get_instance_index(vertex_no_morph.instance_index);
+    out.instance_index = get_instance_index(vertex.instance_index);
 #endif
 
-    out.voxel_id = vertex_no_morph.voxel_id;
+    out.voxel_id = vertex_voxel_id(vertex.packed);
+    out.occlusion = vertex_occlusion(vertex.packed);
+    out.light = vertex_light(vertex.packed);
 
     return out;
 }
@@ -147,15 +102,7 @@ fn fragment(
     pbr_in.position = in.position;
     pbr_in.world_position = in.world_position;
     pbr_in.world_normal = in.world_normal;
-#ifdef VERTEX_UVS
     pbr_in.uv = in.uv;
-#endif
-#ifdef VERTEX_TANGENTS
-    pbr_in.world_tangent = in.world_tangent;
-#endif
-#ifdef VERTEX_COLORS
-    pbr_in.color = in.color;
-#endif
 #ifdef VERTEX_OUTPUT_INSTANCE_INDEX
     pbr_in.instance_index = in.instance_index;
 #endif
@@ -163,17 +110,20 @@ fn fragment(
     // generate a PbrInput struct from the StandardMaterial bindings
     var pbr_input = pbr_input_from_standard_material(pbr_in, is_front);
 
-    // we can optionally modify the input before lighting and alpha_discard is applied
-    if (in.voxel_id == 0u) {
-        pbr_input.material.base_color = vec4(0.12, 0.68, 0.10, 1.0);
-    }
-    if (in.voxel_id == 1u) {
-        pbr_input.material.base_color = vec4(0.42, 0.33, 0.16, 1.0);
-    }
-    if (in.voxel_id == 2u) {
-        pbr_input.material.base_color = vec4(0.5, 0.5, 0.5, 1.0);
-    }
-
+    // base color comes from the terrain atlas, sampled with the per-face UVs generated while meshing,
+    // every other property comes from the material registry
+    let voxel_material = voxel_materials[in.voxel_id];
+    pbr_input.material.base_color *= voxel_material.color;
+    // block light glows with the color of the lit surface, sky light follows the daylight and fades ambient light
+    // out in caves and interiors, whatever the time of day
+    let block_light = pbr_input.material.base_color.rgb * BLOCK_LIGHT_COLOR * in.light.y;
+    pbr_input.material.emissive = vec4<f32>(voxel_material.emissive.rgb + block_light, 1.0);
+    pbr_input.occlusion *= in.light.x * mix(NIGHT_SKY_LIGHT, 1.0, daylight);
+    pbr_input.material.perceptual_roughness = voxel_material.roughness;
+    // baked ambient occlusion only darkens indirect light, like screen space ambient occlusion does
+    pbr_input.occlusion *= in.occlusion;
+    // blended by the translucent pass, opaque and cutout voxels are alpha tested and keep a transparency of 0
+    pbr_input.material.base_color.a *= 1.0 - voxel_material.transparency;
     // alpha discard
     pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
 
@@ -0,0 +1,57 @@
+#import bevy_pbr::{
+    mesh_functions,
+    prepass_io::VertexOutput,
+}
+#import bevy_render::instance_index::get_instance_index
+#import "shaders/terrain_vertex.wgsl"::{vertex_position, vertex_normal, vertex_uv}
+
+struct TerrainVertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(8) packed: vec2<u32>,
+};
+
+@vertex
+fn vertex(vertex: TerrainVertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let model = mesh_functions::get_model_matrix(vertex.instance_index);
+    let position = vec4<f32>(vertex_position(vertex.packed), 1.0);
+
+    out.position = mesh_functions::mesh_position_local_to_clip(model, position);
+#ifdef DEPTH_CLAMP_ORTHO
+    out.clip_position_unclamped = out.position;
+    out.position.z = min(out.position.z, 1.0);
+#endif
+
+#ifdef VERTEX_UVS
+    out.uv = vertex_uv(vertex.packed);
+#endif
+
+#ifdef NORMAL_PREPASS_OR_DEFERRED_PREPASS
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(
+        vertex_normal(vertex.packed),
+        get_instance_index(vertex.instance_index)
+    );
+#endif
+
+#ifdef MOTION_VECTOR_PREPASS_OR_DEFERRED_PREPASS
+    out.world_position = mesh_functions::mesh_position_local_to_world(model, position);
+#endif
+
+#ifdef MOTION_VECTOR_PREPASS
+    out.previous_world_position = mesh_functions::mesh_position_local_to_world(
+        mesh_functions::get_previous_model_matrix(vertex.instance_index),
+        position
+    );
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = get_instance_index(vertex.instance_index);
+#endif
+#ifdef BASE_INSTANCE_WORKAROUND
+    // Keeps the push constant used, like Bevy's prepass does: https://github.com/bevyengine/bevy/issues/10509
+    out.position.x += min(f32(get_instance_index(0u)), 0.0);
+#endif
+
+    return out;
+}
@@ -0,0 +1,39 @@
+// Decoding of the packed terrain vertices, mirroring `TerrainVertex::pack`:
+// - first word: x (8 bits), y (9 bits), z (8 bits) then the normal index (7 bits)
+// - second word: voxel id (10 bits), atlas tile (6 bits), u and v (1 bit each), occlusion (4 bits), then sky and
+//   block light (5 bits each)
+
+fn vertex_position(packed: vec2<u32>) -> vec3<f32> {
+    return vec3<f32>(
+        f32(packed.x & 0xffu),
+        f32((packed.x >> 8u) & 0x1ffu),
+        f32((packed.x >> 17u) & 0xffu),
+    );
+}
+
+// Integer normals go from -2 to 2 on every axis, indexed in base 5
+fn vertex_normal(packed: vec2<u32>) -> vec3<f32> {
+    let index = packed.x >> 25u;
+    let normal = vec3<f32>(f32(index / 25u), f32((index / 5u) % 5u), f32(index % 5u)) - 2.0;
+    return normalize(normal);
+}
+
+fn vertex_voxel_id(packed: vec2<u32>) -> u32 {
+    return packed.y & 0x3ffu;
+}
+
+fn vertex_uv(packed: vec2<u32>) -> vec2<f32> {
+    let tile = (packed.y >> 10u) & 0x3fu;
+    let u = (packed.y >> 16u) & 1u;
+    let v = (packed.y >> 17u) & 1u;
+    return vec2<f32>(f32(u), f32(tile + v) / f32(#{ATLAS_TILE_COUNT}));
+}
+
+fn vertex_occlusion(packed: vec2<u32>) -> f32 {
+    return f32((packed.y >> 18u) & 0xfu) / 15.0;
+}
+
+// Sky and block light
+fn vertex_light(packed: vec2<u32>) -> vec2<f32> {
+    return vec2<f32>(f32((packed.y >> 22u) & 0x1fu), f32(packed.y >> 27u)) / 31.0;
+}
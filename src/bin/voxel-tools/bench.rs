@@ -0,0 +1,152 @@
+use std::{mem::size_of, time::Instant};
+
+use bevy::{math::IVec3, render::mesh::Mesh};
+
+use voxel::world::{
+    chunk::{
+        self,
+        mesh::{occlusion::AmbientOcclusion, ChunkMesh},
+    },
+    light::LightLevel,
+    voxel::VoxelDescriptor,
+};
+
+use super::{generate_world, load_materials, parse_chunk_size, parse_generator, take_option};
+
+/// Chunks meshed around the origin by default, as a radius in chunks.
+const DEFAULT_RADIUS: i32 = 4;
+
+/// Distribution of a measure over the benchmarked chunks.
+struct Percentiles {
+    mean: f64,
+    p50: f64,
+    p90: f64,
+    p99: f64,
+    max: f64,
+}
+
+impl Percentiles {
+    fn new(mut values: Vec<f64>) -> Self {
+        values.sort_by(f64::total_cmp);
+        let percentile = |percentile: f64| {
+            let index = ((values.len() - 1) as f64 * percentile).round() as usize;
+            values[index]
+        };
+        Self {
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: *values.last().unwrap(),
+        }
+    }
+}
+
+/// Generate and mesh a fixed set of chunks around the origin, then print the distribution of how long they took,
+/// their vertex and triangle counts and how much memory they use.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let generator = parse_generator(&mut args)?;
+    let chunk_size = parse_chunk_size(&mut args)?;
+    let radius = match take_option(&mut args, "--radius")? {
+        Some(radius) => radius
+            .parse::<i32>()
+            .map_err(|error| format!("invalid radius {radius:?}: {error}"))?,
+        None => DEFAULT_RADIUS,
+    };
+    if radius < 0 {
+        return Err(format!("invalid radius {radius}, at least 0"));
+    }
+    if !args.is_empty() {
+        return Err("invalid bench arguments".to_string());
+    }
+
+    let materials = load_materials()?;
+    // Meshed chunks need their neighbours to be generated, which are left out of the generation measures
+    let layers = chunk_size.layers();
+    let square = move |radius: i32| {
+        (-radius..=radius).flat_map(move |x| {
+            (-radius..=radius).flat_map(move |z| {
+                (0..layers).map(move |y| chunk::Coordinates(IVec3::new(x, y, z)))
+            })
+        })
+    };
+    let meshed: Vec<chunk::Coordinates> = square(radius).collect();
+    let borders = square(radius + 1).filter(|coordinates| !meshed.contains(coordinates));
+    let (world, durations) = generate_world(
+        chunk_size,
+        meshed.iter().copied().chain(borders),
+        &generator,
+        &materials,
+    );
+
+    let mut generation = vec![];
+    let mut meshing = vec![];
+    let mut vertices = vec![];
+    let mut triangles = vec![];
+    let mut terrain_memory = vec![];
+    let mut mesh_memory = vec![];
+    for (coordinates, duration) in meshed.iter().zip(durations) {
+        generation.push(duration.as_secs_f64() * 1000.0);
+
+        let chunk = world.get_chunk(*coordinates).unwrap();
+        let timer = Instant::now();
+        let (mesh, translucent_mesh, _) =
+            ChunkMesh::new(materials.clone(), AmbientOcclusion::default())
+                .mesh_chunk(chunk.clone(), &world)
+                .mesh();
+        meshing.push(timer.elapsed().as_secs_f64() * 1000.0);
+
+        let meshes = [Some(&mesh), translucent_mesh.as_ref()];
+        let meshes = meshes.into_iter().flatten();
+        let vertex_count: usize = meshes.clone().map(Mesh::count_vertices).sum();
+        vertices.push(vertex_count as f64);
+        let triangle_count: usize = meshes
+            .clone()
+            .map(|mesh| {
+                mesh.indices()
+                    .map_or(mesh.count_vertices(), |indices| indices.len())
+                    / 3
+            })
+            .sum();
+        triangles.push(triangle_count as f64);
+        let attribute_bytes: usize = meshes
+            .flat_map(|mesh| {
+                mesh.attributes()
+                    .map(|(_, values)| values.get_bytes().len())
+            })
+            .sum();
+        mesh_memory.push(attribute_bytes as f64 / 1024.0);
+
+        let chunk = chunk.read();
+        let terrain = chunk.terrain.as_ref().unwrap();
+        let terrain_bytes = terrain.voxels.len() * size_of::<Option<VoxelDescriptor>>()
+            + terrain.light.len() * size_of::<LightLevel>();
+        terrain_memory.push(terrain_bytes as f64 / 1024.0);
+    }
+
+    println!(
+        "Benchmarked {} chunks of {} generated by {generator:?}",
+        meshed.len(),
+        *chunk_size
+    );
+    println!(
+        "{:<22}{:>10}{:>10}{:>10}{:>10}{:>10}",
+        "", "mean", "p50", "p90", "p99", "max"
+    );
+    for (name, values) in [
+        ("generation (ms)", generation),
+        ("meshing (ms)", meshing),
+        ("vertices", vertices),
+        ("triangles", triangles),
+        ("terrain memory (KiB)", terrain_memory),
+        ("mesh memory (KiB)", mesh_memory),
+    ] {
+        let percentiles = Percentiles::new(values);
+        println!(
+            "{name:<22}{:>10.2}{:>10.2}{:>10.2}{:>10.2}{:>10.2}",
+            percentiles.mean, percentiles.p50, percentiles.p90, percentiles.p99, percentiles.max
+        );
+    }
+    Ok(())
+}
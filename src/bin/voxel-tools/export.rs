@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use bevy::math::IVec3;
+
+use voxel::world::chunk::{
+    self,
+    mesh::export::{chunks_in_region, MeshExport},
+};
+
+use super::{
+    generate_world, load_materials, parse_chunk_size, parse_generator, parse_ivec2, parse_ivec3,
+    take_flag,
+};
+
+/// Generate the terrain around a column of chunks or a region and write its mesh to an OBJ or binary glTF file.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let colors = take_flag(&mut args, "--colors");
+    let generator = parse_generator(&mut args)?;
+    let chunk_size = parse_chunk_size(&mut args)?;
+    let size = chunk_size.as_ivec3();
+
+    let (min, max, output) = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["chunk", coordinates, output] => {
+            let coordinates = parse_ivec2(coordinates)?;
+            // Every layer of chunks of the column
+            let chunk = chunk::Coordinates(IVec3::new(coordinates.x, 0, coordinates.y));
+            let min = chunk_size.origin(chunk);
+            let max = IVec3::new(min.x + size.x, chunk_size.sky_height(), min.z + size.z);
+            (min, max - IVec3::ONE, output)
+        }
+        ["region", first, second, output] => {
+            let (first, second) = (parse_ivec3(first)?, parse_ivec3(second)?);
+            (first.min(second), first.max(second), output)
+        }
+        _ => return Err("invalid export arguments".to_string()),
+    };
+
+    let materials = load_materials()?;
+    // Neighbouring chunks are generated too so faces on the region borders are hidden like in game. There are no
+    // chunks above or below the layers of the world.
+    let chunks = chunks_in_region(chunk_size, min - size, max + size)
+        .filter(|coordinates| (0..chunk_size.layers()).contains(&coordinates.0.y))
+        .collect::<Vec<chunk::Coordinates>>();
+    let (world, _) = generate_world(chunk_size, chunks, &generator, &materials);
+
+    let output = PathBuf::from(output);
+    let export = MeshExport::from_region(&world, &materials, min, max);
+    export
+        .write(&output, &materials, colors)
+        .map_err(|error| error.to_string())?;
+    println!("Exported {min} to {max} to {}", output.display());
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use std::{
+    path::Path,
+    process::ExitCode,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    asset::io::file::FileAssetReader,
+    math::IVec3,
+    prelude::{Entity, IVec2},
+};
+
+use voxel::world::{
+    chunk::{
+        self,
+        generator::{heightmap_terrain::Heightmap, TerrainGeneratorKind},
+        mesh::vertex::MAX_CHUNK_SIZE,
+        tasks::generate_terrain,
+        ChunkSize,
+    },
+    voxel::material::registry::{MaterialRegistry, MaterialRegistryAsset, MATERIAL_REGISTRY_PATH},
+    World,
+};
+
+mod bench;
+mod export;
+mod worldgen;
+
+const USAGE: &str = "usage:
+    voxel-tools export chunk <x,z> <output.obj|output.glb> [--colors] [generation options]
+    voxel-tools export region <x,y,z> <x,y,z> <output.obj|output.glb> [--colors] [generation options]
+    voxel-tools worldgen <chunk x,z> <chunk x,z> <output folder> [generation options]
+    voxel-tools bench [--radius <chunks>] [generation options]
+generation options:
+    --seed <seed>
+    --chunk-size <x,y,z>
+    --generator noise|height-noise|showcase|heightmap:<settings.heightmap.ron>";
+
+/// Headless tools working on the terrain without opening a window: mesh export, world generation maps and
+/// benchmarks.
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the headless tool named by the first argument.
+fn run(args: &[String]) -> Result<(), String> {
+    let Some(command) = args.first() else { return Err("missing command".to_string()) };
+    match command.as_str() {
+        "export" => export::run(&args[1..]),
+        "worldgen" => worldgen::run(&args[1..]),
+        "bench" => bench::run(&args[1..]),
+        _ => Err(format!("unknown command {command:?}")),
+    }
+}
+
+/// Remove a flag from the arguments, returning whether it was there.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let Some(index) = args.iter().position(|arg| arg == flag) else { return false };
+    args.remove(index);
+    true
+}
+
+/// Remove an option and its value from the arguments.
+fn take_option(args: &mut Vec<String>, option: &str) -> Result<Option<String>, String> {
+    let Some(index) = args.iter().position(|arg| arg == option) else { return Ok(None) };
+    if index + 1 >= args.len() {
+        return Err(format!("missing value for {option}"));
+    }
+    args.remove(index);
+    Ok(Some(args.remove(index)))
+}
+
+/// Generator named by the `--generator` option, seeded by the `--seed` option.
+fn parse_generator(args: &mut Vec<String>) -> Result<TerrainGeneratorKind, String> {
+    let seed = match take_option(args, "--seed")? {
+        Some(seed) => seed
+            .parse()
+            .map_err(|error| format!("invalid seed {seed:?}: {error}"))?,
+        None => 0,
+    };
+    match take_option(args, "--generator")?.as_deref() {
+        None | Some("noise") => Ok(TerrainGeneratorKind::Noise { seed }),
+        Some("height-noise") => Ok(TerrainGeneratorKind::HeightNoise { seed }),
+        Some("showcase") => Ok(TerrainGeneratorKind::ShapeShowcase),
+        Some(generator) => {
+            let Some(path) = generator.strip_prefix("heightmap:") else { return Err(format!("unknown generator {generator:?}")) };
+            let heightmap = Heightmap::open(Path::new(path)).map_err(|error| error.to_string())?;
+            Ok(TerrainGeneratorKind::Heightmap(Arc::new(heightmap)))
+        }
+    }
+}
+
+/// Dimensions of the chunks given by the `--chunk-size` option, the game's by default.
+fn parse_chunk_size(args: &mut Vec<String>) -> Result<ChunkSize, String> {
+    let Some(size) = take_option(args, "--chunk-size")? else { return Ok(ChunkSize::default()) };
+    let size = parse_ivec3(&size)?;
+    if size.cmple(IVec3::ZERO).any() || size.as_uvec3().cmpgt(MAX_CHUNK_SIZE).any() {
+        return Err(format!(
+            "invalid chunk size {size}, at most {MAX_CHUNK_SIZE}"
+        ));
+    }
+    Ok(ChunkSize::new(size.as_uvec3()))
+}
+
+/// Comma separated integers, like `4,-2`.
+fn parse_integers<const N: usize>(text: &str) -> Result<[i32; N], String> {
+    let integers = text
+        .split(',')
+        .map(|integer| integer.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format!("invalid coordinates {text:?}: {error}"))?;
+    integers
+        .try_into()
+        .map_err(|_| format!("expected {N} coordinates, got {text:?}"))
+}
+
+fn parse_ivec2(text: &str) -> Result<IVec2, String> {
+    parse_integers(text).map(IVec2::from_array)
+}
+
+fn parse_ivec3(text: &str) -> Result<IVec3, String> {
+    parse_integers(text).map(IVec3::from_array)
+}
+
+/// Read the material registry file of the assets folder.
+fn load_materials() -> Result<MaterialRegistry, String> {
+    let path = FileAssetReader::get_base_path()
+        .join("assets")
+        .join(MATERIAL_REGISTRY_PATH);
+    let bytes = std::fs::read(&path).map_err(|error| format!("{}: {error}", path.display()))?;
+    let registry: MaterialRegistryAsset =
+        ron::de::from_bytes(&bytes).map_err(|error| format!("{}: {error}", path.display()))?;
+    Ok(MaterialRegistry::new(registry.materials))
+}
+
+/// World made of freshly generated chunks, lit across their borders like in game, along with how long every chunk
+/// took to generate.
+fn generate_world(
+    chunk_size: ChunkSize,
+    chunks: impl IntoIterator<Item = chunk::Coordinates>,
+    generator: &TerrainGeneratorKind,
+    materials: &MaterialRegistry,
+) -> (World, Vec<Duration>) {
+    let mut world = World::new(chunk_size);
+    let chunks: Vec<chunk::Coordinates> = chunks.into_iter().collect();
+    let mut durations = Vec::with_capacity(chunks.len());
+    for coordinates in chunks.iter() {
+        world.spawn_chunk(Entity::PLACEHOLDER, *coordinates);
+        let chunk = world.get_chunk(*coordinates).unwrap();
+        let mut chunk = chunk.write();
+        let timer = Instant::now();
+        chunk.terrain = Some(generate_terrain(
+            *coordinates,
+            chunk_size,
+            generator,
+            materials,
+        ));
+        durations.push(timer.elapsed());
+        chunk.state = chunk::State::Generated;
+    }
+    for coordinates in chunks {
+        let neighbourhood = world.get_chunk_neighbourhood(coordinates).unwrap();
+        neighbourhood.lock(materials).spread_across_borders();
+    }
+    (world, durations)
+}
@@ -0,0 +1,142 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    math::{IVec2, IVec3, UVec2, Vec3Swizzles},
+    render::{
+        color::Color,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::Image,
+    },
+};
+
+use voxel::world::{
+    chunk::{self, tasks::generate_terrain},
+    voxel::{material::Material, shape::Volume},
+};
+
+use super::{load_materials, parse_chunk_size, parse_generator, parse_ivec2};
+
+/// Top-down view of a generated voxel column.
+#[derive(Clone, Copy, Default)]
+struct Column {
+    /// Height of the top of the highest voxel.
+    height: u32,
+    material: Option<Material>,
+}
+
+/// Generate chunks without rendering them and write top-down maps of their heights and surface materials, along with
+/// how long every chunk took to generate.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let generator = parse_generator(&mut args)?;
+    let chunk_size = parse_chunk_size(&mut args)?;
+    let [first, second, output] = &args[..] else { return Err("invalid worldgen arguments".to_string()) };
+    let (first, second) = (parse_ivec2(first)?, parse_ivec2(second)?);
+    let (min, max) = (first.min(second), first.max(second));
+    let output = PathBuf::from(output);
+    std::fs::create_dir_all(&output).map_err(|error| format!("{}: {error}", output.display()))?;
+
+    let materials = load_materials()?;
+    let chunks = (max - min + IVec2::ONE).as_uvec2();
+    let size = chunks * chunk_size.xz();
+    let mut columns = vec![Column::default(); (size.x * size.y) as usize];
+    let mut timings = "x,y,z,microseconds\n".to_string();
+    let mut durations = vec![];
+    for z in min.y..=max.y {
+        for x in min.x..=max.x {
+            // Layers are generated from the top, the first voxel found being the surface
+            let offset = (IVec2::new(x, z) - min).as_uvec2() * chunk_size.xz();
+            for y in (0..chunk_size.layers()).rev() {
+                let coordinates = chunk::Coordinates(IVec3::new(x, y, z));
+                let timer = Instant::now();
+                let terrain = generate_terrain(coordinates, chunk_size, &generator, &materials);
+                let duration = timer.elapsed();
+                durations.push(duration);
+                writeln!(timings, "{x},{y},{z},{}", duration.as_micros()).unwrap();
+
+                let origin = chunk_size.origin(coordinates);
+                for column_z in 0..chunk_size.z {
+                    for column_x in 0..chunk_size.x {
+                        let position = offset + UVec2::new(column_x, column_z);
+                        let column = &mut columns[(position.y * size.x + position.x) as usize];
+                        if column.material.is_some() {
+                            continue;
+                        }
+                        let surface = (0..chunk_size.y).rev().find_map(|y| {
+                            let position = IVec3::new(column_x as i32, y as i32, column_z as i32);
+                            let voxel = terrain.voxel_at_pos(position).as_ref()?;
+                            (voxel.shape.volume != Volume::ZeroSixth).then_some((y, voxel.material))
+                        });
+                        let Some((y, material)) = surface else { continue };
+                        *column = Column {
+                            height: (origin.y + y as i32 + 1) as u32,
+                            material: Some(material),
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    let sky_height = chunk_size.sky_height() as u32;
+    let heights = columns.iter().flat_map(|column| {
+        let value = (column.height * u8::MAX as u32 / sky_height) as u8;
+        [value, value, value, u8::MAX]
+    });
+    write_png(&output.join("height.png"), size, heights.collect())?;
+    let surface = columns.iter().flat_map(|column| match column.material {
+        Some(material) => material_color(material, column.height, sky_height).as_rgba_u8(),
+        None => [0, 0, 0, u8::MAX],
+    });
+    write_png(&output.join("materials.png"), size, surface.collect())?;
+    let timings_path = output.join("timings.csv");
+    std::fs::write(&timings_path, timings)
+        .map_err(|error| format!("{}: {error}", timings_path.display()))?;
+
+    println!(
+        "Generated {} chunks to {}",
+        durations.len(),
+        output.display()
+    );
+    for (material, properties) in materials.iter() {
+        let [r, g, b, _] = material_color(material, sky_height / 2, sky_height).as_rgba_u8();
+        println!("    {} #{r:02x}{g:02x}{b:02x}", properties.name);
+    }
+    let total: Duration = durations.iter().sum();
+    println!(
+        "Generation took {total:?}, {:?} per chunk on average, {:?} at most",
+        total / durations.len() as u32,
+        durations.iter().max().unwrap(),
+    );
+    Ok(())
+}
+
+/// Distinct color of every material on the map, the registry colors being mostly white tints of their textures.
+/// Higher surfaces are lighter, up to `sky_height`.
+fn material_color(material: Material, height: u32, sky_height: u32) -> Color {
+    let hue = (100.0 + material.id as f32 * 137.5) % 360.0;
+    Color::hsl(hue, 0.6, 0.25 + 0.5 * height as f32 / sky_height as f32)
+}
+
+fn write_png(path: &Path, size: UVec2, rgba: Vec<u8>) -> Result<(), String> {
+    let image = Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        rgba,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    let image = image
+        .try_into_dynamic()
+        .map_err(|error| error.to_string())?;
+    image
+        .save(path)
+        .map_err(|error| format!("{}: {error}", path.display()))
+}
@@ -2,16 +2,35 @@ use std::time::Duration;
 
 use bevy::{pbr::ExtendedMaterial, prelude::*, render};
 
-use crate::world::chunk::material::{
-    StandardMaterialExtension, TerrainMaterial, ATTRIBUTE_VOXEL_ID,
+use crate::world::{
+    chunk::{
+        generator::{shape_showcase::ShapeShowcaseGenerator, TerrainGeneratorKind},
+        material::{StandardMaterialExtension, TerrainDaylight, TerrainMaterial},
+        mesh::{
+            occlusion::AmbientOcclusion,
+            voxel::{SideDescriptor, SIDES},
+            ChunkMesh,
+        },
+    },
+    voxel::{
+        material::{registry::MaterialRegistry, Material},
+        shape::Shape,
+    },
 };
 
+/// Labels further away from the camera than this are hidden to keep the showcase readable.
+const SHOWCASE_LABEL_DISTANCE: f32 = 12.0;
+
 #[derive(Component)]
 pub struct DebugComponent;
 
+#[derive(Component)]
+struct ShowcaseLabel(Vec3);
+
 #[derive(Default)]
 pub struct DebugPluginBuilder {
     debug_playground: bool,
+    shape_showcase: bool,
     adhd_autoclose: Option<Duration>,
 }
 
@@ -23,6 +42,7 @@ impl DebugPluginBuilder {
     pub fn build(self) -> DebugPlugin {
         DebugPlugin {
             debug_playground: self.debug_playground,
+            shape_showcase: self.shape_showcase,
             adhd_autoclose: self.adhd_autoclose,
         }
     }
@@ -33,6 +53,13 @@ impl DebugPluginBuilder {
         self
     }
 
+    /// Replaces the terrain with the shape showcase world, every shape and rotation being labelled.
+    #[allow(dead_code)]
+    pub fn shape_showcase(mut self) -> Self {
+        self.shape_showcase = true;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_adhd_autoclose(mut self, duration: Duration) -> Self {
         self.adhd_autoclose = Some(duration);
@@ -45,13 +72,22 @@ struct AutoClose(Timer);
 
 pub struct DebugPlugin {
     debug_playground: bool,
+    shape_showcase: bool,
     adhd_autoclose: Option<Duration>,
 }
 
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
         if self.debug_playground {
-            app.add_systems(Startup, Self::debug_playground);
+            app.add_systems(
+                Update,
+                Self::debug_playground.run_if(resource_added::<MaterialRegistry>()),
+            );
+        }
+        if self.shape_showcase {
+            app.insert_resource(TerrainGeneratorKind::ShapeShowcase)
+                .add_systems(Startup, Self::spawn_showcase_labels)
+                .add_systems(Update, Self::update_showcase_labels);
         }
         if let Some(duration) = self.adhd_autoclose {
             app.insert_resource(AutoClose(Timer::new(duration, TimerMode::Once)))
@@ -66,6 +102,8 @@ impl DebugPlugin {
         mut meshes: ResMut<Assets<render::mesh::Mesh>>,
         mut standard_material: ResMut<Assets<StandardMaterial>>,
         mut terrain_material: ResMut<Assets<TerrainMaterial>>,
+        registry: Res<MaterialRegistry>,
+        daylight: Res<TerrainDaylight>,
     ) {
         Self::spawn_sphere(
             &mut commands,
@@ -74,20 +112,25 @@ impl DebugPlugin {
             UVec3 { x: 0, y: 0, z: 0 },
         );
 
-        let mesh = Mesh::from(shape::Cube { size: 1.0 }).with_inserted_attribute(
-            ATTRIBUTE_VOXEL_ID,
-            vec![
-                1_u32, 1_u32, 1_u32, 1_u32, 2_u32, 2_u32, 2_u32, 2_u32, 3_u32, 3_u32, 3_u32, 3_u32,
-                3_u32, 3_u32, 3_u32, 3_u32, 2_u32, 2_u32, 2_u32, 2_u32, 1_u32, 1_u32, 1_u32, 1_u32,
-            ],
-        );
+        // A full voxel, every pair of opposite sides having its own material
+        let mut chunk_mesh = ChunkMesh::new(registry.clone(), AmbientOcclusion::default());
+        for (i, side) in SIDES.into_iter().enumerate() {
+            SideDescriptor::from_shape_descriptor(&Shape::FULL.into(), side).mesh_side(
+                &mut chunk_mesh,
+                UVec3::ZERO,
+                &Material {
+                    id: i as u32 / 2 + 1,
+                },
+            );
+        }
+        let (mesh, _, _) = chunk_mesh.mesh();
 
         commands.spawn(MaterialMeshBundle {
             mesh: meshes.add(mesh),
-            transform: Transform::from_xyz(0.0, 0.5, 0.0),
+            transform: Transform::from_xyz(-0.5, 0.0, -0.5),
             material: terrain_material.add(ExtendedMaterial {
                 base: StandardMaterial::default(),
-                extension: StandardMaterialExtension {},
+                extension: StandardMaterialExtension::new(&registry, &daylight),
             }),
             ..default()
         });
@@ -118,6 +161,54 @@ impl DebugPlugin {
             .insert(DebugComponent);
     }
 
+    fn spawn_showcase_labels(mut commands: Commands) {
+        for label in ShapeShowcaseGenerator::labels() {
+            commands.spawn((
+                ShowcaseLabel(label.position),
+                TextBundle {
+                    text: Text::from_section(
+                        label.text,
+                        TextStyle {
+                            font_size: 14.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+            ));
+        }
+    }
+
+    fn update_showcase_labels(
+        camera: Query<(&Camera, &GlobalTransform)>,
+        mut labels: Query<(&ShowcaseLabel, &mut Style, &mut Visibility)>,
+    ) {
+        let Ok((camera, camera_transform)) = camera.get_single() else { return };
+
+        for (label, mut style, mut visibility) in labels.iter_mut() {
+            let viewport_position =
+                if camera_transform.translation().distance(label.0) < SHOWCASE_LABEL_DISTANCE {
+                    camera.world_to_viewport(camera_transform, label.0)
+                } else {
+                    None
+                };
+
+            if let Some(viewport_position) = viewport_position {
+                style.left = Val::Px(viewport_position.x);
+                style.top = Val::Px(viewport_position.y);
+                *visibility = Visibility::Inherited;
+            } else {
+                *visibility = Visibility::Hidden;
+            }
+        }
+    }
+
     fn auto_close(mut timer: ResMut<AutoClose>, time: Res<Time>) {
         timer.0.tick(time.delta());
 
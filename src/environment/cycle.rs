@@ -0,0 +1,142 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    prelude::{Color, Event, FromWorld, ReflectResource, Resource, Vec3, World},
+    reflect::Reflect,
+};
+
+/// Sun elevation (sine of its angle above the horizon) over which the sky goes from night to day.
+const TWILIGHT: f32 = 0.2;
+
+/// How the sky moves above the world. Insert it before `EnvironmentPlugin` to override the defaults, changes made while
+/// running are applied right away.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct EnvironmentSettings {
+    /// Real time seconds a full day lasts.
+    pub day_length: f32,
+    /// Latitude of the world in degrees, 0.0 being the equator. Moves the sun path away from the zenith.
+    pub latitude: f32,
+    /// Axial tilt in degrees. Seasons aren't simulated, days are always as long as on the summer solstice.
+    pub axial_tilt: f32,
+    /// Time of day the game starts at, see `DaylightCycle::time_of_day`.
+    pub start_time: f32,
+    /// A moon rising opposite to the sun, lighting the night.
+    pub moon: Option<MoonSettings>,
+}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            day_length: 600.0,
+            latitude: 45.0,
+            axial_tilt: 23.44,
+            start_time: 0.25,
+            moon: Some(MoonSettings::default()),
+        }
+    }
+}
+
+#[derive(Reflect, Clone, Debug)]
+pub struct MoonSettings {
+    pub illuminance: f32,
+    pub color: Color,
+}
+
+impl Default for MoonSettings {
+    fn default() -> Self {
+        Self {
+            illuminance: 2_000.0,
+            color: Color::rgb(0.6, 0.7, 1.0),
+        }
+    }
+}
+
+impl EnvironmentSettings {
+    /// Direction pointing towards the sun, +x being west and +z north.
+    pub fn sun_direction(&self, time_of_day: f32) -> Vec3 {
+        let hour_angle = (time_of_day - 0.5) * TAU;
+        let latitude = self.latitude.to_radians();
+        let declination = self.axial_tilt.to_radians();
+
+        let east = -declination.cos() * hour_angle.sin();
+        let up = latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos();
+        let north = latitude.cos() * declination.sin()
+            - latitude.sin() * declination.cos() * hour_angle.cos();
+        Vec3::new(-east, up, north).normalize()
+    }
+
+    pub fn moon_direction(&self, time_of_day: f32) -> Vec3 {
+        -self.sun_direction(time_of_day)
+    }
+
+    /// Daylight value between 0.0 and 1.0 (0.0 = night, 1.0 = day), fading during twilight.
+    pub fn daylight(&self, time_of_day: f32) -> f32 {
+        let elevation = self.sun_direction(time_of_day).y;
+        let t = ((elevation + TWILIGHT) / (2.0 * TWILIGHT)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// Current time of day, the time source everything following the sun reads from.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct DaylightCycle {
+    /// Fraction of the day elapsed, 0.0 being midnight and 0.5 noon.
+    time_of_day: f32,
+    paused: bool,
+}
+
+/// Starts at `EnvironmentSettings::start_time`.
+impl FromWorld for DaylightCycle {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource_or_insert_with(EnvironmentSettings::default);
+        Self::new(settings.start_time)
+    }
+}
+
+impl DaylightCycle {
+    pub fn new(time_of_day: f32) -> Self {
+        Self {
+            time_of_day: time_of_day.rem_euclid(1.0),
+            paused: false,
+        }
+    }
+
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    /// Jump to a time of day, wrapping around midnight.
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Move the time of day forward by `seconds` of real time, unless paused.
+    pub fn advance(&mut self, seconds: f32, day_length: f32) {
+        if !self.paused {
+            self.time_of_day =
+                (self.time_of_day + seconds / day_length.max(f32::EPSILON)).rem_euclid(1.0);
+        }
+    }
+}
+
+/// Sent when the sun crosses the horizon.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DaylightEvent {
+    Dawn,
+    Dusk,
+}
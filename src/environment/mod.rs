@@ -1,7 +1,3 @@
-use std::f32::consts::PI;
-
-#[cfg(feature = "atmosphere")]
-use bevy::prelude::Vec3;
 #[cfg(feature = "atmosphere")]
 use bevy_atmosphere::{
     prelude::{AtmosphereModel, AtmospherePlugin, Nishita},
@@ -15,72 +11,125 @@ use bevy::{
 };
 
 use bevy::{
+    pbr::CascadeShadowConfigBuilder,
     prelude::{
-        default, AmbientLight, Color, Commands, Component, DirectionalLight,
-        DirectionalLightBundle, Plugin, PointLight, PointLightBundle, Quat, Query, ReflectResource,
-        Res, ResMut, Resource, Startup, Transform, Update, With, Without,
+        default, resource_added, resource_exists, AmbientLight, Color, Commands, Component,
+        Condition, DetectChanges, DirectionalLight, DirectionalLightBundle, EventWriter,
+        IntoSystemConfigs, Local, Plugin, PointLight, PointLightBundle, Quat, Query, Res, ResMut,
+        Startup, Transform, Update, Vec3, With, Without,
     },
-    reflect::Reflect,
-    time::{Time, Timer, TimerMode},
+    render::renderer::RenderQueue,
+    time::Time,
 };
 use bevy_spectator::Spectator;
 
+use self::{
+    cycle::{DaylightCycle, DaylightEvent, EnvironmentSettings},
+    weather::{Weather, WeatherPlugin},
+};
+use crate::{debug::app::DebugApp, world::chunk::material::TerrainDaylight};
+
+pub mod cycle;
+pub mod weather;
+
 pub struct EnvironmentPlugin;
 
 #[derive(Component)]
 pub struct Sun;
 
-#[derive(Resource)]
-struct CycleTimer(Timer);
-
-/// Daylight value between 0.0 and 1.0 (0.0 = night, 1.0 = day)
-#[derive(Resource, Reflect, Default)]
-#[reflect(Resource)]
-struct DaylightCycle(f32);
+#[derive(Component)]
+pub struct Moon;
 
 impl Plugin for EnvironmentPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         #[cfg(feature = "atmosphere")]
         {
             app.insert_resource(AtmosphereModel::default());
-            app.add_plugins(AtmospherePlugin)
-                .add_systems(Update, Self::update_atmosphere);
+            app.add_plugins(AtmospherePlugin).add_systems(
+                Update,
+                Self::update_atmosphere
+                    .after(Self::daylight_cycle)
+                    .run_if(Self::environment_changed),
+            );
         }
         #[cfg(not(feature = "atmosphere"))]
-        app.add_systems(Update, Self::update_clear_color);
+        app.add_systems(
+            Update,
+            Self::update_clear_color
+                .after(Self::daylight_cycle)
+                .run_if(Self::environment_changed),
+        );
 
         app.add_systems(Startup, Self::setup_player_light)
             .add_systems(Update, Self::update_player_light);
 
-        app.insert_resource(CycleTimer(Timer::new(
-            bevy::utils::Duration::from_millis(16), // Update our atmosphere every 50ms (in a real game, this would be much slower, but for the sake of an example we use a faster update)
-            TimerMode::Repeating,
-        )))
-        .init_resource::<DaylightCycle>()
-        .add_systems(Startup, Self::setup_environment)
-        .add_systems(Update, (Self::daylight_cycle, Self::update_lights));
+        app.add_plugins(WeatherPlugin)
+            .init_resource::<EnvironmentSettings>()
+            .init_resource::<DaylightCycle>()
+            .add_event::<DaylightEvent>()
+            .debug_resource::<EnvironmentSettings>()
+            .debug_resource::<DaylightCycle>()
+            .add_systems(Startup, Self::setup_environment)
+            .add_systems(
+                Update,
+                (
+                    Self::daylight_cycle,
+                    (Self::update_lights, Self::send_daylight_events)
+                        .run_if(Self::environment_changed),
+                    Self::update_terrain_daylight.run_if(
+                        resource_exists::<TerrainDaylight>().and_then(
+                            Self::environment_changed.or_else(resource_added::<TerrainDaylight>()),
+                        ),
+                    ),
+                )
+                    .chain(),
+            );
     }
 }
 
 impl EnvironmentPlugin {
-    fn setup_environment(mut commands: Commands) {
+    fn environment_changed(
+        daylight: Res<DaylightCycle>,
+        settings: Res<EnvironmentSettings>,
+        weather: Res<Weather>,
+    ) -> bool {
+        daylight.is_changed() || settings.is_changed() || weather.is_changed()
+    }
+
+    fn setup_environment(mut commands: Commands, settings: Res<EnvironmentSettings>) {
         commands.spawn((
             Sun,
             DirectionalLightBundle {
                 directional_light: DirectionalLight {
                     illuminance: 10_000.0,
-                    // shadows_enabled: true,
+                    shadows_enabled: true,
                     ..default()
                 },
-                transform: Transform::from_rotation(Quat::from_euler(
-                    bevy::prelude::EulerRot::ZYX,
-                    0.0,
-                    PI * -0.15,
-                    PI * -0.15,
-                )),
+                // Tight first cascade for the terrain around the player, the last ones cover a few chunks away
+                cascade_shadow_config: CascadeShadowConfigBuilder {
+                    num_cascades: 4,
+                    minimum_distance: 0.1,
+                    first_cascade_far_bound: 16.0,
+                    maximum_distance: 256.0,
+                    overlap_proportion: 0.2,
+                }
+                .into(),
                 ..default()
             },
         ));
+        if let Some(moon) = &settings.moon {
+            commands.spawn((
+                Moon,
+                DirectionalLightBundle {
+                    directional_light: DirectionalLight {
+                        color: moon.color,
+                        illuminance: 0.0,
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        }
         commands.insert_resource(AmbientLight {
             color: Color::rgb(1.0, 1.0, 1.0),
             brightness: 1.0,
@@ -108,59 +157,97 @@ impl EnvironmentPlugin {
     }
 
     fn daylight_cycle(
-        mut timer: ResMut<CycleTimer>,
         time: Res<Time>,
+        settings: Res<EnvironmentSettings>,
         mut daylight: ResMut<DaylightCycle>,
     ) {
-        timer.0.tick(time.delta());
+        // Don't flag the cycle as changed while paused
+        if !daylight.is_paused() {
+            daylight.advance(time.delta_seconds(), settings.day_length);
+        }
+    }
 
-        if timer.0.finished() {
-            let t = time.elapsed_seconds_wrapped() / 100.0;
-            daylight.0 = (t.sin() + 1.0) / 2.0;
+    fn send_daylight_events(
+        daylight: Res<DaylightCycle>,
+        settings: Res<EnvironmentSettings>,
+        mut sun_was_up: Local<Option<bool>>,
+        mut events: EventWriter<DaylightEvent>,
+    ) {
+        let sun_is_up = settings.sun_direction(daylight.time_of_day()).y > 0.0;
+        match *sun_was_up {
+            Some(false) if sun_is_up => events.send(DaylightEvent::Dawn),
+            Some(true) if !sun_is_up => events.send(DaylightEvent::Dusk),
+            _ => {}
         }
+        *sun_was_up = Some(sun_is_up);
     }
 
     #[cfg(not(feature = "atmosphere"))]
     fn update_clear_color(
         daylight: Res<DaylightCycle>,
+        settings: Res<EnvironmentSettings>,
+        weather: Res<Weather>,
         mut camera_3d: Query<(&mut Camera3d,)>,
         mut fog: Query<(&mut FogSettings,)>,
     ) {
-        camera_3d.single_mut().0.clear_color = ClearColorConfig::Custom(Color::rgb(
-            0.7 * daylight.0,
-            0.8 * daylight.0,
-            1.0 * daylight.0,
-        ));
-        fog.single_mut().0.color = Color::rgb(0.7 * daylight.0, 0.8 * daylight.0, 1.0 * daylight.0);
+        let daylight = settings.daylight(daylight.time_of_day());
+        let sky_color =
+            weather
+                .effects()
+                .sky_color(Color::rgb(0.7 * daylight, 0.8 * daylight, 1.0 * daylight));
+        camera_3d.single_mut().0.clear_color = ClearColorConfig::Custom(sky_color);
+        fog.single_mut().0.color = sky_color;
     }
 
     #[cfg(feature = "atmosphere")]
     fn update_atmosphere(
         mut atmosphere: AtmosphereMut<Nishita>,
-        timer: Res<CycleTimer>,
-        time: Res<Time>,
+        daylight: Res<DaylightCycle>,
+        settings: Res<EnvironmentSettings>,
     ) {
-        if timer.0.finished() {
-            let t = time.elapsed_seconds_wrapped() as f32 / 100.0;
-            atmosphere.sun_position = Vec3::new(0., t.sin(), t.cos());
-        }
+        atmosphere.sun_position = settings.sun_direction(daylight.time_of_day());
     }
 
     fn update_lights(
-        mut query: Query<(&mut Transform, &mut DirectionalLight), With<Sun>>,
-        timer: Res<CycleTimer>,
+        mut sun: Query<(&mut Transform, &mut DirectionalLight), (With<Sun>, Without<Moon>)>,
+        mut moon: Query<(&mut Transform, &mut DirectionalLight), (With<Moon>, Without<Sun>)>,
         daylight: Res<DaylightCycle>,
+        settings: Res<EnvironmentSettings>,
+        weather: Res<Weather>,
         mut ambient_light: ResMut<AmbientLight>,
-        time: Res<Time>,
     ) {
-        if timer.0.finished() {
-            let t = time.elapsed_seconds_wrapped() / 100.0;
-
-            if let Some((mut light_trans, mut directional)) = query.single_mut().into() {
-                light_trans.rotation = Quat::from_rotation_x(-t.sin().atan2(t.cos()));
-                directional.illuminance = interpolation::lerp(&0.0, &100_000.0, &daylight.0);
-            }
-            ambient_light.brightness = interpolation::lerp(&0.1, &1.0, &daylight.0);
+        let time_of_day = daylight.time_of_day();
+        let daylight = settings.daylight(time_of_day);
+        let weather = weather.effects();
+
+        // Directional lights shine towards their forward direction
+        if let Ok((mut light_trans, mut directional)) = sun.get_single_mut() {
+            light_trans.rotation =
+                Quat::from_rotation_arc(Vec3::NEG_Z, -settings.sun_direction(time_of_day));
+            directional.illuminance =
+                interpolation::lerp(&0.0, &100_000.0, &daylight) * weather.sunlight();
+        }
+        if let (Ok((mut light_trans, mut directional)), Some(moon_settings)) =
+            (moon.get_single_mut(), &settings.moon)
+        {
+            let moon_direction = settings.moon_direction(time_of_day);
+            light_trans.rotation = Quat::from_rotation_arc(Vec3::NEG_Z, -moon_direction);
+            directional.color = moon_settings.color;
+            directional.illuminance = moon_settings.illuminance
+                * (1.0 - daylight)
+                * moon_direction.y.max(0.0)
+                * weather.sunlight();
         }
+        ambient_light.brightness = interpolation::lerp(&0.1, &1.0, &daylight) * weather.ambient();
+    }
+
+    /// Baked sky light is scaled by daylight in the terrain shader.
+    fn update_terrain_daylight(
+        daylight: Res<DaylightCycle>,
+        settings: Res<EnvironmentSettings>,
+        mut terrain_daylight: ResMut<TerrainDaylight>,
+        render_queue: Res<RenderQueue>,
+    ) {
+        terrain_daylight.set(settings.daylight(daylight.time_of_day()), &render_queue);
     }
 }
@@ -0,0 +1,473 @@
+use bevy::{
+    asset::Handle,
+    pbr::NotShadowCaster,
+    prelude::{
+        default, resource_exists, shape, AlphaMode, App, Assets, Color, Commands, Component, IVec3,
+        IntoSystemConfigs, Local, Mesh, PbrBundle, Plugin, Query, ReflectResource, Res, ResMut,
+        Resource, StandardMaterial, Startup, Transform, Update, Vec3, Visibility, With, Without,
+    },
+    reflect::Reflect,
+    time::Time,
+};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+
+use crate::{
+    debug::app::DebugApp,
+    player::Player,
+    world::{
+        biome::Biome,
+        edit::WorldEdit,
+        light::blocks_light,
+        voxel::{
+            material::registry::MaterialRegistry,
+            shape::{Rotation, Shape, Volume},
+            VoxelDescriptor,
+        },
+        World,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Overcast,
+    Rain,
+    Snow,
+    Fog,
+}
+
+impl WeatherKind {
+    const ALL: [Self; 5] = [
+        Self::Clear,
+        Self::Overcast,
+        Self::Rain,
+        Self::Snow,
+        Self::Fog,
+    ];
+
+    /// Relative chance of the weather being picked in a biome, 0.0 meaning it never happens there.
+    pub fn likelihood(&self, biome: Biome) -> f32 {
+        match (biome, self) {
+            (Biome::Temperate, Self::Clear) => 0.4,
+            (Biome::Temperate, Self::Overcast) => 0.3,
+            (Biome::Temperate, Self::Rain) => 0.2,
+            (Biome::Temperate, Self::Fog) => 0.1,
+            (Biome::Desert, Self::Clear) => 0.8,
+            (Biome::Desert, Self::Overcast) => 0.15,
+            (Biome::Desert, Self::Fog) => 0.05,
+            (Biome::Tundra, Self::Clear) => 0.3,
+            (Biome::Tundra, Self::Overcast) => 0.3,
+            (Biome::Tundra, Self::Snow) => 0.35,
+            (Biome::Tundra, Self::Fog) => 0.05,
+            (Biome::Wetlands, Self::Clear) => 0.1,
+            (Biome::Wetlands, Self::Overcast) => 0.3,
+            (Biome::Wetlands, Self::Rain) => 0.3,
+            (Biome::Wetlands, Self::Fog) => 0.3,
+            _ => 0.0,
+        }
+    }
+
+    pub fn is_precipitation(&self) -> bool {
+        matches!(self, Self::Rain | Self::Snow)
+    }
+
+    fn effects(&self) -> WeatherEffects {
+        let (visibility, cloudiness) = match self {
+            Self::Clear => (1.0, 0.0),
+            Self::Overcast => (0.85, 0.6),
+            Self::Rain => (0.6, 0.8),
+            Self::Snow => (0.5, 0.7),
+            Self::Fog => (0.25, 0.5),
+        };
+        WeatherEffects {
+            visibility,
+            cloudiness,
+            precipitation: if self.is_precipitation() { 1.0 } else { 0.0 },
+        }
+    }
+}
+
+/// How the weather changes the scene, blended during transitions.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub struct WeatherEffects {
+    /// Multiplier of the fog distances, lower is thicker.
+    pub visibility: f32,
+    /// Dims the sun and ambient light and greys the sky, between 0.0 and 1.0.
+    pub cloudiness: f32,
+    /// Fraction of the particles falling, between 0.0 and 1.0.
+    pub precipitation: f32,
+}
+
+impl WeatherEffects {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            visibility: interpolation::lerp(&self.visibility, &other.visibility, &t),
+            cloudiness: interpolation::lerp(&self.cloudiness, &other.cloudiness, &t),
+            precipitation: interpolation::lerp(&self.precipitation, &other.precipitation, &t),
+        }
+    }
+
+    /// Multiplier of the sun and moon illuminance.
+    pub fn sunlight(&self) -> f32 {
+        1.0 - 0.8 * self.cloudiness
+    }
+
+    /// Multiplier of the ambient light, overcast skies stay brighter than direct sunlight.
+    pub fn ambient(&self) -> f32 {
+        1.0 - 0.4 * self.cloudiness
+    }
+
+    /// Grey the sky of a clear day depending on cloudiness.
+    pub fn sky_color(&self, clear_sky: Color) -> Color {
+        let [r, g, b, a] = clear_sky.as_rgba_f32();
+        let grey = (r + g + b) / 3.0;
+        let mix = |channel: f32| interpolation::lerp(&channel, &grey, &self.cloudiness);
+        Color::rgba(mix(r), mix(g), mix(b), a)
+    }
+}
+
+/// Current weather, changed every few minutes to one suiting the biome the player is in.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct Weather {
+    current: WeatherKind,
+    previous: WeatherKind,
+    /// Effects when the current weather started, blended towards the ones of the current weather.
+    from: WeatherEffects,
+    /// Transition progress from the previous weather, between 0.0 and 1.0.
+    transition: f32,
+    /// Seconds before the next weather is picked.
+    remaining: f32,
+    /// Biome the player was last seen in.
+    biome: Biome,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self {
+            current: WeatherKind::Clear,
+            previous: WeatherKind::Clear,
+            from: WeatherKind::Clear.effects(),
+            transition: 1.0,
+            remaining: WeatherSettings::default().min_duration,
+            biome: Biome::Temperate,
+        }
+    }
+}
+
+impl Weather {
+    pub fn current(&self) -> WeatherKind {
+        self.current
+    }
+
+    pub fn biome(&self) -> Biome {
+        self.biome
+    }
+
+    /// Switch to another weather lasting `duration` seconds, transitioning from the current one.
+    pub fn set(&mut self, kind: WeatherKind, duration: f32) {
+        if kind != self.current {
+            self.from = self.effects();
+            self.previous = self.current;
+            self.current = kind;
+            self.transition = 0.0;
+        }
+        self.remaining = duration;
+    }
+
+    pub fn effects(&self) -> WeatherEffects {
+        self.from.lerp(&self.current.effects(), self.transition)
+    }
+
+    /// Rain or snow currently falling, including while fading out.
+    pub fn precipitation(&self) -> Option<WeatherKind> {
+        [self.current, self.previous]
+            .into_iter()
+            .find(WeatherKind::is_precipitation)
+    }
+
+    fn advance(&mut self, seconds: f32, transition_duration: f32) {
+        self.transition =
+            (self.transition + seconds / transition_duration.max(f32::EPSILON)).min(1.0);
+        self.remaining -= seconds;
+    }
+}
+
+/// Insert it before `EnvironmentPlugin` to override the defaults.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct WeatherSettings {
+    /// Range of seconds a weather lasts.
+    pub min_duration: f32,
+    pub max_duration: f32,
+    /// Seconds it takes to blend from a weather to the next one.
+    pub transition_duration: f32,
+    /// Rain drops and snowflakes around the player, only read at startup.
+    pub particle_count: usize,
+    /// Horizontal distance from the player particles fall within.
+    pub particle_radius: f32,
+    /// Let snow pile up on the terrain while snowing. Snow voxels are regular voxels, they stay once it stopped snowing.
+    pub snow_accumulation: bool,
+    /// Snow voxels placed per second when it snows at full intensity.
+    pub snow_accumulation_rate: f32,
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        Self {
+            min_duration: 120.0,
+            max_duration: 480.0,
+            transition_duration: 20.0,
+            particle_count: 2_000,
+            particle_radius: 24.0,
+            snow_accumulation: false,
+            snow_accumulation_rate: 4.0,
+        }
+    }
+}
+
+#[derive(Component, Default)]
+struct Particle {
+    /// Precipitation the particle belongs to, `None` while hidden.
+    kind: Option<WeatherKind>,
+    velocity: Vec3,
+    /// Offset of the snowflakes drifting sideways.
+    phase: f32,
+}
+
+#[derive(Resource)]
+struct ParticleAssets {
+    rain: (Handle<Mesh>, Handle<StandardMaterial>),
+    snow: (Handle<Mesh>, Handle<StandardMaterial>),
+}
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeatherSettings>()
+            .init_resource::<Weather>()
+            .debug_resource::<WeatherSettings>()
+            .debug_resource::<Weather>()
+            .add_systems(Startup, Self::setup_particles)
+            .add_systems(
+                Update,
+                (
+                    Self::update_weather,
+                    Self::update_particles,
+                    Self::accumulate_snow.run_if(resource_exists::<MaterialRegistry>()),
+                )
+                    .chain(),
+            );
+    }
+}
+
+impl WeatherPlugin {
+    fn setup_particles(
+        mut commands: Commands,
+        settings: Res<WeatherSettings>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        let assets = ParticleAssets {
+            rain: (
+                meshes.add(shape::Box::new(0.02, 0.6, 0.02).into()),
+                materials.add(StandardMaterial {
+                    base_color: Color::rgba(0.7, 0.75, 0.85, 0.5),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                }),
+            ),
+            snow: (
+                meshes.add(shape::Cube::new(0.08).into()),
+                materials.add(StandardMaterial {
+                    base_color: Color::WHITE,
+                    unlit: true,
+                    ..default()
+                }),
+            ),
+        };
+
+        for _ in 0..settings.particle_count {
+            commands.spawn((
+                Particle::default(),
+                PbrBundle {
+                    mesh: assets.rain.0.clone(),
+                    material: assets.rain.1.clone(),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                NotShadowCaster,
+            ));
+        }
+        commands.insert_resource(assets);
+    }
+
+    fn update_weather(
+        time: Res<Time>,
+        settings: Res<WeatherSettings>,
+        mut weather: ResMut<Weather>,
+        player: Query<&Transform, With<Player>>,
+    ) {
+        weather.advance(time.delta_seconds(), settings.transition_duration);
+        if let Ok(player) = player.get_single() {
+            weather.biome = Biome::at(player.translation);
+        }
+
+        // Weathers that can't happen in the biome the player walked into end right away
+        let biome = weather.biome;
+        if weather.remaining > 0.0 && weather.current.likelihood(biome) > 0.0 {
+            return;
+        }
+        let weights = WeatherKind::ALL.map(|kind| kind.likelihood(biome));
+        let Ok(distribution) = WeightedIndex::new(weights) else { return };
+        let mut rng = rand::thread_rng();
+        let kind = WeatherKind::ALL[distribution.sample(&mut rng)];
+        let duration =
+            rng.gen_range(settings.min_duration..=settings.max_duration.max(settings.min_duration));
+        weather.set(kind, duration);
+    }
+
+    /// Rain and snow fall in a cylinder around the player. Particles going out of it or hitting the terrain are moved
+    /// back above the player.
+    fn update_particles(
+        time: Res<Time>,
+        weather: Res<Weather>,
+        settings: Res<WeatherSettings>,
+        assets: Option<Res<ParticleAssets>>,
+        world: Res<World>,
+        player: Query<&Transform, (With<Player>, Without<Particle>)>,
+        mut particles: Query<(
+            &mut Particle,
+            &mut Transform,
+            &mut Visibility,
+            &mut Handle<Mesh>,
+            &mut Handle<StandardMaterial>,
+        )>,
+    ) {
+        let Some(assets) = assets else { return };
+        let Ok(player) = player.get_single() else { return };
+        let center = player.translation;
+        let radius = settings.particle_radius;
+        let active = (particles.iter().len() as f32 * weather.effects().precipitation) as usize;
+        let falling = weather.precipitation();
+        let mut rng = rand::thread_rng();
+
+        for (i, (mut particle, mut transform, mut visibility, mut mesh, mut material)) in
+            particles.iter_mut().enumerate()
+        {
+            let Some(kind) = falling.filter(|_| i < active) else {
+                particle.kind = None;
+                *visibility = Visibility::Hidden;
+                continue;
+            };
+
+            let position = &mut transform.translation;
+            let offset = *position - center;
+            let landed = world
+                .get_voxel(position.floor().as_ivec3())
+                .is_some_and(|voxel| voxel.shape.volume != Volume::ZeroSixth);
+            let out_of_range = offset.y < -radius || offset.x.hypot(offset.z) > radius;
+
+            if particle.kind != Some(kind) || landed || out_of_range {
+                // Freshly shown particles fill the whole cylinder, recycled ones start again from the top
+                let height = if particle.kind.is_some() {
+                    rng.gen_range(0.5..1.0)
+                } else {
+                    rng.gen_range(-1.0..1.0)
+                };
+                let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                let distance = radius * rng.gen::<f32>().sqrt();
+                *position = center
+                    + Vec3::new(
+                        angle.cos() * distance,
+                        height * radius,
+                        angle.sin() * distance,
+                    );
+
+                let (particle_mesh, particle_material) = match kind {
+                    WeatherKind::Snow => &assets.snow,
+                    _ => &assets.rain,
+                };
+                if particle.kind != Some(kind) {
+                    *mesh = particle_mesh.clone();
+                    *material = particle_material.clone();
+                }
+                particle.velocity = match kind {
+                    WeatherKind::Snow => Vec3::new(0.0, -rng.gen_range(1.0..2.0), 0.0),
+                    _ => Vec3::new(0.0, -rng.gen_range(18.0..22.0), 0.0),
+                };
+                particle.phase = rng.gen_range(0.0..std::f32::consts::TAU);
+                particle.kind = Some(kind);
+                *visibility = Visibility::Visible;
+                continue;
+            }
+
+            let drift = match kind {
+                WeatherKind::Snow => {
+                    let t = time.elapsed_seconds() + particle.phase;
+                    Vec3::new(t.sin(), 0.0, (t * 0.7).cos()) * 0.5
+                }
+                _ => Vec3::ZERO,
+            };
+            *position += (particle.velocity + drift) * time.delta_seconds();
+        }
+    }
+
+    /// Cover exposed full voxels around the player with snow while it's snowing. Voxels only come in sixths, none of
+    /// them flat halfway up: the three sixth wedge holds as much snow as a slab would, under a single flat top.
+    /// Snow isn't one of the player's actions and stays out of the build history.
+    fn accumulate_snow(
+        time: Res<Time>,
+        weather: Res<Weather>,
+        settings: Res<WeatherSettings>,
+        world: Res<World>,
+        registry: Res<MaterialRegistry>,
+        player: Query<&Transform, With<Player>>,
+        mut budget: Local<f32>,
+    ) {
+        if !settings.snow_accumulation || weather.current() != WeatherKind::Snow {
+            return;
+        }
+        let Some(snow) = registry.find("snow") else { return };
+        let Ok(player) = player.get_single() else { return };
+
+        *budget += settings.snow_accumulation_rate
+            * weather.effects().precipitation
+            * time.delta_seconds();
+        let mut rng = rand::thread_rng();
+        let mut edit = WorldEdit::new();
+        while *budget >= 1.0 {
+            *budget -= 1.0;
+
+            let radius = settings.particle_radius;
+            let x = (player.translation.x + rng.gen_range(-radius..radius)).floor() as i32;
+            let z = (player.translation.z + rng.gen_range(-radius..radius)).floor() as i32;
+            // Highest voxel of the column, the only one exposed to the sky
+            let sky_height = world.chunk_size.sky_height();
+            let Some(ground) = (0..sky_height)
+                .rev()
+                .filter_map(|y| world.get_voxel(IVec3::new(x, y, z)))
+                .find(|voxel| voxel.shape.volume != Volume::ZeroSixth)
+            else { continue };
+            let position = ground.position + IVec3::Y;
+            if ground.material == snow
+                || !blocks_light(&VoxelDescriptor::from(ground), &registry)
+                || position.y >= sky_height
+            {
+                continue;
+            }
+
+            edit.set(
+                position,
+                VoxelDescriptor {
+                    shape: Shape::new(Rotation::FacingNorth0Degrees, Volume::ThreeSixth),
+                    material: snow,
+                },
+            );
+        }
+        edit.apply(&world, &registry);
+    }
+}
@@ -0,0 +1,10 @@
+#![feature(lazy_cell)]
+#![feature(core_intrinsics)]
+
+pub use plugins::VoxelPlugins;
+
+pub mod debug;
+pub mod environment;
+pub mod player;
+mod plugins;
+pub mod world;
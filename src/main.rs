@@ -1,33 +1,17 @@
-#![feature(lazy_cell)]
-#![feature(core_intrinsics)]
-
 use bevy::{core::TaskPoolThreadAssignmentPolicy, prelude::*, window::PresentMode};
 
-#[cfg(feature = "taa")]
-use bevy::core_pipeline::experimental::taa::TemporalAntiAliasPlugin;
-
 #[cfg(feature = "debug")]
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
 #[cfg(feature = "debug")]
-use debug::plugin::DebugPluginBuilder;
-use environment::EnvironmentPlugin;
-use player::PlayerPlugin;
-use world::{
-    chunk::{loader::ChunkLoaderPlugin, material::TerrainMaterial},
-    WorldPlugin,
-};
-
-mod debug;
-mod environment;
-mod player;
-mod world;
+use voxel::debug::plugin::DebugPluginBuilder;
+use voxel::VoxelPlugins;
 
 #[bevy_main]
 fn main() {
     let mut app = App::new();
 
-    app.add_plugins(
+    app.add_plugins((
         DefaultPlugins
             .set(WindowPlugin {
                 primary_window: Some(Window {
@@ -47,15 +31,7 @@ fn main() {
                     ..default()
                 },
             }),
-    )
-    .add_plugins((
-        WorldPlugin,
-        ChunkLoaderPlugin::new(1000, 1200),
-        MaterialPlugin::<TerrainMaterial>::default(),
-        PlayerPlugin,
-        EnvironmentPlugin,
-        #[cfg(feature = "taa")]
-        TemporalAntiAliasPlugin,
+        VoxelPlugins::new(),
     ));
 
     #[cfg(feature = "debug")]
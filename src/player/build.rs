@@ -1,55 +1,143 @@
 use bevy::{input::common_conditions::input_just_pressed, prelude::*};
 
-use crate::world::voxel::{
-    material::*,
-    shape::{Shape, Volume},
-    VoxelDescriptor,
+use crate::world::{
+    edit::WorldEdit,
+    voxel::{
+        material::{registry::MaterialRegistry, Material, GRASS},
+        shape::{Rotation, Shape, Volume},
+        VoxelDescriptor,
+    },
 };
 
-use super::Raycast;
+use super::{history::EditHistory, Raycast};
+
+/// Voxel placed by the build tools.
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct Brush {
+    pub shape: Shape,
+    pub material: Material,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            shape: Shape::FULL,
+            material: GRASS,
+        }
+    }
+}
+
+impl From<Brush> for VoxelDescriptor {
+    fn from(brush: Brush) -> Self {
+        VoxelDescriptor {
+            shape: brush.shape,
+            material: brush.material,
+        }
+    }
+}
 
 pub struct BuildPlugin;
 
 impl Plugin for BuildPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.init_resource::<Brush>().add_systems(
             Update,
             (
                 Self::interact.run_if(input_just_pressed(MouseButton::Left)),
                 Self::place.run_if(input_just_pressed(MouseButton::Right)),
-            ),
+                Self::pick_brush,
+            )
+                .run_if(resource_exists::<MaterialRegistry>()),
         );
     }
 }
 
 impl BuildPlugin {
-    fn interact(world: Res<crate::world::World>, raycast: Res<Raycast>) {
+    fn interact(
+        world: Res<crate::world::World>,
+        raycast: Res<Raycast>,
+        registry: Res<MaterialRegistry>,
+        mut history: ResMut<EditHistory>,
+    ) {
         let Some(result) = raycast.result else { return };
-        let Some(chunk) = world.get_chunk_at_pos(result.position) else { return };
-        {
-            let mut chunk_lock = chunk.write();
-            let relative_position = chunk_lock.get_relative_position(result.position);
-            let Some(ref mut terrain) = chunk_lock.terrain else { return };
-            let Some(voxel) = terrain.voxel_at_pos_mut(relative_position.as_ivec3()) else { return };
-            voxel.shape.volume = Volume::ZeroSixth;
-        }
-        chunk.write().dirty = true;
+        let Some(voxel) = world.get_voxel(result.position) else { return };
+        let mut edit = WorldEdit::new();
+        edit.set(
+            result.position,
+            VoxelDescriptor {
+                shape: Shape {
+                    volume: Volume::ZeroSixth,
+                    ..voxel.shape
+                },
+                material: voxel.material,
+            },
+        );
+        history.record(edit.apply(&world, &registry));
     }
 
-    fn place(world: Res<crate::world::World>, raycast: Res<Raycast>) {
+    fn place(
+        world: Res<crate::world::World>,
+        raycast: Res<Raycast>,
+        registry: Res<MaterialRegistry>,
+        brush: Res<Brush>,
+        mut history: ResMut<EditHistory>,
+    ) {
         let Some(result) = raycast.result else { return };
-        let Some(chunk) = world.get_chunk_at_pos(result.position) else { return };
-        {
-            let mut chunk_lock = chunk.write();
-            let relative_position = chunk_lock.get_relative_position(result.position);
-            let Some(ref mut terrain) = chunk_lock.terrain else { return };
-            let voxel =
-                terrain.voxel_at_pos_mut(relative_position.as_ivec3() + IVec3::new(0, 1, 0));
-            *voxel = Some(VoxelDescriptor {
-                shape: Shape::FULL,
-                material: GRASS,
-            });
+        let mut edit = WorldEdit::new();
+        edit.set(result.position + IVec3::new(0, 1, 0), (*brush).into());
+        history.record(edit.apply(&world, &registry));
+    }
+
+    /// Middle click copies the aimed voxel into the brush. B, N and Q step through the materials, volumes and rotations,
+    /// backwards while holding shift.
+    fn pick_brush(
+        keys: Res<Input<KeyCode>>,
+        mouse: Res<Input<MouseButton>>,
+        world: Res<crate::world::World>,
+        raycast: Res<Raycast>,
+        registry: Res<MaterialRegistry>,
+        mut brush: ResMut<Brush>,
+    ) {
+        let mut picked = *brush;
+        if mouse.just_pressed(MouseButton::Middle) {
+            if let Some(voxel) = raycast
+                .result
+                .and_then(|result| world.get_voxel(result.position))
+                .filter(|voxel| voxel.shape.volume != Volume::ZeroSixth)
+            {
+                picked.shape = voxel.shape;
+                picked.material = voxel.material;
+            }
+        }
+
+        let step = if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            -1
+        } else {
+            1
+        };
+        let cycle =
+            |value: u32, count: usize| (value as i32 + step).rem_euclid(count.max(1) as i32) as u32;
+        if keys.just_pressed(KeyCode::B) {
+            picked.material = Material {
+                id: cycle(picked.material.id, registry.iter().count()),
+            };
+        }
+        // Empty volumes are skipped, placing them would erase voxels
+        if keys.just_pressed(KeyCode::N) {
+            picked.shape.volume = Volume::from(cycle(picked.shape.volume as u32 - 1, 6) as u8 + 1);
+        }
+        if keys.just_pressed(KeyCode::Q) {
+            picked.shape.rotation = Rotation::from(cycle(picked.shape.rotation as u32, 24) as u8);
+        }
+
+        if brush.set_if_neq(picked) {
+            let name = registry
+                .get(picked.material)
+                .map_or("unknown", |properties| &properties.name);
+            info!(
+                "Brush set to {name}, {:?} {:?}",
+                picked.shape.volume, picked.shape.rotation
+            );
         }
-        chunk.write().dirty = true;
     }
 }
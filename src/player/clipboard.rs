@@ -0,0 +1,199 @@
+use std::{
+    any::TypeId,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{asset::LoadedFolder, prelude::*};
+
+use crate::world::{
+    blueprint::{vox::RegistryVoxMaterialTable, Blueprint, BlueprintTransform, BLUEPRINT_FOLDER},
+    voxel::{material::registry::MaterialRegistry, shape::Volume},
+    World,
+};
+
+use super::{
+    history::EditHistory,
+    selection::{Selection, MAX_SELECTION_VOLUME},
+    Raycast,
+};
+
+/// Voxels at least this full are exported to MagicaVoxel as cubes, the others are left out.
+const VOX_EXPORT_MIN_VOLUME: Volume = Volume::ThreeSixth;
+
+/// Copied voxels, pasted on top of the aimed voxel.
+#[derive(Resource, Default)]
+pub struct Clipboard {
+    pub blueprint: Option<Blueprint>,
+    pub transform: BlueprintTransform,
+    /// Index of the next blueprint of the assets folder to open.
+    next_blueprint: usize,
+}
+
+impl Clipboard {
+    fn paste_origin(raycast: &Raycast) -> Option<IVec3> {
+        Some(raycast.result?.position + IVec3::Y)
+    }
+}
+
+#[derive(Resource)]
+struct BlueprintFolder(Handle<LoadedFolder>);
+
+pub struct ClipboardPlugin;
+
+impl Plugin for ClipboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Clipboard>()
+            // The material registry is needed to load `.vox` models
+            .add_systems(
+                Update,
+                Self::load_blueprints.run_if(resource_added::<MaterialRegistry>()),
+            )
+            .add_systems(
+                Update,
+                (
+                    Self::transform,
+                    (
+                        Self::copy,
+                        Self::paste,
+                        Self::save,
+                        Self::export,
+                        Self::open,
+                    )
+                        .run_if(resource_exists::<MaterialRegistry>()),
+                    Self::render_preview,
+                )
+                    .chain(),
+            );
+    }
+}
+
+impl ClipboardPlugin {
+    fn load_blueprints(mut commands: Commands, asset_server: Res<AssetServer>) {
+        commands.insert_resource(BlueprintFolder(asset_server.load_folder(BLUEPRINT_FOLDER)));
+    }
+
+    fn control_pressed(keys: &Input<KeyCode>, key: KeyCode) -> bool {
+        keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) && keys.just_pressed(key)
+    }
+
+    fn transform(keys: Res<Input<KeyCode>>, mut clipboard: ResMut<Clipboard>) {
+        if keys.just_pressed(KeyCode::T) {
+            clipboard.transform.turns = (clipboard.transform.turns + 1) % 4;
+        }
+        if keys.just_pressed(KeyCode::M) {
+            clipboard.transform.mirrored = !clipboard.transform.mirrored;
+        }
+    }
+
+    fn copy(
+        keys: Res<Input<KeyCode>>,
+        selection: Res<Selection>,
+        world: Res<World>,
+        registry: Res<MaterialRegistry>,
+        mut clipboard: ResMut<Clipboard>,
+    ) {
+        if !Self::control_pressed(&keys, KeyCode::C) {
+            return;
+        }
+        let Some((min, max)) = selection.bounds() else { return };
+        let size = max - min + IVec3::ONE;
+        if size.x * size.y * size.z > MAX_SELECTION_VOLUME {
+            warn!("Selection of {size} voxels is too large to be copied");
+            return;
+        }
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        clipboard.blueprint = Some(Blueprint::copy(
+            format!("blueprint-{created}"),
+            &world,
+            &registry,
+            min,
+            max,
+        ));
+        clipboard.transform = BlueprintTransform::default();
+    }
+
+    fn paste(
+        keys: Res<Input<KeyCode>>,
+        raycast: Res<Raycast>,
+        clipboard: Res<Clipboard>,
+        world: Res<World>,
+        registry: Res<MaterialRegistry>,
+        mut history: ResMut<EditHistory>,
+    ) {
+        if !Self::control_pressed(&keys, KeyCode::V) {
+            return;
+        }
+        let Some(blueprint) = &clipboard.blueprint else { return };
+        let Some(origin) = Clipboard::paste_origin(&raycast) else { return };
+        let edit = blueprint.paste(origin, clipboard.transform, &registry);
+        history.record(edit.apply(&world, &registry));
+    }
+
+    fn save(keys: Res<Input<KeyCode>>, clipboard: Res<Clipboard>) {
+        if !Self::control_pressed(&keys, KeyCode::S) {
+            return;
+        }
+        let Some(blueprint) = &clipboard.blueprint else { return };
+        match blueprint.save() {
+            Ok(path) => info!("Saved blueprint to {}", path.display()),
+            Err(error) => error!("{error}"),
+        }
+    }
+
+    fn export(
+        keys: Res<Input<KeyCode>>,
+        clipboard: Res<Clipboard>,
+        table: Res<RegistryVoxMaterialTable>,
+        registry: Res<MaterialRegistry>,
+    ) {
+        if !Self::control_pressed(&keys, KeyCode::E) {
+            return;
+        }
+        let Some(blueprint) = &clipboard.blueprint else { return };
+        match blueprint.save_vox(&table.get(), &registry, VOX_EXPORT_MIN_VOLUME) {
+            Ok(path) => info!("Exported blueprint to {}", path.display()),
+            Err(error) => error!("{error}"),
+        }
+    }
+
+    /// Put the next blueprint of the assets folder in the clipboard.
+    fn open(
+        keys: Res<Input<KeyCode>>,
+        folder: Option<Res<BlueprintFolder>>,
+        folders: Res<Assets<LoadedFolder>>,
+        blueprints: Res<Assets<Blueprint>>,
+        mut clipboard: ResMut<Clipboard>,
+    ) {
+        if !Self::control_pressed(&keys, KeyCode::O) {
+            return;
+        }
+        let Some(folder) = folder.and_then(|folder| folders.get(&folder.0)) else { return };
+        let loaded: Vec<&Blueprint> = folder
+            .handles
+            .iter()
+            .filter(|handle| handle.type_id() == TypeId::of::<Blueprint>())
+            .filter_map(|handle| blueprints.get(handle.clone().typed::<Blueprint>()))
+            .collect();
+        if loaded.is_empty() {
+            return;
+        }
+        let index = clipboard.next_blueprint % loaded.len();
+        info!("Opened blueprint {}", loaded[index].name);
+        clipboard.blueprint = Some(loaded[index].clone());
+        clipboard.transform = BlueprintTransform::default();
+        clipboard.next_blueprint = index + 1;
+    }
+
+    fn render_preview(mut gizmos: Gizmos, raycast: Res<Raycast>, clipboard: Res<Clipboard>) {
+        let Some(blueprint) = &clipboard.blueprint else { return };
+        let Some(origin) = Clipboard::paste_origin(&raycast) else { return };
+        let size = blueprint.transformed_size(clipboard.transform).as_vec3();
+        gizmos.cuboid(
+            Transform::from_translation(origin.as_vec3() + size / 2.0).with_scale(size),
+            Color::CYAN,
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    input::mouse::MouseMotion,
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use bevy_spectator::{SpectatorSettings, SpectatorSystemSet};
+
+use crate::{
+    debug::app::DebugApp,
+    world::{voxel::collision::Aabb, World},
+};
+
+use super::Player;
+
+/// Longest distance the body moves between two collision checks, shorter than a voxel so it can't go through one.
+const MAX_STEP: f32 = 0.25;
+const MAX_RESOLUTION_ITERATIONS: usize = 4;
+/// Contacts lifting the body less than this don't stop it, so it doesn't snag on the edges between voxels.
+const SNAG_TOLERANCE: f32 = 0.05;
+
+#[derive(Resource, Reflect, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[reflect(Resource)]
+pub enum MovementMode {
+    /// Free flying camera going through the terrain.
+    #[default]
+    Spectator,
+    /// Character walking on the terrain.
+    Walking,
+}
+
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct WalkSettings {
+    pub speed: f32,
+    pub sprint_speed: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    /// Size of the collision box, the camera being `eye_height` above its bottom.
+    pub width: f32,
+    pub height: f32,
+    pub eye_height: f32,
+    /// Steepest slope in degrees the player walks up, steeper ones are walls.
+    /// One to three sixth slopes are 45 to 55 degrees steep.
+    pub max_slope: f32,
+    pub toggle_key: KeyCode,
+}
+
+impl Default for WalkSettings {
+    fn default() -> Self {
+        Self {
+            speed: 4.5,
+            sprint_speed: 8.0,
+            jump_speed: 8.0,
+            gravity: 25.0,
+            width: 0.6,
+            height: 1.8,
+            eye_height: 1.6,
+            max_slope: 55.0,
+            toggle_key: KeyCode::F,
+        }
+    }
+}
+
+impl WalkSettings {
+    fn body(&self, feet: Vec3) -> Aabb {
+        let half_width = self.width / 2.0;
+        Aabb {
+            min: feet - Vec3::new(half_width, 0.0, half_width),
+            max: feet + Vec3::new(half_width, self.height, half_width),
+        }
+    }
+
+    /// Move a body through the world, sliding along walls and walking up slopes.
+    /// Returns the new position and velocity of the body, and whether it stands on the ground.
+    fn move_and_slide(
+        &self,
+        world: &World,
+        mut feet: Vec3,
+        mut velocity: Vec3,
+        delta_seconds: f32,
+    ) -> (Vec3, Vec3, bool) {
+        let min_ground_normal_y = self.max_slope.to_radians().cos();
+        let steps = ((velocity * delta_seconds).length() / MAX_STEP)
+            .ceil()
+            .max(1.0);
+        let mut grounded = false;
+
+        for _ in 0..steps as usize {
+            feet += velocity * delta_seconds / steps;
+
+            for _ in 0..MAX_RESOLUTION_ITERATIONS {
+                let contacts = world.aabb_contacts(&self.body(feet));
+                if contacts.is_empty() {
+                    break;
+                }
+                let lift = contacts
+                    .iter()
+                    .map(|contact| contact.lift)
+                    .fold(0.0, f32::max);
+                if velocity.y <= 0.0 && lift <= SNAG_TOLERANCE {
+                    feet.y += lift;
+                    velocity.y = 0.0;
+                    grounded = true;
+                    break;
+                }
+
+                let Some(contact) = contacts.iter().max_by(|a, b| a.depth.total_cmp(&b.depth)) else { break };
+                if contact.normal.y >= min_ground_normal_y {
+                    // Pushed straight up so standing on a slope doesn't slide down
+                    feet.y += contact.depth / contact.normal.y;
+                    velocity.y = velocity.y.max(0.0);
+                    grounded = true;
+                } else {
+                    feet += contact.normal * contact.depth;
+                    velocity -= contact.normal * velocity.dot(contact.normal).min(0.0);
+                }
+            }
+        }
+
+        (feet, velocity, grounded)
+    }
+}
+
+#[derive(Component, Default, Debug)]
+pub struct CharacterController {
+    pub velocity: Vec3,
+    pub grounded: bool,
+}
+
+pub struct ControllerPlugin;
+
+impl Plugin for ControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MovementMode>()
+            .init_resource::<WalkSettings>()
+            .debug_resource::<MovementMode>()
+            .debug_resource::<WalkSettings>()
+            .add_systems(
+                Update,
+                (
+                    Self::toggle_mode,
+                    (Self::grab_cursor, Self::look, Self::walk)
+                        .run_if(resource_equals(MovementMode::Walking)),
+                )
+                    .chain()
+                    // Move before the raycast like the spectator camera does
+                    .before(SpectatorSystemSet),
+            );
+    }
+}
+
+impl ControllerPlugin {
+    fn toggle_mode(
+        keys: Res<Input<KeyCode>>,
+        settings: Res<WalkSettings>,
+        mut mode: ResMut<MovementMode>,
+        mut spectator: ResMut<SpectatorSettings>,
+        mut player: Query<(Entity, &mut CharacterController), With<Player>>,
+        mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    ) {
+        if !keys.just_pressed(settings.toggle_key) {
+            return;
+        }
+        let Ok((entity, mut controller)) = player.get_single_mut() else { return };
+
+        *mode = match *mode {
+            MovementMode::Spectator => MovementMode::Walking,
+            MovementMode::Walking => MovementMode::Spectator,
+        };
+        match *mode {
+            MovementMode::Walking => {
+                spectator.active = None;
+                *controller = CharacterController::default();
+                if let Ok(mut window) = windows.get_single_mut() {
+                    window.cursor.grab_mode = CursorGrabMode::Locked;
+                    window.cursor.visible = false;
+                }
+            }
+            MovementMode::Spectator => spectator.active = Some(entity),
+        }
+    }
+
+    fn grab_cursor(
+        mouse: Res<Input<MouseButton>>,
+        keys: Res<Input<KeyCode>>,
+        mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    ) {
+        let Ok(mut window) = windows.get_single_mut() else { return };
+        if mouse.just_pressed(MouseButton::Left) {
+            window.cursor.grab_mode = CursorGrabMode::Locked;
+            window.cursor.visible = false;
+        }
+        if keys.just_pressed(KeyCode::Escape) {
+            window.cursor.grab_mode = CursorGrabMode::None;
+            window.cursor.visible = true;
+        }
+    }
+
+    fn look(
+        mut motion: EventReader<MouseMotion>,
+        spectator: Res<SpectatorSettings>,
+        windows: Query<&Window, With<PrimaryWindow>>,
+        mut player: Query<&mut Transform, With<Player>>,
+    ) {
+        let delta: Vec2 = motion.read().map(|motion| motion.delta).sum();
+        let Ok(window) = windows.get_single() else { return };
+        if window.cursor.grab_mode == CursorGrabMode::None {
+            return;
+        }
+        let Ok(mut transform) = player.get_single_mut() else { return };
+
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let yaw = yaw - delta.x * spectator.sensitivity;
+        let pitch =
+            (pitch - delta.y * spectator.sensitivity).clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+    }
+
+    fn walk(
+        time: Res<Time>,
+        keys: Res<Input<KeyCode>>,
+        settings: Res<WalkSettings>,
+        world: Res<World>,
+        mut player: Query<(&mut Transform, &mut CharacterController), With<Player>>,
+    ) {
+        let Ok((mut transform, mut controller)) = player.get_single_mut() else { return };
+        let feet = transform.translation - Vec3::Y * settings.eye_height;
+        // Wait for the terrain around the player to be generated
+        if world.get_voxel(feet.floor().as_ivec3()).is_none() {
+            return;
+        }
+        // Long frames would make the player go through the ground
+        let delta_seconds = time.delta_seconds().min(0.05);
+
+        let forward = (transform.forward() * Vec3::new(1.0, 0.0, 1.0)).normalize_or_zero();
+        let right = (transform.right() * Vec3::new(1.0, 0.0, 1.0)).normalize_or_zero();
+        let mut direction = Vec3::ZERO;
+        for (key, key_direction) in [
+            (KeyCode::W, forward),
+            (KeyCode::S, -forward),
+            (KeyCode::D, right),
+            (KeyCode::A, -right),
+        ] {
+            if keys.pressed(key) {
+                direction += key_direction;
+            }
+        }
+        let speed = if keys.pressed(KeyCode::ShiftLeft) {
+            settings.sprint_speed
+        } else {
+            settings.speed
+        };
+        let horizontal = direction.normalize_or_zero() * speed;
+
+        let mut velocity = Vec3::new(horizontal.x, controller.velocity.y, horizontal.z);
+        if controller.grounded && keys.pressed(KeyCode::Space) {
+            velocity.y = settings.jump_speed;
+        }
+        velocity.y -= settings.gravity * delta_seconds;
+
+        let (feet, velocity, grounded) =
+            settings.move_and_slide(&world, feet, velocity, delta_seconds);
+        transform.translation = feet + Vec3::Y * settings.eye_height;
+        controller.velocity = velocity;
+        controller.grounded = grounded;
+    }
+}
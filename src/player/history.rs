@@ -0,0 +1,261 @@
+use std::{collections::VecDeque, mem::size_of};
+
+use bevy::prelude::*;
+
+use crate::{
+    debug::app::DebugApp,
+    world::{
+        edit::{VoxelChange, WorldEdit},
+        voxel::material::registry::MaterialRegistry,
+        World,
+    },
+};
+
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct HistorySettings {
+    /// Bytes the recorded changes may use, the oldest actions being forgotten past it.
+    pub memory_budget: usize,
+    /// Pressed with control.
+    pub undo_key: KeyCode,
+    /// Pressed with control, or the undo key with control and shift.
+    pub redo_key: KeyCode,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            memory_budget: 16 * 1024 * 1024,
+            undo_key: KeyCode::Z,
+            redo_key: KeyCode::Y,
+        }
+    }
+}
+
+/// Journal of the changes made by the build tools, each action being the changes of one tool use. Changes are stored
+/// by world position so actions outlive the chunks they touch, but actions touching unloaded chunks can't be undone
+/// or redone until they are loaded again.
+#[derive(Resource, Default, Debug)]
+pub struct EditHistory {
+    undone: VecDeque<Vec<VoxelChange>>,
+    redone: Vec<Vec<VoxelChange>>,
+    change_count: usize,
+}
+
+impl EditHistory {
+    /// Record an action, forgetting the actions it can no longer be redone over.
+    pub fn record(&mut self, action: Vec<VoxelChange>) {
+        if action.is_empty() {
+            return;
+        }
+        self.change_count -= self
+            .redone
+            .drain(..)
+            .map(|action| action.len())
+            .sum::<usize>();
+        self.change_count += action.len();
+        self.undone.push_back(action);
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.change_count * size_of::<VoxelChange>()
+    }
+
+    /// Put back the voxels replaced by the last action. Returns whether there was one to undo.
+    pub fn undo(&mut self, world: &World, materials: &MaterialRegistry) -> bool {
+        let Some(action) = self.undone.pop_back() else { return false };
+        if !Self::is_loaded(&action, world) {
+            warn!("Can't undo edits to unloaded chunks");
+            self.undone.push_back(action);
+            return false;
+        }
+        let mut edit = WorldEdit::new();
+        for change in action.iter() {
+            edit.set(change.position, change.previous);
+        }
+        edit.apply(world, materials);
+        self.redone.push(action);
+        true
+    }
+
+    /// Make the last undone action again. Returns whether there was one to redo.
+    pub fn redo(&mut self, world: &World, materials: &MaterialRegistry) -> bool {
+        let Some(action) = self.redone.pop() else { return false };
+        if !Self::is_loaded(&action, world) {
+            warn!("Can't redo edits to unloaded chunks");
+            self.redone.push(action);
+            return false;
+        }
+        let mut edit = WorldEdit::new();
+        for change in action.iter() {
+            edit.set(change.position, change.voxel);
+        }
+        edit.apply(world, materials);
+        self.undone.push_back(action);
+        true
+    }
+
+    /// Forget the oldest actions until the changes fit in the budget.
+    fn trim(&mut self, memory_budget: usize) {
+        while self.memory_usage() > memory_budget {
+            let Some(action) = self.undone.pop_front() else { break };
+            self.change_count -= action.len();
+        }
+    }
+
+    fn is_loaded(action: &[VoxelChange], world: &World) -> bool {
+        action.iter().all(|change| {
+            world
+                .get_chunk_at_pos(change.position)
+                .is_some_and(|chunk| chunk.read().terrain.is_some())
+        })
+    }
+}
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditHistory>()
+            .init_resource::<HistorySettings>()
+            .debug_resource::<HistorySettings>()
+            .add_systems(
+                Update,
+                (
+                    Self::undo_redo.run_if(resource_exists::<MaterialRegistry>()),
+                    Self::trim_history,
+                )
+                    .chain(),
+            );
+    }
+}
+
+impl HistoryPlugin {
+    fn undo_redo(
+        keys: Res<Input<KeyCode>>,
+        settings: Res<HistorySettings>,
+        world: Res<World>,
+        registry: Res<MaterialRegistry>,
+        mut history: ResMut<EditHistory>,
+    ) {
+        if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+            return;
+        }
+        let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        if keys.just_pressed(settings.redo_key) || shift && keys.just_pressed(settings.undo_key) {
+            history.redo(&world, &registry);
+        } else if keys.just_pressed(settings.undo_key) {
+            history.undo(&world, &registry);
+        }
+    }
+
+    fn trim_history(settings: Res<HistorySettings>, mut history: ResMut<EditHistory>) {
+        if history.memory_usage() > settings.memory_budget {
+            history.trim(settings.memory_budget);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use bevy::prelude::{Entity, IVec3, UVec3};
+
+    use crate::world::{
+        chunk::{ChunkSize, Coordinates, Terrain},
+        edit::{VoxelChange, WorldEdit},
+        voxel::{
+            material::{registry::MaterialRegistry, Material, DIRT, STONE},
+            shape::Shape,
+            VoxelDescriptor,
+        },
+        World,
+    };
+
+    use super::EditHistory;
+
+    /// A single chunk of air.
+    fn world() -> World {
+        let mut world = World::new(ChunkSize::new(UVec3::splat(4)));
+        let coordinates = Coordinates(IVec3::ZERO);
+        world.spawn_chunk(Entity::PLACEHOLDER, coordinates);
+        world.get_chunk(coordinates).unwrap().write().terrain = Some(Terrain::new(UVec3::splat(4)));
+        world
+    }
+
+    fn full(material: Material) -> VoxelDescriptor {
+        VoxelDescriptor {
+            shape: Shape::FULL,
+            material,
+        }
+    }
+
+    fn place(world: &World, position: IVec3, material: Material, history: &mut EditHistory) {
+        let mut edit = WorldEdit::new();
+        edit.set(position, full(material));
+        history.record(edit.apply(world, &MaterialRegistry::default()));
+    }
+
+    fn voxel(world: &World, position: IVec3) -> VoxelDescriptor {
+        world.get_voxel(position).unwrap().into()
+    }
+
+    #[test]
+    fn undo_and_redo_walk_through_recorded_actions() {
+        let world = world();
+        let materials = MaterialRegistry::default();
+        let mut history = EditHistory::default();
+        let position = IVec3::new(1, 2, 3);
+        let air = voxel(&world, position);
+
+        place(&world, position, STONE, &mut history);
+        place(&world, position, DIRT, &mut history);
+        assert_eq!(voxel(&world, position), full(DIRT));
+
+        assert!(history.undo(&world, &materials));
+        assert_eq!(voxel(&world, position), full(STONE));
+        assert!(history.undo(&world, &materials));
+        assert_eq!(voxel(&world, position), air);
+        assert!(!history.undo(&world, &materials));
+
+        assert!(history.redo(&world, &materials));
+        assert_eq!(voxel(&world, position), full(STONE));
+        assert!(history.redo(&world, &materials));
+        assert_eq!(voxel(&world, position), full(DIRT));
+        assert!(!history.redo(&world, &materials));
+    }
+
+    #[test]
+    fn recording_after_undo_forgets_undone_actions() {
+        let world = world();
+        let materials = MaterialRegistry::default();
+        let mut history = EditHistory::default();
+        let position = IVec3::new(1, 2, 3);
+
+        place(&world, position, STONE, &mut history);
+        place(&world, position, DIRT, &mut history);
+        assert!(history.undo(&world, &materials));
+        place(&world, IVec3::ZERO, DIRT, &mut history);
+
+        assert!(!history.redo(&world, &materials));
+        assert_eq!(voxel(&world, position), full(STONE));
+        assert_eq!(history.memory_usage(), 2 * size_of::<VoxelChange>());
+        assert!(history.undo(&world, &materials));
+        assert!(history.undo(&world, &materials));
+        assert!(!history.undo(&world, &materials));
+    }
+
+    #[test]
+    fn edits_leaving_voxels_unchanged_are_not_recorded() {
+        let world = world();
+        let materials = MaterialRegistry::default();
+        let mut history = EditHistory::default();
+        let position = IVec3::new(1, 2, 3);
+
+        place(&world, position, STONE, &mut history);
+        place(&world, position, STONE, &mut history);
+        assert!(history.undo(&world, &materials));
+        assert!(!history.undo(&world, &materials));
+    }
+}
@@ -7,22 +7,46 @@ use bevy::{core_pipeline::clear_color::ClearColorConfig, prelude::*};
 use bevy_atmosphere::prelude::AtmosphereCamera;
 use bevy_spectator::{Spectator, SpectatorPlugin, SpectatorSettings};
 
-use crate::world::chunk::loader::{ChunkLoaderSource, RenderDistance};
+use crate::{
+    environment::weather::Weather,
+    world::chunk::loader::{ChunkLoaderSource, RenderDistance},
+};
 
-use self::{build::BuildPlugin, raycast::RaycastPlugin};
+use self::{
+    build::BuildPlugin,
+    clipboard::ClipboardPlugin,
+    controller::{CharacterController, ControllerPlugin},
+    history::HistoryPlugin,
+    selection::SelectionPlugin,
+};
 
 mod build;
+mod clipboard;
+pub mod controller;
+pub mod history;
 mod raycast;
-pub use raycast::Raycast;
+mod selection;
+pub use raycast::{Raycast, RaycastPlugin};
 
+/// Camera the world is seen from, moved by the character controller and followed by the weather.
 #[derive(Component)]
 pub struct Player;
 
+/// Spawns the player camera, flying or walking around.
 pub struct PlayerPlugin;
 
+/// Placing and breaking voxels, region selection, undo history and clipboard. Aims with the `RaycastPlugin`.
+pub struct BuildToolsPlugin;
+
+impl Plugin for BuildToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((BuildPlugin, SelectionPlugin, HistoryPlugin, ClipboardPlugin));
+    }
+}
+
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_plugins((SpectatorPlugin, RaycastPlugin, BuildPlugin))
+        app.add_plugins((SpectatorPlugin, ControllerPlugin))
             .add_systems(Startup, Self::setup_player)
             .add_systems(Update, Self::update_fog)
             .insert_resource(SpectatorSettings {
@@ -31,6 +55,9 @@ impl Plugin for PlayerPlugin {
                 sensitivity: 0.001,
                 ..Default::default()
             });
+        // Screen space ambient occlusion is skipped with multisampling
+        #[cfg(feature = "ssao")]
+        app.insert_resource(Msaa::Off);
     }
 }
 
@@ -65,19 +92,20 @@ impl PlayerPlugin {
             },
             #[cfg(feature = "atmosphere")]
             AtmosphereCamera::default(),
+            #[cfg(feature = "ssao")]
+            ScreenSpaceAmbientOcclusionBundle {
+                settings: ScreenSpaceAmbientOcclusionSettings {
+                    quality_level: bevy::pbr::ScreenSpaceAmbientOcclusionQualityLevel::Custom {
+                        slice_count: 3,
+                        samples_per_slice_side: 3,
+                    },
+                },
+                ..default()
+            },
             Spectator,
+            CharacterController::default(),
             ChunkLoaderSource,
         ));
-        #[cfg(feature = "ssao")]
-        commands.spawn(ScreenSpaceAmbientOcclusionBundle {
-            settings: ScreenSpaceAmbientOcclusionSettings {
-                quality_level: bevy::pbr::ScreenSpaceAmbientOcclusionQualityLevel::Custom {
-                    slice_count: 3,
-                    samples_per_slice_side: 3,
-                },
-            },
-            ..default()
-        });
         #[cfg(feature = "taa")]
         commands.spawn(TemporalAntiAliasBundle::default());
     }
@@ -85,11 +113,14 @@ impl PlayerPlugin {
     fn update_fog(
         mut fogs: Query<(&mut FogSettings,), With<Player>>,
         render_distance: Res<RenderDistance>,
+        weather: Option<Res<Weather>>,
     ) {
+        // Thick weathers pull the fog closer, its start even more so the fog gets denser
+        let visibility = weather.map_or(1.0, |weather| weather.effects().visibility);
         for (mut fog,) in fogs.iter_mut() {
             fog.falloff = FogFalloff::Linear {
-                end: render_distance.unload_distance as f32 / 1.4,
-                start: render_distance.load_distance as f32 / 1.4,
+                end: render_distance.unload_distance as f32 / 1.4 * visibility,
+                start: render_distance.load_distance as f32 / 1.4 * visibility * visibility,
             }
         }
     }
@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+
+use crate::world::{
+    edit::WorldEdit,
+    voxel::{
+        material::{registry::MaterialRegistry, Material},
+        shape::Shape,
+        VoxelDescriptor,
+    },
+    World,
+};
+
+use super::{build::Brush, history::EditHistory, Raycast};
+
+/// Larger selections are refused, editing them would stall the game.
+pub const MAX_SELECTION_VOLUME: i32 = 128 * 128 * 128;
+
+/// Box between two corner voxels, both included.
+#[derive(Resource, Default, Debug)]
+pub struct Selection {
+    pub first: Option<IVec3>,
+    pub second: Option<IVec3>,
+}
+
+impl Selection {
+    /// Smallest and largest voxel of the selection once both corners are set.
+    pub fn bounds(&self) -> Option<(IVec3, IVec3)> {
+        let (first, second) = (self.first?, self.second?);
+        Some((first.min(second), first.max(second)))
+    }
+}
+
+/// Edits applied to the selected box, using the brush for every placed voxel.
+#[derive(Event, Clone, Copy, Debug)]
+pub enum SelectionCommand {
+    Fill,
+    /// Change the material of every non empty voxel made of `from`, keeping its shape.
+    Replace {
+        from: Material,
+        to: Material,
+    },
+    /// Outline and empty the inside.
+    Hollow,
+    /// The four vertical sides.
+    Walls,
+    /// The six sides.
+    Outline,
+}
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Selection>()
+            .add_event::<SelectionCommand>()
+            .add_systems(
+                Update,
+                (
+                    Self::select,
+                    Self::send_commands,
+                    Self::apply_commands.run_if(resource_exists::<MaterialRegistry>()),
+                    Self::render_selection,
+                )
+                    .chain(),
+            );
+    }
+}
+
+impl SelectionPlugin {
+    fn select(keys: Res<Input<KeyCode>>, raycast: Res<Raycast>, mut selection: ResMut<Selection>) {
+        if keys.just_pressed(KeyCode::Back) {
+            *selection = Selection::default();
+        }
+        let Some(result) = raycast.result else { return };
+        if keys.just_pressed(KeyCode::Key1) {
+            selection.first = Some(result.position);
+        }
+        if keys.just_pressed(KeyCode::Key2) {
+            selection.second = Some(result.position);
+        }
+    }
+
+    fn send_commands(
+        keys: Res<Input<KeyCode>>,
+        raycast: Res<Raycast>,
+        world: Res<World>,
+        brush: Res<Brush>,
+        mut commands: EventWriter<SelectionCommand>,
+    ) {
+        if keys.just_pressed(KeyCode::G) {
+            commands.send(SelectionCommand::Fill);
+        }
+        if keys.just_pressed(KeyCode::H) {
+            commands.send(SelectionCommand::Hollow);
+        }
+        if keys.just_pressed(KeyCode::J) {
+            commands.send(SelectionCommand::Walls);
+        }
+        if keys.just_pressed(KeyCode::K) {
+            commands.send(SelectionCommand::Outline);
+        }
+        // Replace the material of the aimed voxel with the brush one
+        if keys.just_pressed(KeyCode::R) {
+            let Some(result) = raycast.result else { return };
+            let Some(voxel) = world.get_voxel(result.position) else { return };
+            commands.send(SelectionCommand::Replace {
+                from: voxel.material,
+                to: brush.material,
+            });
+        }
+    }
+
+    fn apply_commands(
+        mut commands: EventReader<SelectionCommand>,
+        selection: Res<Selection>,
+        brush: Res<Brush>,
+        world: Res<World>,
+        registry: Res<MaterialRegistry>,
+        mut history: ResMut<EditHistory>,
+    ) {
+        for command in commands.read() {
+            let Some((min, max)) = selection.bounds() else { continue };
+            let size = max - min + IVec3::ONE;
+            if size.x * size.y * size.z > MAX_SELECTION_VOLUME {
+                warn!("Selection of {size} voxels is too large to be edited");
+                continue;
+            }
+
+            let mut edit = WorldEdit::new();
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        let position = IVec3::new(x, y, z);
+                        let on_side = |axis: usize| {
+                            position[axis] == min[axis] || position[axis] == max[axis]
+                        };
+                        let on_walls = on_side(0) || on_side(2);
+                        let on_outline = on_walls || on_side(1);
+
+                        let voxel = match command {
+                            SelectionCommand::Fill => Some((*brush).into()),
+                            SelectionCommand::Replace { from, to } => world
+                                .get_voxel(position)
+                                .filter(|voxel| voxel.material == *from)
+                                .map(|voxel| VoxelDescriptor {
+                                    shape: voxel.shape,
+                                    material: *to,
+                                }),
+                            SelectionCommand::Hollow if on_outline => Some((*brush).into()),
+                            SelectionCommand::Hollow => Some(VoxelDescriptor {
+                                shape: Shape::EMPTY,
+                                material: brush.material,
+                            }),
+                            SelectionCommand::Walls if on_walls => Some((*brush).into()),
+                            SelectionCommand::Outline if on_outline => Some((*brush).into()),
+                            _ => None,
+                        };
+                        if let Some(voxel) = voxel {
+                            edit.set(position, voxel);
+                        }
+                    }
+                }
+            }
+            history.record(edit.apply(&world, &registry));
+        }
+    }
+
+    fn render_selection(mut gizmos: Gizmos, selection: Res<Selection>) {
+        for (corner, color) in [
+            (selection.first, Color::GREEN),
+            (selection.second, Color::RED),
+        ] {
+            let Some(corner) = corner else { continue };
+            gizmos.cuboid(
+                Transform::from_translation(corner.as_vec3() + Vec3::splat(0.5))
+                    .with_scale(Vec3::splat(1.05)),
+                color,
+            );
+        }
+        let Some((min, max)) = selection.bounds() else { return };
+        let size = (max - min + IVec3::ONE).as_vec3();
+        gizmos.cuboid(
+            Transform::from_translation(min.as_vec3() + size / 2.0).with_scale(size),
+            Color::YELLOW,
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::{app::PluginGroupBuilder, prelude::*};
+
+#[cfg(feature = "taa")]
+use bevy::core_pipeline::experimental::taa::TemporalAntiAliasPlugin;
+
+use crate::{
+    environment::EnvironmentPlugin,
+    player::{BuildToolsPlugin, PlayerPlugin, RaycastPlugin},
+    world::{
+        chunk::{
+            generator::TerrainGeneratorKind, loader::ChunkLoaderPlugin,
+            material::TerrainMaterialPlugin, visibility::ChunkVisibilityPlugin, ChunkSize,
+        },
+        voxel::material::registry::MATERIAL_REGISTRY_PATH,
+        WorldPlugin,
+    },
+};
+
+/// Every plugin of the voxel world, to be added after Bevy's `DefaultPlugins`. The world, chunk loading and terrain
+/// rendering are always there, the player, tools and environment can be left out to bring your own.
+///
+/// Materials, textures and shaders are read from the assets folder, which has to be shipped along.
+pub struct VoxelPlugins {
+    load_distance: u32,
+    unload_distance: u32,
+    chunk_size: ChunkSize,
+    generator: TerrainGeneratorKind,
+    materials: String,
+    player: bool,
+    raycast: bool,
+    build: bool,
+    environment: bool,
+}
+
+impl Default for VoxelPlugins {
+    fn default() -> Self {
+        Self {
+            load_distance: 1000,
+            unload_distance: 1200,
+            chunk_size: ChunkSize::default(),
+            generator: TerrainGeneratorKind::default(),
+            materials: MATERIAL_REGISTRY_PATH.to_string(),
+            player: true,
+            raycast: true,
+            build: true,
+            environment: true,
+        }
+    }
+}
+
+impl VoxelPlugins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Distances in voxels around the `ChunkLoaderSource` within which chunks are loaded, and beyond which they are
+    /// unloaded. Changeable later through the `RenderDistance` resource.
+    pub fn load_distances(mut self, load_distance: u32, unload_distance: u32) -> Self {
+        self.load_distance = load_distance;
+        self.unload_distance = unload_distance;
+        self
+    }
+
+    /// Dimensions of the chunks in voxels. Chunks are stacked in layers when shorter than the world height, smaller
+    /// chunks being quicker to remesh after edits but more numerous.
+    pub fn chunk_size(mut self, size: UVec3) -> Self {
+        self.chunk_size = ChunkSize::new(size);
+        self
+    }
+
+    /// Terrain generator of new chunks. Changeable later through the `TerrainGeneratorKind` resource.
+    pub fn generator(mut self, generator: TerrainGeneratorKind) -> Self {
+        self.generator = generator;
+        self
+    }
+
+    /// Material registry file, relative to the assets folder.
+    pub fn materials(mut self, path: impl Into<String>) -> Self {
+        self.materials = path.into();
+        self
+    }
+
+    /// Spawn the player camera. Without it, chunks are loaded around the entities given a `ChunkLoaderSource`.
+    pub fn player(mut self, enabled: bool) -> Self {
+        self.player = enabled;
+        self
+    }
+
+    /// Aim at voxels from the camera. Always included along with the build tools.
+    pub fn raycast(mut self, enabled: bool) -> Self {
+        self.raycast = enabled;
+        self
+    }
+
+    /// Placing and breaking voxels, region selection, undo history and clipboard.
+    pub fn build(mut self, enabled: bool) -> Self {
+        self.build = enabled;
+        self
+    }
+
+    /// Sky, daylight cycle and weather.
+    pub fn environment(mut self, enabled: bool) -> Self {
+        self.environment = enabled;
+        self
+    }
+}
+
+impl PluginGroup for VoxelPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let mut group = PluginGroupBuilder::start::<Self>()
+            .add(WorldPlugin {
+                chunk_size: self.chunk_size,
+                generator: self.generator,
+                materials: self.materials,
+            })
+            .add(ChunkLoaderPlugin::new(
+                self.load_distance,
+                self.unload_distance,
+            ))
+            .add(ChunkVisibilityPlugin)
+            .add(TerrainMaterialPlugin);
+        if self.player {
+            group = group.add(PlayerPlugin);
+        }
+        if self.raycast || self.build {
+            group = group.add(RaycastPlugin);
+        }
+        if self.build {
+            group = group.add(BuildToolsPlugin);
+        }
+        if self.environment {
+            group = group.add(EnvironmentPlugin);
+        }
+        #[cfg(feature = "taa")]
+        {
+            group = group.add(TemporalAntiAliasPlugin);
+        }
+        group
+    }
+}
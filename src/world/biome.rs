@@ -0,0 +1,31 @@
+use bevy::{prelude::Vec3, reflect::Reflect};
+use noise::{NoiseFn, SuperSimplex};
+
+/// Climate of a region, sampled from low frequency temperature and humidity noises independent of the terrain.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+pub enum Biome {
+    Temperate,
+    Desert,
+    Tundra,
+    Wetlands,
+}
+
+impl Biome {
+    pub fn at(position: Vec3) -> Self {
+        // arbitrary scale, climates span many chunks
+        let div = 1000.0;
+        let point = [position.x as f64 / div, position.z as f64 / div];
+        let temperature = SuperSimplex::new(1).get(point);
+        let humidity = SuperSimplex::new(2).get(point);
+
+        if temperature < -0.25 {
+            Self::Tundra
+        } else if temperature > 0.25 && humidity < 0.0 {
+            Self::Desert
+        } else if humidity > 0.25 {
+            Self::Wetlands
+        } else {
+            Self::Temperate
+        }
+    }
+}
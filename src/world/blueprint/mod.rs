@@ -0,0 +1,446 @@
+use std::{f32::consts::FRAC_PI_2, fmt::Display, path::PathBuf};
+
+use bevy::{
+    asset::{
+        io::{file::FileAssetReader, Reader},
+        AssetLoader, AsyncReadExt, LoadContext,
+    },
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::{Deserialize, Serialize};
+
+use self::vox::{RegistryVoxMaterialTable, VoxLoader};
+
+use super::{
+    edit::WorldEdit,
+    voxel::{
+        material::registry::MaterialRegistry,
+        shape::{Shape, ShapeDescriptor},
+        VoxelDescriptor,
+    },
+    World,
+};
+
+pub mod vox;
+
+/// Asset folder blueprints are saved to and loaded from.
+pub const BLUEPRINT_FOLDER: &str = "blueprints";
+pub const BLUEPRINT_EXTENSION: &str = "blueprint.ron";
+
+/// Voxel kind used by a blueprint. Materials are stored by name so blueprints can be shared between registries.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PaletteEntry {
+    /// `ShapeDescriptor` of the shape.
+    pub shape: u8,
+    pub material: String,
+}
+
+/// Copied box of voxels, saved as a file listing every kind of voxel it's made of and the run length encoded voxels.
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct Blueprint {
+    pub name: String,
+    /// Size of the bounding box in voxels.
+    pub size: [u32; 3],
+    palette: Vec<PaletteEntry>,
+    /// Palette indices in x, y then z order as (count, index) pairs, starting at 1. Voxels at 0 were unloaded when
+    /// copied and are left as they are when pasting.
+    runs: Vec<(u32, u16)>,
+}
+
+impl Blueprint {
+    /// Copy the voxels from `min` to `max` included.
+    pub fn copy(
+        name: String,
+        world: &World,
+        materials: &MaterialRegistry,
+        min: IVec3,
+        max: IVec3,
+    ) -> Self {
+        let size = (max - min + IVec3::ONE).as_uvec3();
+        let voxels = (min.x..=max.x).flat_map(move |x| {
+            (min.y..=max.y).flat_map(move |y| {
+                (min.z..=max.z).map(move |z| {
+                    let voxel = world.get_voxel(IVec3::new(x, y, z))?;
+                    Some(PaletteEntry {
+                        shape: ShapeDescriptor::from(voxel.shape).0,
+                        material: materials
+                            .get(voxel.material)
+                            .map(|properties| properties.name.clone())
+                            .unwrap_or_default(),
+                    })
+                })
+            })
+        });
+        Self::from_voxels(name, size, voxels)
+    }
+
+    /// Build a blueprint from its voxels in x, y then z order, `None` being left as they are when pasting.
+    fn from_voxels(
+        name: String,
+        size: UVec3,
+        voxels: impl Iterator<Item = Option<PaletteEntry>>,
+    ) -> Self {
+        let mut blueprint = Self {
+            name,
+            size: size.to_array(),
+            palette: vec![],
+            runs: vec![],
+        };
+        let mut palette_indices = HashMap::new();
+
+        for voxel in voxels {
+            let index = voxel.map_or(0, |entry| {
+                *palette_indices.entry(entry.clone()).or_insert_with(|| {
+                    blueprint.palette.push(entry);
+                    blueprint.palette.len() as u16
+                })
+            });
+            match blueprint.runs.last_mut() {
+                Some((count, last)) if *last == index => *count += 1,
+                _ => blueprint.runs.push((1, index)),
+            }
+        }
+
+        blueprint
+    }
+
+    /// Palette indices in x, y then z order.
+    fn indices(&self) -> impl Iterator<Item = u16> + '_ {
+        self.runs
+            .iter()
+            .flat_map(|(count, index)| (0..*count).map(move |_| *index))
+    }
+
+    /// Position in the blueprint of the voxel at `index` in x, y then z order.
+    fn position(&self, index: usize) -> UVec3 {
+        let [_, height, length] = self.size;
+        let index = index as u32;
+        UVec3::new(
+            index / (height * length),
+            index / length % height,
+            index % length,
+        )
+    }
+
+    /// Size of the bounding box once transformed.
+    pub fn transformed_size(&self, transform: BlueprintTransform) -> UVec3 {
+        let size = UVec3::from_array(self.size);
+        if transform.turns % 2 == 0 {
+            size
+        } else {
+            UVec3::new(size.z, size.y, size.x)
+        }
+    }
+
+    /// Voxels with their position in the blueprint. Voxels of materials missing from the registry are skipped.
+    pub fn voxels<'a>(
+        &'a self,
+        materials: &'a MaterialRegistry,
+    ) -> impl Iterator<Item = (UVec3, VoxelDescriptor)> + 'a {
+        let palette: Vec<Option<VoxelDescriptor>> = self
+            .palette
+            .iter()
+            .map(|entry| {
+                Some(VoxelDescriptor {
+                    shape: ShapeDescriptor(entry.shape).into(),
+                    material: materials.find(&entry.material)?,
+                })
+            })
+            .collect();
+
+        self.indices().enumerate().filter_map(move |(i, index)| {
+            let voxel = (*palette.get(index.checked_sub(1)? as usize)?)?;
+            Some((self.position(i), voxel))
+        })
+    }
+
+    /// Edit pasting the blueprint turned and mirrored, its bounding box starting at `origin`.
+    pub fn paste(
+        &self,
+        origin: IVec3,
+        transform: BlueprintTransform,
+        materials: &MaterialRegistry,
+    ) -> WorldEdit {
+        let matrix = transform.matrix();
+        let size = UVec3::from_array(self.size).as_vec3();
+        // Offset bringing the transformed box back on positive coordinates
+        let offset = (matrix * size).min(Vec3::ZERO);
+        let mut shapes: HashMap<u8, Shape> = HashMap::new();
+
+        let mut edit = WorldEdit::new();
+        for (position, voxel) in self.voxels(materials) {
+            let center = matrix * (position.as_vec3() + Vec3::splat(0.5)) - offset;
+            let shape = *shapes
+                .entry(ShapeDescriptor::from(voxel.shape).0)
+                .or_insert_with(|| {
+                    voxel
+                        .shape
+                        .transformed(matrix)
+                        .expect("blueprint transforms map axes onto axes")
+                });
+            edit.set(
+                origin + (center - Vec3::splat(0.5)).round().as_ivec3(),
+                VoxelDescriptor {
+                    shape,
+                    material: voxel.material,
+                },
+            );
+        }
+        edit
+    }
+
+    /// Write the blueprint to the assets folder, named after it. Returns the written file.
+    pub fn save(&self) -> Result<PathBuf, BlueprintError> {
+        let path = self.asset_path(BLUEPRINT_EXTENSION)?;
+        std::fs::write(&path, ron::to_string(self)?)?;
+        Ok(path)
+    }
+
+    /// File of the blueprint folder named after the blueprint, creating the folder if needed. Names that could lead
+    /// out of the folder are refused.
+    fn asset_path(&self, extension: &str) -> Result<PathBuf, BlueprintError> {
+        if self.name.is_empty() || self.name.contains(['/', '\\']) || self.name.contains("..") {
+            return Err(BlueprintError::Name(self.name.clone()));
+        }
+        let folder = FileAssetReader::get_base_path()
+            .join("assets")
+            .join(BLUEPRINT_FOLDER);
+        std::fs::create_dir_all(&folder)?;
+        Ok(folder.join(format!("{}.{extension}", self.name)))
+    }
+}
+
+/// How a blueprint is pasted, mirrored then turned.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct BlueprintTransform {
+    /// Quarter turns around the vertical axis.
+    pub turns: u8,
+    /// Along the X axis.
+    pub mirrored: bool,
+}
+
+impl BlueprintTransform {
+    pub fn matrix(&self) -> Mat3 {
+        let mirror = if self.mirrored {
+            Mat3::from_diagonal(Vec3::new(-1.0, 1.0, 1.0))
+        } else {
+            Mat3::IDENTITY
+        };
+        let rotation = Mat3::from_rotation_y(-FRAC_PI_2 * (self.turns % 4) as f32);
+        // Round away the floating point error so positions stay whole
+        Mat3::from_cols_array(&(rotation * mirror).to_cols_array().map(f32::round))
+    }
+}
+
+#[derive(Debug)]
+pub enum BlueprintError {
+    Io(std::io::Error),
+    Ron(ron::Error),
+    /// A palette entry isn't a valid shape.
+    Shape(u8),
+    /// The name can't be used as a file name.
+    Name(String),
+    Vox(&'static str),
+}
+
+impl Display for BlueprintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read or write blueprint: {error}"),
+            Self::Ron(error) => write!(f, "could not parse blueprint: {error}"),
+            Self::Shape(shape) => write!(f, "invalid blueprint shape {shape}"),
+            Self::Name(name) => write!(f, "invalid blueprint name {name:?}"),
+            Self::Vox(error) => write!(f, "invalid vox model: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for BlueprintError {}
+
+impl From<std::io::Error> for BlueprintError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::Error> for BlueprintError {
+    fn from(error: ron::Error) -> Self {
+        Self::Ron(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for BlueprintError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Ron(error.code)
+    }
+}
+
+#[derive(Default)]
+pub struct BlueprintLoader;
+
+impl AssetLoader for BlueprintLoader {
+    type Asset = Blueprint;
+    type Settings = ();
+    type Error = BlueprintError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let blueprint: Blueprint = ron::de::from_bytes(&bytes)?;
+            if let Some(entry) = blueprint
+                .palette
+                .iter()
+                .find(|entry| entry.shape >> 5 > 6 || entry.shape & 0b1_1111 >= 24)
+            {
+                return Err(BlueprintError::Shape(entry.shape));
+            }
+            Ok(blueprint)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[BLUEPRINT_EXTENSION]
+    }
+}
+
+pub struct BlueprintPlugin;
+
+impl Plugin for BlueprintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Blueprint>()
+            .init_asset_loader::<BlueprintLoader>()
+            .init_asset_loader::<VoxLoader>()
+            .add_systems(
+                PreUpdate,
+                Self::update_vox_table.run_if(resource_exists_and_changed::<MaterialRegistry>()),
+            );
+    }
+}
+
+impl BlueprintPlugin {
+    fn update_vox_table(registry: Res<MaterialRegistry>, table: Res<RegistryVoxMaterialTable>) {
+        table.update(&registry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, IVec3, UVec3};
+
+    use crate::world::{
+        chunk::{ChunkSize, Coordinates, Terrain},
+        edit::WorldEdit,
+        voxel::{
+            material::{
+                registry::{MaterialProperties, MaterialRegistry, Opacity},
+                FaceTextures, Material,
+            },
+            shape::{Rotation, Shape, Volume},
+            VoxelDescriptor,
+        },
+        World,
+    };
+
+    use super::{Blueprint, BlueprintError, BlueprintTransform};
+
+    fn materials() -> MaterialRegistry {
+        let properties = |name: &str| MaterialProperties {
+            name: name.to_string(),
+            color: [1.0; 4],
+            textures: FaceTextures::default(),
+            roughness: 1.0,
+            emissive: [0.0; 3],
+            hardness: 0.0,
+            transparency: 0.0,
+            opacity: Opacity::Opaque,
+        };
+        MaterialRegistry::new(vec![properties("stone"), properties("dirt")])
+    }
+
+    /// A single chunk of air.
+    fn world() -> World {
+        let mut world = World::new(ChunkSize::new(UVec3::splat(8)));
+        let coordinates = Coordinates(IVec3::ZERO);
+        world.spawn_chunk(Entity::PLACEHOLDER, coordinates);
+        world.get_chunk(coordinates).unwrap().write().terrain = Some(Terrain::new(UVec3::splat(8)));
+        world
+    }
+
+    fn voxel(world: &World, position: IVec3) -> VoxelDescriptor {
+        world.get_voxel(position).unwrap().into()
+    }
+
+    #[test]
+    fn pasted_copy_matches_the_copied_voxels() {
+        let materials = materials();
+        let world = world();
+        let (min, max) = (IVec3::new(1, 1, 1), IVec3::new(3, 2, 3));
+        let mut edit = WorldEdit::new();
+        edit.set(
+            IVec3::new(1, 1, 1),
+            VoxelDescriptor {
+                shape: Shape::FULL,
+                material: Material { id: 0 },
+            },
+        );
+        edit.set(
+            IVec3::new(1, 1, 2),
+            VoxelDescriptor {
+                shape: Shape::FULL,
+                material: Material { id: 0 },
+            },
+        );
+        edit.set(
+            IVec3::new(2, 2, 3),
+            VoxelDescriptor {
+                shape: Shape::new(Rotation::FacingEast90Degrees, Volume::TwoSixth),
+                material: Material { id: 1 },
+            },
+        );
+        edit.apply(&world, &materials);
+
+        let blueprint = Blueprint::copy("test".to_string(), &world, &materials, min, max);
+        assert_eq!(blueprint.size, [3, 2, 3]);
+        assert_eq!(blueprint.palette.len(), 3);
+        let runs = blueprint.runs.iter().map(|(count, _)| count).sum::<u32>();
+        assert_eq!(runs, 18);
+        assert!(blueprint.runs.len() < 18);
+
+        let blueprint: Blueprint = ron::from_str(&ron::to_string(&blueprint).unwrap()).unwrap();
+        let offset = IVec3::new(4, 0, 0);
+        blueprint
+            .paste(min + offset, BlueprintTransform::default(), &materials)
+            .apply(&world, &materials);
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    let position = IVec3::new(x, y, z);
+                    assert_eq!(
+                        voxel(&world, position + offset),
+                        voxel(&world, position),
+                        "{position}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn names_leading_out_of_the_blueprint_folder_are_refused() {
+        for name in ["", "../house", "houses/house", "houses\\house", ".."] {
+            let blueprint =
+                Blueprint::from_voxels(name.to_string(), UVec3::ONE, [None].into_iter());
+            assert!(matches!(
+                blueprint.asset_path("vox"),
+                Err(BlueprintError::Name(_))
+            ));
+        }
+    }
+}
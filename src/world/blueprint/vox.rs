@@ -0,0 +1,469 @@
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::world::voxel::{
+    material::{registry::MaterialRegistry, STONE},
+    shape::{Shape, ShapeDescriptor, Volume},
+};
+
+use super::{Blueprint, BlueprintError, PaletteEntry};
+
+const VOX_VERSION: i32 = 150;
+/// MagicaVoxel models are at most this many voxels long on every axis.
+const VOX_MAX_SIZE: u32 = 256;
+
+/// Materials of the voxels of MagicaVoxel models, by palette index. Used as the settings of the `.vox` loader, which
+/// can be changed with a `.meta` file next to the model. Models loaded with an empty table use the registry one.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct VoxMaterialTable {
+    /// Palette index 0 is for empty cells, it can't be given a material.
+    pub materials: BTreeMap<u8, String>,
+    /// Material of the palette indices missing from the table. Their voxels are skipped without one.
+    pub default: Option<String>,
+}
+
+impl VoxMaterialTable {
+    /// The registry materials in order from palette index 1, the other indices being stone.
+    pub fn from_registry(registry: &MaterialRegistry) -> Self {
+        Self {
+            materials: (1..=255)
+                .zip(registry.iter())
+                .map(|(index, (_, properties))| (index, properties.name.clone()))
+                .collect(),
+            default: registry
+                .get(STONE)
+                .map(|properties| properties.name.clone()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.materials.is_empty() && self.default.is_none()
+    }
+
+    fn material(&self, index: u8) -> Option<&String> {
+        self.materials.get(&index).or(self.default.as_ref())
+    }
+}
+
+/// Little endian reader of the chunks of a `.vox` file.
+struct VoxReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> VoxReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], BlueprintError> {
+        if self.bytes.len() < count {
+            return Err(BlueprintError::Vox("unexpected end of file"));
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, BlueprintError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Next chunk id and content. Its children are the chunks that follow.
+    fn chunk(&mut self) -> Result<(&'a [u8], VoxReader<'a>), BlueprintError> {
+        let id = self.take(4)?;
+        let content_size = self.u32()? as usize;
+        let _children_size = self.u32()?;
+        let content = self.take(content_size)?;
+        Ok((id, VoxReader { bytes: content }))
+    }
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+    bytes.extend_from_slice(id);
+    bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(content);
+    bytes.extend_from_slice(children);
+}
+
+// MagicaVoxel models have the Z axis going up, their Y axis is turned into the world's negative Z axis so models
+// aren't mirrored.
+impl Blueprint {
+    /// Blueprint of the first model of a `.vox` file. Cells without a voxel are left as they are when pasting.
+    pub fn from_vox(
+        name: String,
+        bytes: &[u8],
+        table: &VoxMaterialTable,
+    ) -> Result<Self, BlueprintError> {
+        let mut reader = VoxReader { bytes };
+        if reader.take(4)? != b"VOX " {
+            return Err(BlueprintError::Vox("not a vox file"));
+        }
+        let _version = reader.u32()?;
+        let (id, _) = reader.chunk()?;
+        if id != b"MAIN" {
+            return Err(BlueprintError::Vox("missing main chunk"));
+        }
+
+        let mut size = None;
+        let mut cells = None;
+        while !reader.bytes.is_empty() && cells.is_none() {
+            let (id, mut content) = reader.chunk()?;
+            match id {
+                b"SIZE" => {
+                    let model_size = UVec3::new(content.u32()?, content.u32()?, content.u32()?);
+                    if model_size.cmpgt(UVec3::splat(VOX_MAX_SIZE)).any() {
+                        return Err(BlueprintError::Vox("model too large"));
+                    }
+                    size = Some(model_size);
+                }
+                b"XYZI" => {
+                    let Some(size) = size else { return Err(BlueprintError::Vox("voxels before size")) };
+                    let mut indices = vec![0; (size.x * size.y * size.z) as usize];
+                    for _ in 0..content.u32()? {
+                        let [x, y, z, index] = content.take(4)?.try_into().unwrap();
+                        let [x, y, z] = [x, y, z].map(u32::from);
+                        if x >= size.x || y >= size.y || z >= size.z {
+                            return Err(BlueprintError::Vox("voxel outside of the model"));
+                        }
+                        indices[((x * size.y + y) * size.z + z) as usize] = index;
+                    }
+                    cells = Some((size, indices));
+                }
+                _ => {}
+            }
+        }
+        let Some((vox_size, indices)) = cells else { return Err(BlueprintError::Vox("missing voxels")) };
+
+        let size = UVec3::new(vox_size.x, vox_size.z, vox_size.y);
+        let voxels = (0..size.x).flat_map(move |x| {
+            (0..size.y).flat_map(move |y| (0..size.z).map(move |z| UVec3::new(x, y, z)))
+        });
+        let voxels = voxels.map(|position| {
+            let (x, y, z) = (position.x, size.z - 1 - position.z, position.y);
+            let index = indices[((x * vox_size.y + y) * vox_size.z + z) as usize];
+            if index == 0 {
+                return None;
+            }
+            Some(PaletteEntry {
+                shape: ShapeDescriptor::from(Shape::FULL).0,
+                material: table.material(index)?.clone(),
+            })
+        });
+        Ok(Self::from_voxels(name, size, voxels))
+    }
+
+    /// Write the blueprint as a `.vox` model made of full cubes, keeping the voxels of at least `min_volume`.
+    /// Materials missing from the table are given the free palette indices, colored like the material.
+    pub fn to_vox(
+        &self,
+        table: &VoxMaterialTable,
+        materials: &MaterialRegistry,
+        min_volume: Volume,
+    ) -> Result<Vec<u8>, BlueprintError> {
+        if table.materials.contains_key(&0) {
+            return Err(BlueprintError::Vox("palette index 0 is for empty cells"));
+        }
+        let [length, height, depth] = self.size;
+        if [length, height, depth]
+            .iter()
+            .any(|size| *size > VOX_MAX_SIZE)
+        {
+            return Err(BlueprintError::Vox("models are at most 256 voxels long"));
+        }
+
+        let mut palette = table.materials.clone();
+        let palette_indices: Vec<Option<u8>> = self
+            .palette
+            .iter()
+            .map(|entry| {
+                let shape: Shape = ShapeDescriptor(entry.shape).into();
+                if (shape.volume as u8) < min_volume as u8 || shape.volume == Volume::ZeroSixth {
+                    return None;
+                }
+                let known = palette.iter().find(|(_, name)| **name == entry.material);
+                known.map(|(index, _)| *index).or_else(|| {
+                    let index = (1..=255).find(|index| !palette.contains_key(index))?;
+                    palette.insert(index, entry.material.clone());
+                    Some(index)
+                })
+            })
+            .collect();
+
+        let mut xyzi = vec![];
+        let mut count = 0u32;
+        for (i, index) in self.indices().enumerate() {
+            let Some(index) = index
+                .checked_sub(1)
+                .and_then(|index| palette_indices[index as usize])
+            else { continue };
+            let position = self.position(i);
+            xyzi.extend_from_slice(&[
+                position.x as u8,
+                (depth - 1 - position.z) as u8,
+                position.y as u8,
+                index,
+            ]);
+            count += 1;
+        }
+
+        // Palette colors are shifted by one, the color of index 0 being unused
+        let mut rgba = vec![0u8; 256 * 4];
+        for (index, material) in palette.iter() {
+            let color = materials
+                .find(material)
+                .and_then(|material| materials.get(material))
+                .map(|properties| {
+                    let [r, g, b, a] = properties.color;
+                    Color::rgba_linear(r, g, b, a).as_rgba_u8()
+                })
+                .unwrap_or([255; 4]);
+            let offset = (*index as usize - 1) * 4;
+            rgba[offset..offset + 4].copy_from_slice(&color);
+        }
+
+        let mut children = vec![];
+        let size: Vec<u8> = [length, depth, height]
+            .iter()
+            .flat_map(|size| size.to_le_bytes())
+            .collect();
+        write_chunk(&mut children, b"SIZE", &size, &[]);
+        write_chunk(
+            &mut children,
+            b"XYZI",
+            &[count.to_le_bytes().as_slice(), &xyzi].concat(),
+            &[],
+        );
+        write_chunk(&mut children, b"RGBA", &rgba, &[]);
+
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&VOX_VERSION.to_le_bytes());
+        write_chunk(&mut bytes, b"MAIN", &[], &children);
+        Ok(bytes)
+    }
+
+    /// Write the blueprint to the assets folder as a `.vox` model. Returns the written file.
+    pub fn save_vox(
+        &self,
+        table: &VoxMaterialTable,
+        materials: &MaterialRegistry,
+        min_volume: Volume,
+    ) -> Result<PathBuf, BlueprintError> {
+        let path = self.asset_path("vox")?;
+        std::fs::write(&path, self.to_vox(table, materials, min_volume)?)?;
+        Ok(path)
+    }
+}
+
+/// Table built from the material registry, used to export models and to load the ones without a table of their own.
+#[derive(Resource, Clone, Default)]
+pub struct RegistryVoxMaterialTable(Arc<RwLock<VoxMaterialTable>>);
+
+impl RegistryVoxMaterialTable {
+    pub fn get(&self) -> VoxMaterialTable {
+        self.0.read().clone()
+    }
+
+    pub(super) fn update(&self, registry: &MaterialRegistry) {
+        *self.0.write() = VoxMaterialTable::from_registry(registry);
+    }
+}
+
+/// Loads MagicaVoxel models as blueprints named after their file.
+pub struct VoxLoader {
+    registry_table: RegistryVoxMaterialTable,
+}
+
+impl FromWorld for VoxLoader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            registry_table: world
+                .get_resource_or_insert_with(RegistryVoxMaterialTable::default)
+                .clone(),
+        }
+    }
+}
+
+impl AssetLoader for VoxLoader {
+    type Asset = Blueprint;
+    type Settings = VoxMaterialTable;
+    type Error = BlueprintError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let name = load_context
+                .path()
+                .file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if settings.is_empty() {
+                Blueprint::from_vox(name, &bytes, &self.registry_table.get())
+            } else {
+                Blueprint::from_vox(name, &bytes, settings)
+            }
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vox"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bevy::prelude::{Color, UVec3};
+
+    use crate::world::{
+        blueprint::{Blueprint, BlueprintError, PaletteEntry},
+        voxel::{
+            material::{
+                registry::{MaterialProperties, MaterialRegistry, Opacity},
+                FaceTextures,
+            },
+            shape::{Rotation, Shape, ShapeDescriptor, Volume},
+        },
+    };
+
+    use super::{VoxMaterialTable, VoxReader};
+
+    fn materials() -> MaterialRegistry {
+        let properties = |name: &str, color: [f32; 4]| MaterialProperties {
+            name: name.to_string(),
+            color,
+            textures: FaceTextures::default(),
+            roughness: 1.0,
+            emissive: [0.0; 3],
+            hardness: 0.0,
+            transparency: 0.0,
+            opacity: Opacity::Opaque,
+        };
+        MaterialRegistry::new(vec![
+            properties("grass", [0.0, 0.0, 0.0, 1.0]),
+            properties("dirt", [0.0, 0.0, 0.0, 1.0]),
+            properties("stone", [0.0, 1.0, 0.0, 1.0]),
+        ])
+    }
+
+    fn table(materials: &[&str]) -> VoxMaterialTable {
+        VoxMaterialTable {
+            materials: (1..)
+                .zip(materials)
+                .map(|(index, material)| (index, material.to_string()))
+                .collect(),
+            default: None,
+        }
+    }
+
+    /// Blueprint of 2 by 3 by 4 voxels, with the given voxels and the others left as they are.
+    fn blueprint(voxels: &[(UVec3, Shape, &str)]) -> Blueprint {
+        let size = UVec3::new(2, 3, 4);
+        let cells = (0..size.x).flat_map(move |x| {
+            (0..size.y).flat_map(move |y| (0..size.z).map(move |z| UVec3::new(x, y, z)))
+        });
+        let cells = cells.map(|position| {
+            let (_, shape, material) = voxels.iter().find(|(voxel, ..)| *voxel == position)?;
+            Some(PaletteEntry {
+                shape: ShapeDescriptor::from(*shape).0,
+                material: material.to_string(),
+            })
+        });
+        Blueprint::from_voxels("test".to_string(), size, cells)
+    }
+
+    /// Content of the first chunk with the given id among the children of the main chunk.
+    fn chunk<'a>(bytes: &'a [u8], id: &[u8; 4]) -> VoxReader<'a> {
+        let mut reader = VoxReader { bytes: &bytes[8..] };
+        reader.chunk().unwrap();
+        loop {
+            let (chunk_id, content) = reader.chunk().unwrap();
+            if chunk_id == id {
+                return content;
+            }
+        }
+    }
+
+    #[test]
+    fn exported_models_load_back_as_full_cubes() {
+        let materials = materials();
+        let half = Shape::new(Rotation::FacingEast90Degrees, Volume::FourSixth);
+        let sliver = Shape::new(Rotation::FacingNorth0Degrees, Volume::OneSixth);
+        let original = blueprint(&[
+            (UVec3::new(0, 0, 0), Shape::FULL, "grass"),
+            (UVec3::new(1, 2, 3), Shape::FULL, "dirt"),
+            (UVec3::new(0, 1, 2), half, "stone"),
+            (UVec3::new(1, 0, 0), sliver, "grass"),
+        ]);
+
+        let bytes = original
+            .to_vox(&table(&["grass", "dirt"]), &materials, Volume::ThreeSixth)
+            .unwrap();
+
+        // Vox models are Z up, their Y axis going along the world negative Z axis
+        let mut size = chunk(&bytes, b"SIZE");
+        assert_eq!(
+            [size.u32(), size.u32(), size.u32()].map(Result::unwrap),
+            [2, 4, 3]
+        );
+        let mut xyzi = chunk(&bytes, b"XYZI");
+        let count = xyzi.u32().unwrap();
+        let mut cells: Vec<[u8; 4]> = (0..count)
+            .map(|_| xyzi.take(4).unwrap().try_into().unwrap())
+            .collect();
+        cells.sort();
+        // Stone was missing from the table and is given the first free index, and the sliver is left out
+        assert_eq!(cells, [[0, 1, 1, 3], [0, 3, 0, 1], [1, 0, 2, 2]]);
+        let rgba = chunk(&bytes, b"RGBA").bytes;
+        assert_eq!(
+            rgba[8..12],
+            Color::rgba_linear(0.0, 1.0, 0.0, 1.0).as_rgba_u8()
+        );
+
+        let loaded = Blueprint::from_vox(
+            "test".to_string(),
+            &bytes,
+            &table(&["grass", "dirt", "stone"]),
+        )
+        .unwrap();
+        let expected = blueprint(&[
+            (UVec3::new(0, 0, 0), Shape::FULL, "grass"),
+            (UVec3::new(1, 2, 3), Shape::FULL, "dirt"),
+            (UVec3::new(0, 1, 2), Shape::FULL, "stone"),
+        ]);
+        assert_eq!(loaded.size, expected.size);
+        assert_eq!(loaded.palette, expected.palette);
+        assert_eq!(loaded.runs, expected.runs);
+    }
+
+    #[test]
+    fn palette_index_zero_cannot_be_exported() {
+        let table = VoxMaterialTable {
+            materials: BTreeMap::from([(0, "stone".to_string())]),
+            default: None,
+        };
+        let blueprint = blueprint(&[(UVec3::ZERO, Shape::FULL, "stone")]);
+        assert!(matches!(
+            blueprint.to_vox(&table, &materials(), Volume::SixSixth),
+            Err(BlueprintError::Vox(_))
+        ));
+    }
+
+    #[test]
+    fn registry_table_follows_the_registry_order() {
+        let table = VoxMaterialTable::from_registry(&materials());
+        assert_eq!(table.material(1).unwrap(), "grass");
+        assert_eq!(table.material(3).unwrap(), "stone");
+        assert_eq!(table.material(200).unwrap(), "stone");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::world::{
-    chunk::{Grid, CHUNK_SIZE},
+    chunk::Grid,
+    light::LightLevel,
     voxel::{
         material,
         shape::{ShapeDescriptor, Volume, SHAPE_DESCRIPTOR_TO_VOXEL_INDEX_MAP},
@@ -11,23 +12,24 @@ use ndshape::Shape as NdShape;
 
 use super::{Materializator, Terrain};
 
+/// Depth of the grass and dirt covering the terrain, stone being below.
+pub const SURFACE_DEPTH: u32 = 8;
+
 pub struct DefaultMaterializator;
 
 impl Materializator for DefaultMaterializator {
-    fn materialize(&self, chunk: &Grid) -> Terrain {
-        // todo: this is bof
-        let extended_size = CHUNK_SIZE + (UVec3::ONE * 2);
-        let grid_shape = crate::world::chunk::Shape {};
+    fn materialize(&self, chunk: &Grid, size: UVec3) -> Terrain {
+        let terrain_shape = crate::world::chunk::Shape::new(size.to_array());
+        let grid_height = chunk.shape.as_array()[1];
 
-        let mut data: Vec<Option<VoxelDescriptor>> =
-            vec![None; (extended_size.x * extended_size.y * extended_size.z) as usize];
+        let mut data: Vec<Option<VoxelDescriptor>> = vec![None; terrain_shape.size() as usize];
 
-        for x in 0..CHUNK_SIZE.x {
-            for z in 0..CHUNK_SIZE.x {
+        for x in 0..size.x {
+            for z in 0..size.z {
                 let mut depth = 0;
 
-                // reverse iterator to iterate from the surface first
-                for y in (0..CHUNK_SIZE.y).rev() {
+                // reverse iterator to iterate from the surface first, including the rows above the chunk
+                for y in (0..grid_height).rev() {
                     let idx = chunk.shape.linearize([x, y, z]);
                     let shape = chunk.data[idx as usize];
                     let shape_descriptor: ShapeDescriptor = shape.into();
@@ -48,22 +50,24 @@ impl Materializator for DefaultMaterializator {
 
                     let material = if depth <= 2 {
                         material::GRASS
-                    } else if depth <= 8 {
+                    } else if depth <= SURFACE_DEPTH {
                         material::DIRT
                     } else {
                         material::STONE
                     };
 
-                    let block = VoxelDescriptor { shape, material };
-                    data[idx as usize] = Some(block);
+                    if y < size.y {
+                        let block = VoxelDescriptor { shape, material };
+                        data[terrain_shape.linearize([x, y, z]) as usize] = Some(block);
+                    }
                 }
             }
         }
         Terrain {
-            size: CHUNK_SIZE,
-            // extended_size: CHUNK_SIZE + (UVec3::ONE * 2),
+            size,
             voxels: data,
-            shape: grid_shape,
+            light: vec![LightLevel::default(); terrain_shape.size() as usize],
+            shape: terrain_shape,
         }
     }
 }
@@ -1,21 +1,14 @@
-use std::intrinsics::unlikely;
-
-use bevy::prelude::IVec3;
+use bevy::prelude::{IVec3, UVec3};
 use ndshape::Shape as NdShape;
 use noise::{NoiseFn, OpenSimplex};
 
-use crate::world::{
-    chunk::{CHUNK_HEIGHT, CHUNK_LENGTH},
-    voxel::shape::{Shape, Volume, VOXEL_INDEX_TO_SHAPE_MAP},
-};
+use crate::world::chunk::WORLD_HEIGHT;
 
 use super::{Grid, TerrainGenerator};
 
-const WORLD_HEIGHT: f64 = CHUNK_HEIGHT as f64 / 1.1;
-const VALUES_LENGTH: u32 = CHUNK_LENGTH + 1;
-// const VALUES_HEIGHT: u32 = CHUNK_HEIGHT + 1;
+const SURFACE_HEIGHT: f64 = WORLD_HEIGHT as f64 / 1.1;
 
-type MapShape = ndshape::ConstShape2u32<VALUES_LENGTH, VALUES_LENGTH>;
+type MapShape = ndshape::RuntimeShape<u32, 2>;
 
 pub struct HeightNoiseTerrainGenerator {
     origin: IVec3,
@@ -24,12 +17,11 @@ pub struct HeightNoiseTerrainGenerator {
 }
 
 impl HeightNoiseTerrainGenerator {
-    #[allow(dead_code)]
-    pub fn new(origin: IVec3) -> Self {
-        let noise = OpenSimplex::default();
+    pub fn new(origin: IVec3, size: UVec3, seed: u32) -> Self {
+        let noise = OpenSimplex::new(seed);
         let div = 100.0;
 
-        let noise_map_shape = MapShape {};
+        let noise_map_shape = MapShape::new([size.x + 1, size.z + 1]);
         let noise_map: Vec<f32> = (0..noise_map_shape.size())
             .map(|i| {
                 let [x, y] = noise_map_shape.delinearize(i);
@@ -58,32 +50,12 @@ impl TerrainGenerator for HeightNoiseTerrainGenerator {
                 let idx_2 = self.noise_map[self.noise_map_shape.linearize([x, z + 1]) as usize];
                 let idx_3 = self.noise_map[self.noise_map_shape.linearize([x + 1, z + 1]) as usize];
 
-                let idx_0 = ((idx_0 + 1.0) / 2.0 * WORLD_HEIGHT as f32) as u32;
-                let idx_1 = ((idx_1 + 1.0) / 2.0 * WORLD_HEIGHT as f32) as u32;
-                let idx_2 = ((idx_2 + 1.0) / 2.0 * WORLD_HEIGHT as f32) as u32;
-                let idx_3 = ((idx_3 + 1.0) / 2.0 * WORLD_HEIGHT as f32) as u32;
-
-                let index = Self::voxel_idx(&[
-                    self.origin.y as u32 + y < idx_0,
-                    self.origin.y as u32 + y < idx_1,
-                    self.origin.y as u32 + y < idx_2,
-                    self.origin.y as u32 + y < idx_3,
-                    self.origin.y as u32 + y + 1 < idx_0,
-                    self.origin.y as u32 + y + 1 < idx_1,
-                    self.origin.y as u32 + y + 1 < idx_2,
-                    self.origin.y as u32 + y + 1 < idx_3,
-                ]);
+                let idx_0 = ((idx_0 + 1.0) / 2.0 * SURFACE_HEIGHT as f32) as i32;
+                let idx_1 = ((idx_1 + 1.0) / 2.0 * SURFACE_HEIGHT as f32) as i32;
+                let idx_2 = ((idx_2 + 1.0) / 2.0 * SURFACE_HEIGHT as f32) as i32;
+                let idx_3 = ((idx_3 + 1.0) / 2.0 * SURFACE_HEIGHT as f32) as i32;
 
-                // Fill invalid voxels with empty or full voxels depending on the index
-                let mut shape = VOXEL_INDEX_TO_SHAPE_MAP[index as usize];
-                if unlikely(shape.volume == Volume::ZeroSixth && index > 0) {
-                    shape = if index.count_ones() > 4 {
-                        Shape::FULL
-                    } else {
-                        Shape::EMPTY
-                    };
-                }
-                shape
+                Self::column_shape(self.origin.y + y as i32, [idx_0, idx_1, idx_2, idx_3])
             })
             .collect();
         Grid { shape, data }
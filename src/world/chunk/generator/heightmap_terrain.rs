@@ -0,0 +1,283 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    prelude::{IVec3, UVec2, UVec3, Vec2},
+    render::{
+        render_resource::TextureFormat,
+        texture::{CompressedImageFormats, Image, ImageSampler, ImageType},
+    },
+};
+use ndshape::Shape as NdShape;
+use serde::Deserialize;
+
+use crate::world::{
+    chunk::Terrain,
+    voxel::material::{self, registry::MaterialRegistry},
+};
+
+use super::{
+    default_materializator::DefaultMaterializator, Grid, Materializator, TerrainGenerator,
+};
+
+type MapShape = ndshape::RuntimeShape<u32, 2>;
+
+/// Heightmap settings file, next to the images it refers to.
+#[derive(Deserialize, Clone, Debug)]
+pub struct HeightmapSettings {
+    /// Greyscale image of the heights, 8 or 16 bits per pixel.
+    pub image: PathBuf,
+    /// Height in voxels of white pixels.
+    pub scale: f32,
+    /// Height in voxels of black pixels.
+    #[serde(default)]
+    pub offset: f32,
+    /// Width in voxels of a pixel, heights being interpolated in between.
+    #[serde(default = "HeightmapSettings::default_pixel_size")]
+    pub pixel_size: f32,
+    /// Repeat the image around the world instead of stretching its borders.
+    #[serde(default)]
+    pub tiling: bool,
+    /// Greyscale image of the surface materials, of the same size as the heightmap.
+    #[serde(default)]
+    pub mask: Option<PathBuf>,
+    /// Material of the surface where the mask is at least as bright as the key. The default materials are kept below
+    /// the darkest key.
+    #[serde(default)]
+    pub mask_materials: BTreeMap<u8, String>,
+}
+
+impl HeightmapSettings {
+    fn default_pixel_size() -> f32 {
+        1.0
+    }
+}
+
+#[derive(Debug)]
+pub enum HeightmapError {
+    Io(PathBuf, std::io::Error),
+    Ron(ron::Error),
+    Image(PathBuf, String),
+    /// The mask isn't as large as the heightmap.
+    MaskSize(UVec2, UVec2),
+}
+
+impl Display for HeightmapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path, error) => write!(f, "could not read {}: {error}", path.display()),
+            Self::Ron(error) => write!(f, "could not parse heightmap settings: {error}"),
+            Self::Image(path, error) => write!(f, "invalid image {}: {error}", path.display()),
+            Self::MaskSize(heightmap, mask) => {
+                write!(
+                    f,
+                    "mask of {mask} pixels doesn't match heightmap of {heightmap} pixels"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for HeightmapError {}
+
+/// Heights and surface mask decoded from images, shared by every chunk generated from them.
+pub struct Heightmap {
+    settings: HeightmapSettings,
+    size: UVec2,
+    /// Brightness of every pixel from 0 to 1, row by row.
+    heights: Vec<f32>,
+    mask: Option<Vec<u8>>,
+}
+
+impl std::fmt::Debug for Heightmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Heightmap")
+            .field("settings", &self.settings)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Heightmap {
+    /// Read a settings file and the images it refers to, relative to the file.
+    pub fn open(path: &Path) -> Result<Self, HeightmapError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| HeightmapError::Io(path.to_path_buf(), error))?;
+        let settings: HeightmapSettings =
+            ron::from_str(&text).map_err(|error| HeightmapError::Ron(error.code))?;
+        let folder = path.parent().unwrap_or(Path::new(""));
+
+        let (size, heights) = read_image(&folder.join(&settings.image))?;
+        let mask = match &settings.mask {
+            Some(mask) => {
+                let (mask_size, mask) = read_image(&folder.join(mask))?;
+                if mask_size != size {
+                    return Err(HeightmapError::MaskSize(size, mask_size));
+                }
+                Some(
+                    mask.iter()
+                        .map(|value| (value * 255.0).round() as u8)
+                        .collect(),
+                )
+            }
+            None => None,
+        };
+        Ok(Self {
+            settings,
+            size,
+            heights,
+            mask,
+        })
+    }
+
+    /// Index of the pixel under a voxel column, wrapped around or clamped to the borders.
+    fn pixel_index(&self, x: i32, z: i32) -> usize {
+        let (width, depth) = (self.size.x as i32, self.size.y as i32);
+        let (x, z) = if self.settings.tiling {
+            (x.rem_euclid(width), z.rem_euclid(depth))
+        } else {
+            (x.clamp(0, width - 1), z.clamp(0, depth - 1))
+        };
+        (z * width + x) as usize
+    }
+
+    /// Height in voxels of the surface at a voxel corner.
+    pub fn height(&self, x: i32, z: i32) -> f32 {
+        let position = Vec2::new(x as f32, z as f32) / self.settings.pixel_size;
+        let cell = position.floor();
+        let t = position - cell;
+        let (x, z) = (cell.x as i32, cell.y as i32);
+        let [a, b, c, d] = [(x, z), (x + 1, z), (x, z + 1), (x + 1, z + 1)]
+            .map(|(x, z)| self.heights[self.pixel_index(x, z)]);
+        let value = (a * (1.0 - t.x) + b * t.x) * (1.0 - t.y) + (c * (1.0 - t.x) + d * t.x) * t.y;
+        self.settings.offset + value * self.settings.scale
+    }
+
+    /// Mask value of the pixel under a voxel column.
+    fn mask(&self, x: i32, z: i32) -> Option<u8> {
+        let position = Vec2::new(x as f32, z as f32) / self.settings.pixel_size;
+        let index = self.pixel_index(position.x.floor() as i32, position.y.floor() as i32);
+        Some(self.mask.as_ref()?[index])
+    }
+}
+
+/// Brightness from 0 to 1 of the first channel of every pixel of an image.
+fn read_image(path: &Path) -> Result<(UVec2, Vec<f32>), HeightmapError> {
+    let bytes =
+        std::fs::read(path).map_err(|error| HeightmapError::Io(path.to_path_buf(), error))?;
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let image = Image::from_buffer(
+        &bytes,
+        ImageType::Extension(&extension),
+        CompressedImageFormats::NONE,
+        false,
+        ImageSampler::Default,
+    )
+    .map_err(|error| HeightmapError::Image(path.to_path_buf(), error.to_string()))?;
+
+    // 16 bits images are decoded to native endian integers
+    let values = match image.texture_descriptor.format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => image
+            .data
+            .chunks_exact(4)
+            .map(|pixel| pixel[0] as f32 / u8::MAX as f32)
+            .collect(),
+        format
+        @ (TextureFormat::R16Uint | TextureFormat::Rg16Uint | TextureFormat::Rgba16Unorm) => {
+            let stride = format.block_size(None).unwrap_or(2) as usize;
+            image
+                .data
+                .chunks_exact(stride)
+                .map(|pixel| u16::from_ne_bytes([pixel[0], pixel[1]]) as f32 / u16::MAX as f32)
+                .collect()
+        }
+        format => {
+            let error = format!("unsupported format {format:?}");
+            return Err(HeightmapError::Image(path.to_path_buf(), error));
+        }
+    };
+    Ok((image.size(), values))
+}
+
+pub struct HeightmapTerrainGenerator {
+    origin: IVec3,
+    height_map: Vec<i32>,
+    height_map_shape: MapShape,
+}
+
+impl HeightmapTerrainGenerator {
+    pub fn new(origin: IVec3, size: UVec3, heightmap: &Heightmap) -> Self {
+        let height_map_shape = MapShape::new([size.x + 1, size.z + 1]);
+        let height_map: Vec<i32> = (0..height_map_shape.size())
+            .map(|i| {
+                let [x, z] = height_map_shape.delinearize(i);
+                heightmap.height(x as i32 + origin.x, z as i32 + origin.z) as i32
+            })
+            .collect();
+        HeightmapTerrainGenerator {
+            origin,
+            height_map,
+            height_map_shape,
+        }
+    }
+}
+
+impl TerrainGenerator for HeightmapTerrainGenerator {
+    fn generate(&self, shape: crate::world::chunk::Shape) -> Grid {
+        let data = (0..shape.size())
+            .map(|i| {
+                let [x, y, z] = shape.delinearize(i);
+                let heights = [[x, z], [x + 1, z], [x, z + 1], [x + 1, z + 1]].map(|corner| {
+                    self.height_map[self.height_map_shape.linearize(corner) as usize]
+                });
+                Self::column_shape(self.origin.y + y as i32, heights)
+            })
+            .collect();
+        Grid { shape, data }
+    }
+}
+
+/// Materializes like the default materializator, then paints the surface with the materials of the mask.
+pub struct HeightmapMaterializator<'a> {
+    pub heightmap: &'a Heightmap,
+    pub origin: IVec3,
+    pub materials: &'a MaterialRegistry,
+}
+
+impl Materializator for HeightmapMaterializator<'_> {
+    fn materialize(&self, chunk: &Grid, size: UVec3) -> Terrain {
+        let mut terrain = DefaultMaterializator.materialize(chunk, size);
+        let mask_materials: BTreeMap<u8, material::Material> = self
+            .heightmap
+            .settings
+            .mask_materials
+            .iter()
+            .filter_map(|(value, name)| Some((*value, self.materials.find(name)?)))
+            .collect();
+        if self.heightmap.mask.is_none() || mask_materials.is_empty() {
+            return terrain;
+        }
+
+        for x in 0..size.x {
+            for z in 0..size.z {
+                let Some(value) = self
+                    .heightmap
+                    .mask(self.origin.x + x as i32, self.origin.z + z as i32)
+                else { continue };
+                let Some((_, material)) = mask_materials.range(..=value).next_back() else { continue };
+                // The default materializator covers the surface with grass
+                for y in 0..size.y {
+                    let index = terrain.shape.linearize([x, y, z]) as usize;
+                    let Some(voxel) = &mut terrain.voxels[index] else { continue };
+                    if voxel.material == material::GRASS {
+                        voxel.material = *material;
+                    }
+                }
+            }
+        }
+        terrain
+    }
+}
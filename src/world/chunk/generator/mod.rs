@@ -1,10 +1,23 @@
-use crate::world::voxel::shape::Shape;
+use std::{intrinsics::unlikely, sync::Arc};
+
+use bevy::prelude::{IVec3, Resource, UVec3};
+
+use crate::world::voxel::shape::{Shape, Volume, VOXEL_INDEX_TO_SHAPE_MAP};
+
+use self::{
+    height_noise_terrain::HeightNoiseTerrainGenerator,
+    heightmap_terrain::{Heightmap, HeightmapTerrainGenerator},
+    noise_terrain_generator::NoiseTerrainGenerator,
+    shape_showcase::ShapeShowcaseGenerator,
+};
 
 use super::{Terrain, VoxelIndex};
 
 // SHape generators
 pub mod height_noise_terrain;
+pub mod heightmap_terrain;
 pub mod noise_terrain_generator;
+pub mod shape_showcase;
 
 // Materializators
 pub mod default_materializator;
@@ -26,13 +39,74 @@ pub trait TerrainGenerator {
         idx |= (bits[7] as u8) << 7;
         idx
     }
+
+    /// Shape of the voxel at height `y` of a column whose four vertical edges are filled up to `heights`, making
+    /// smooth slopes between them.
+    fn column_shape(y: i32, heights: [i32; 4]) -> Shape {
+        let index = Self::voxel_idx(&[
+            y < heights[0],
+            y < heights[1],
+            y < heights[2],
+            y < heights[3],
+            y + 1 < heights[0],
+            y + 1 < heights[1],
+            y + 1 < heights[2],
+            y + 1 < heights[3],
+        ]);
+
+        // Fill invalid voxels with empty or full voxels depending on the index
+        let mut shape = VOXEL_INDEX_TO_SHAPE_MAP[index as usize];
+        if unlikely(shape.volume == Volume::ZeroSixth && index > 0) {
+            shape = if index.count_ones() > 4 {
+                Shape::FULL
+            } else {
+                Shape::EMPTY
+            };
+        }
+        shape
+    }
 }
 
+/// Generated shapes of a chunk. It can be taller than the chunk, the voxels above telling materializators how deep
+/// the top voxels of the chunk are.
 pub struct Grid {
     shape: super::Shape,
     pub data: Vec<Shape>,
 }
 
 pub trait Materializator {
-    fn materialize(&self, chunk: &Grid) -> Terrain;
+    /// Terrain of the chunk of `size` at the bottom of the grid.
+    fn materialize(&self, chunk: &Grid, size: UVec3) -> Terrain;
+}
+
+/// Selects the shape generator used for every newly loaded chunk.
+#[derive(Resource, Clone, Debug)]
+pub enum TerrainGeneratorKind {
+    Noise { seed: u32 },
+    HeightNoise { seed: u32 },
+    ShapeShowcase,
+    Heightmap(Arc<Heightmap>),
+}
+
+impl Default for TerrainGeneratorKind {
+    fn default() -> Self {
+        Self::Noise { seed: 0 }
+    }
+}
+
+impl TerrainGeneratorKind {
+    /// Shapes of the `size` voxels from `origin`.
+    pub fn generate(&self, origin: IVec3, size: UVec3) -> Grid {
+        let shape = super::Shape::new(size.to_array());
+        match self {
+            Self::Noise { seed } => NoiseTerrainGenerator::new(origin, size, *seed).generate(shape),
+            Self::HeightNoise { seed } => {
+                HeightNoiseTerrainGenerator::new(origin, size, *seed).generate(shape)
+            }
+            Self::ShapeShowcase => ShapeShowcaseGenerator::new(origin).generate(shape),
+            Self::Heightmap(heightmap) => {
+                HeightmapTerrainGenerator::new(origin, size, heightmap).generate(shape)
+            }
+        }
+    }
 }
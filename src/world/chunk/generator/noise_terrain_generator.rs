@@ -1,21 +1,19 @@
 use std::intrinsics::unlikely;
 
-use bevy::prelude::IVec3;
+use bevy::prelude::{IVec3, UVec3};
 use interpolation::lerp;
 use ndshape::Shape as NdShape;
 use noise::{Cache, NoiseFn, SuperSimplex};
 
 use crate::world::{
-    chunk::{CHUNK_HEIGHT, CHUNK_LENGTH},
+    chunk::WORLD_HEIGHT,
     voxel::shape::{Shape, Volume, VOXEL_INDEX_TO_SHAPE_MAP},
 };
 
 use super::{Grid, TerrainGenerator};
 
-const WORLD_HEIGHT: f64 = CHUNK_HEIGHT as f64 / 1.5;
-const VALUES_LENGTH: u32 = CHUNK_LENGTH + 1;
-const VALUES_HEIGHT: u32 = CHUNK_HEIGHT + 1;
-type ValuesShape = ndshape::ConstShape3u32<VALUES_LENGTH, VALUES_HEIGHT, VALUES_LENGTH>;
+const SURFACE_HEIGHT: f64 = WORLD_HEIGHT as f64 / 1.5;
+type ValuesShape = ndshape::RuntimeShape<u32, 3>;
 
 pub struct NoiseTerrainGenerator {
     origin: IVec3,
@@ -24,11 +22,12 @@ pub struct NoiseTerrainGenerator {
 }
 
 impl NoiseTerrainGenerator {
-    pub fn new(origin: IVec3) -> Self {
-        let noise = Cache::<SuperSimplex>::new(SuperSimplex::new(0));
+    pub fn new(origin: IVec3, size: UVec3, seed: u32) -> Self {
+        let noise = Cache::<SuperSimplex>::new(SuperSimplex::new(seed));
         // arbitrary scale
         let div = 100.0;
-        let values_shape = ValuesShape {};
+        // Values are at the corners of the voxels
+        let values_shape = ValuesShape::new((size + UVec3::ONE).to_array());
 
         // TODO: maybe fill by linearizing instead of delinearizing as it cost less
         let values: Vec<f32> = (0..values_shape.size())
@@ -59,12 +58,12 @@ impl TerrainGenerator for NoiseTerrainGenerator {
                 let lower_height_treshold = lerp(
                     &-1.0,
                     &1.0,
-                    &((self.origin.y + y as i32) as f32 / WORLD_HEIGHT as f32),
+                    &((self.origin.y + y as i32) as f32 / SURFACE_HEIGHT as f32),
                 );
                 let higher_height_treshold = lerp(
                     &-1.0,
                     &1.0,
-                    &((self.origin.y + y as i32 + 1) as f32 / WORLD_HEIGHT as f32),
+                    &((self.origin.y + y as i32 + 1) as f32 / SURFACE_HEIGHT as f32),
                 );
                 let idx_0 = self.values[self.values_shape.linearize([x, y, z]) as usize];
                 let idx_1 = self.values[self.values_shape.linearize([x + 1, y, z]) as usize];
@@ -0,0 +1,177 @@
+use bevy::prelude::{IVec3, Vec3};
+use ndshape::Shape as NdShape;
+
+use crate::world::voxel::shape::{Rotation, Shape, Volume};
+
+use super::{Grid, TerrainGenerator};
+
+// Layout of the showcase world, everything sits on top of a flat floor:
+// - The showcase grid: one row per non-empty volume, one column per rotation, every shape separated by a one voxel gap
+// - Three adjacency blocks (X, Y and Z axes) where every shape is paired with every other shape along that axis.
+//   Rows are the first shape of the pair, columns the second one.
+const FLOOR_HEIGHT: i32 = 32;
+const SHOWCASE_PITCH: i32 = 2;
+const ADJACENCY_PITCH: i32 = 3;
+const ADJACENCY_ORIGIN_Z: i32 = 16;
+const ADJACENCY_BLOCK_GAP: i32 = 8;
+
+const SHAPE_COUNT: i32 = 6 * 24;
+const ADJACENCY_BLOCK_LENGTH: i32 = SHAPE_COUNT * ADJACENCY_PITCH + ADJACENCY_BLOCK_GAP;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+const AXES: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+/// A label attached to a showcase cell, in world coordinates.
+pub struct ShowcaseLabel {
+    pub position: Vec3,
+    pub text: String,
+}
+
+/// Deterministic debug world laying out every `Volume` in every `Rotation`, plus every pair of shapes next to each
+/// other along the three axes. Face culling issues between shapes are easy to spot this way.
+pub struct ShapeShowcaseGenerator {
+    origin: IVec3,
+}
+
+impl ShapeShowcaseGenerator {
+    pub fn new(origin: IVec3) -> Self {
+        Self { origin }
+    }
+
+    /// Every non-empty shape, in showcase order: volumes first, then rotations.
+    pub fn shapes() -> impl Iterator<Item = Shape> {
+        (0..SHAPE_COUNT).map(Self::shape_from_index)
+    }
+
+    fn shape_from_index(index: i32) -> Shape {
+        Shape::new(
+            Rotation::from((index % 24) as u8),
+            Volume::from((index / 24 + 1) as u8),
+        )
+    }
+
+    pub fn shape_at(position: IVec3) -> Shape {
+        if position.y <= FLOOR_HEIGHT {
+            return Shape::FULL;
+        }
+        if position.x < 0 || position.z < 0 {
+            return Shape::EMPTY;
+        }
+
+        if position.z < ADJACENCY_ORIGIN_Z {
+            return Self::showcase_shape_at(position);
+        }
+
+        let block_position = position.z - ADJACENCY_ORIGIN_Z;
+        let Some(axis) = AXES.get((block_position / ADJACENCY_BLOCK_LENGTH) as usize) else {
+            return Shape::EMPTY;
+        };
+        Self::adjacency_shape_at(
+            *axis,
+            IVec3::new(
+                position.x,
+                position.y - FLOOR_HEIGHT - 1,
+                block_position % ADJACENCY_BLOCK_LENGTH,
+            ),
+        )
+    }
+
+    fn showcase_shape_at(position: IVec3) -> Shape {
+        if position.y != FLOOR_HEIGHT + 1
+            || position.x % SHOWCASE_PITCH != 0
+            || position.z % SHOWCASE_PITCH != 0
+        {
+            return Shape::EMPTY;
+        }
+        let rotation = position.x / SHOWCASE_PITCH;
+        let volume = position.z / SHOWCASE_PITCH;
+        if rotation >= 24 || volume >= 6 {
+            return Shape::EMPTY;
+        }
+        Self::shape_from_index(volume * 24 + rotation)
+    }
+
+    /// `position` is relative to the adjacency block origin, y being 0 right above the floor.
+    fn adjacency_shape_at(axis: Axis, position: IVec3) -> Shape {
+        let (column, row) = (position.x / ADJACENCY_PITCH, position.z / ADJACENCY_PITCH);
+        if column >= SHAPE_COUNT || row >= SHAPE_COUNT {
+            return Shape::EMPTY;
+        }
+        let cell_position = IVec3::new(
+            position.x % ADJACENCY_PITCH,
+            position.y,
+            position.z % ADJACENCY_PITCH,
+        );
+        let second = match axis {
+            Axis::X => IVec3::new(1, 0, 0),
+            Axis::Y => IVec3::new(0, 1, 0),
+            Axis::Z => IVec3::new(0, 0, 1),
+        };
+
+        if cell_position == IVec3::ZERO {
+            Self::shape_from_index(row)
+        } else if cell_position == second {
+            Self::shape_from_index(column)
+        } else {
+            Shape::EMPTY
+        }
+    }
+
+    pub fn labels() -> Vec<ShowcaseLabel> {
+        let label_offset = Vec3::new(0.5, 1.5, 0.5);
+        let mut labels = Vec::new();
+
+        for (index, shape) in Self::shapes().enumerate() {
+            let index = index as i32;
+            let position = IVec3::new(
+                (index % 24) * SHOWCASE_PITCH,
+                FLOOR_HEIGHT + 1,
+                (index / 24) * SHOWCASE_PITCH,
+            );
+            labels.push(ShowcaseLabel {
+                position: position.as_vec3() + label_offset,
+                text: format!("{:?}\n{:?}", shape.volume, shape.rotation),
+            });
+        }
+
+        for (axis_index, axis) in AXES.iter().enumerate() {
+            let block_z = ADJACENCY_ORIGIN_Z + axis_index as i32 * ADJACENCY_BLOCK_LENGTH;
+            for (index, shape) in Self::shapes().enumerate() {
+                let offset = index as i32 * ADJACENCY_PITCH;
+                let text = format!("{:?} {:?}\n{:?}", axis, shape.volume, shape.rotation);
+                // Row header, first shape of the pair
+                labels.push(ShowcaseLabel {
+                    position: IVec3::new(-2, FLOOR_HEIGHT + 1, block_z + offset).as_vec3()
+                        + label_offset,
+                    text: text.clone(),
+                });
+                // Column header, second shape of the pair
+                labels.push(ShowcaseLabel {
+                    position: IVec3::new(offset, FLOOR_HEIGHT + 1, block_z - 2).as_vec3()
+                        + label_offset,
+                    text,
+                });
+            }
+        }
+
+        labels
+    }
+}
+
+impl TerrainGenerator for ShapeShowcaseGenerator {
+    fn generate(&self, shape: crate::world::chunk::Shape) -> Grid {
+        let data = (0..shape.size())
+            .map(|i| {
+                let [x, y, z] = shape.delinearize(i);
+                Self::shape_at(self.origin + IVec3::new(x as i32, y as i32, z as i32))
+            })
+            .collect();
+        Grid { shape, data }
+    }
+}
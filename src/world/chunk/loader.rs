@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 
 #[cfg(feature = "debug")]
 use super::{GenerationDuration, MeshingDuration};
@@ -6,12 +6,16 @@ use super::{GenerationDuration, MeshingDuration};
 use crate::debug::stats::Average;
 
 use crate::world::{
-    chunk, Chunk, World, WorldChunk, WorldSimulationSystemSet, WorldTasksSystemSet,
+    chunk, voxel::material::registry::MaterialRegistry, Chunk, World, WorldChunk,
+    WorldSimulationSystemSet, WorldTasksSystemSet,
 };
 
 use super::{
+    generator::TerrainGeneratorKind,
+    material::TerrainMaterials,
+    mesh::occlusion::AmbientOcclusion,
     tasks::{self, AsyncPool, ComputePool},
-    State, CHUNK_LENGTH, CHUNK_SIZE,
+    ChunkSize, State,
 };
 
 pub struct ChunkLoaderPlugin {
@@ -24,13 +28,28 @@ impl Plugin for ChunkLoaderPlugin {
         app.add_systems(
             Update,
             (
-                (Self::load_chunks, Self::unload_chunks, Self::mesh_chunks)
+                (
+                    Self::load_chunks.run_if(resource_exists::<MaterialRegistry>()),
+                    Self::unload_chunks,
+                    Self::mesh_chunks.run_if(resource_exists::<MaterialRegistry>()),
+                )
                     .chain()
                     .in_set(WorldSimulationSystemSet),
                 (Self::mesh_dirty_chunks)
+                    .run_if(resource_exists::<MaterialRegistry>())
                     .after(WorldSimulationSystemSet)
                     .before(WorldTasksSystemSet),
-                (Chunk::handle_generation_tasks, Chunk::handle_meshing_tasks)
+                Chunk::update_terrain_material
+                    .run_if(resource_exists_and_changed::<MaterialRegistry>())
+                    .before(Self::mesh_dirty_chunks),
+                Chunk::update_ambient_occlusion
+                    .run_if(resource_changed::<AmbientOcclusion>())
+                    .before(Self::mesh_dirty_chunks),
+                (
+                    Chunk::handle_generation_tasks.run_if(resource_exists::<MaterialRegistry>()),
+                    Chunk::handle_lighting_tasks,
+                    Chunk::handle_meshing_tasks.run_if(resource_exists::<TerrainMaterials>()),
+                )
                     .in_set(WorldTasksSystemSet),
             ),
         )
@@ -64,12 +83,18 @@ impl ChunkLoaderPlugin {
         mut commands: Commands,
         source: Query<(&Transform, &ChunkLoaderSource)>,
         render_distance: Res<RenderDistance>,
+        generator: Res<TerrainGeneratorKind>,
+        registry: Res<MaterialRegistry>,
         mut world: ResMut<World>,
     ) {
-        let load_distance = render_distance.load_distance / CHUNK_LENGTH;
         let Ok((source_transform, _)) = source.get_single() else { return };
-        let mut coordinates =
-            Self::chunk_coordinates_within_range(source_transform.translation, load_distance);
+        let mut coordinates: Vec<chunk::Coordinates> = Self::chunk_coordinates_within_range(
+            source_transform.translation,
+            render_distance.load_distance,
+            world.chunk_size,
+        )
+        .into_iter()
+        .collect();
 
         // TODO: might do absolutely nothing
         coordinates.sort_by(|a, b| {
@@ -84,7 +109,13 @@ impl ChunkLoaderPlugin {
                 let mut chunk_entity = commands.spawn((chunk::Marker,));
                 world.spawn_chunk(chunk_entity.id(), chunk_coordinates);
                 let chunk = world.get_chunk(chunk_coordinates).unwrap().clone();
-                let task = chunk::tasks::new_generate_chunk_task(chunk, chunk_coordinates);
+                let task = chunk::tasks::new_generate_chunk_task(
+                    chunk,
+                    chunk_coordinates,
+                    world.chunk_size,
+                    generator.clone(),
+                    registry.clone(),
+                );
                 chunk_entity.insert(chunk::tasks::AsyncGenerateChunk(task));
             }
         }
@@ -94,8 +125,13 @@ impl ChunkLoaderPlugin {
         mut commands: Commands,
         queued_chunks: Query<(Entity, With<tasks::MeshChunk<AsyncPool>>)>,
         world: Res<crate::world::World>,
+        registry: Res<MaterialRegistry>,
+        ambient_occlusion: Res<AmbientOcclusion>,
     ) {
-        let queued_chunks_entities = queued_chunks.iter().map(|c| c.0).collect::<Vec<Entity>>();
+        let queued_chunks_entities = queued_chunks
+            .iter()
+            .map(|c| c.0)
+            .collect::<HashSet<Entity>>();
         let generated_chunks = world
             .chunks
             .values()
@@ -110,7 +146,8 @@ impl ChunkLoaderPlugin {
             let task = tasks::new_mesh_chunk_task::<AsyncPool>(
                 chunk.clone(),
                 adjacent_chunks,
-                chunk.read().coordinates,
+                registry.clone(),
+                *ambient_occlusion,
             );
             commands
                 .entity(chunk.read().entity)
@@ -124,10 +161,12 @@ impl ChunkLoaderPlugin {
         render_distance: Res<RenderDistance>,
         mut world: ResMut<World>,
     ) {
-        let unload_distance = render_distance.unload_distance / CHUNK_LENGTH;
         let Ok((source_transform, _)) = source.get_single() else { return };
-        let coordinates =
-            Self::chunk_coordinates_within_range(source_transform.translation, unload_distance);
+        let coordinates = Self::chunk_coordinates_within_range(
+            source_transform.translation,
+            render_distance.unload_distance,
+            world.chunk_size,
+        );
         let out_of_range = world
             .chunks
             .extract_if(|k, _v| !coordinates.contains(k))
@@ -135,12 +174,17 @@ impl ChunkLoaderPlugin {
             .collect::<Vec<WorldChunk>>();
         for chunk in out_of_range {
             let chunk = chunk.read();
-            commands.entity(chunk.entity).despawn();
+            commands.entity(chunk.entity).despawn_recursive();
             world.remove_chunk(chunk.coordinates);
         }
     }
 
-    fn mesh_dirty_chunks(mut commands: Commands, world: Res<crate::world::World>) {
+    fn mesh_dirty_chunks(
+        mut commands: Commands,
+        world: Res<crate::world::World>,
+        registry: Res<MaterialRegistry>,
+        ambient_occlusion: Res<AmbientOcclusion>,
+    ) {
         let dirty_chunks = world
             .chunks
             .values()
@@ -154,7 +198,8 @@ impl ChunkLoaderPlugin {
             let task = tasks::new_mesh_chunk_task::<AsyncPool>(
                 chunk.clone(),
                 adjacent_chunks,
-                chunk.read().coordinates,
+                registry.clone(),
+                *ambient_occlusion,
             );
             commands
                 .entity(chunk.read().entity)
@@ -162,23 +207,26 @@ impl ChunkLoaderPlugin {
         }
     }
 
-    fn chunk_coordinates_within_range(source: Vec3, radius: u32) -> Vec<chunk::Coordinates> {
-        let mut chunks = Vec::new();
-        let source_coordinates = Vec3::new(
-            source.x / CHUNK_SIZE.x as f32,
-            0.0,
-            source.z / CHUNK_SIZE.z as f32,
-        );
-        let current_chunk = (source / CHUNK_SIZE.as_vec3()).as_ivec3();
-
-        for x in (current_chunk.x - radius as i32)..(current_chunk.x + radius as i32) {
-            for z in (current_chunk.z - radius as i32)..(current_chunk.z + radius as i32) {
-                let chunk_coordinates = IVec3::new(x, 0, z);
-                let chunk_middle = chunk_coordinates.as_vec3() + Vec3::ONE / 2.0;
-                let distance_squared = (chunk_middle - source_coordinates).length_squared();
-
-                if distance_squared < (radius * radius) as f32 {
-                    chunks.push(chunk::Coordinates(chunk_coordinates));
+    /// Every layer of chunks whose middle is within `distance` voxels of the source horizontally.
+    fn chunk_coordinates_within_range(
+        source: Vec3,
+        distance: u32,
+        chunk_size: ChunkSize,
+    ) -> HashSet<chunk::Coordinates> {
+        let mut chunks = HashSet::new();
+        let size = chunk_size.as_vec3();
+        let current_chunk = chunk_size.coordinates(source.floor().as_ivec3()).0;
+        let radius = (Vec2::splat(distance as f32) / size.xz()).ceil().as_ivec2();
+
+        for x in (current_chunk.x - radius.x)..=(current_chunk.x + radius.x) {
+            for z in (current_chunk.z - radius.y)..=(current_chunk.z + radius.y) {
+                let chunk_middle = (Vec2::new(x as f32, z as f32) + Vec2::ONE / 2.0) * size.xz();
+                let distance_squared = (chunk_middle - source.xz()).length_squared();
+
+                if distance_squared < (distance * distance) as f32 {
+                    for y in 0..chunk_size.layers() {
+                        chunks.insert(chunk::Coordinates(IVec3::new(x, y, z)));
+                    }
                 }
             }
         }
@@ -3,20 +3,120 @@ use bevy::{
     prelude::*,
     render::{
         mesh::MeshVertexAttribute,
-        render_resource::{AsBindGroup, VertexBufferLayout, VertexStepMode},
+        render_resource::{
+            AsBindGroup, Buffer, BufferInitDescriptor, BufferUsages, ShaderDefVal, ShaderType,
+            VertexBufferLayout, VertexStepMode,
+        },
+        renderer::{RenderDevice, RenderQueue},
     },
 };
 
+use crate::world::voxel::material::{registry::MaterialRegistry, ATLAS_TILE_COUNT};
+
 pub type TerrainMaterial = ExtendedMaterial<StandardMaterial, StandardMaterialExtension>;
 
-pub const ATTRIBUTE_VOXEL_ID: MeshVertexAttribute = MeshVertexAttribute::new(
-    "VertexId",
-    10461101531982422,
-    bevy::render::render_resource::VertexFormat::Uint32,
+pub const TERRAIN_ATLAS_PATH: &str = "blocks/atlas.png";
+
+/// Every terrain vertex in two words, see `TerrainVertex::pack` for the layout decoded by the terrain shaders.
+pub const ATTRIBUTE_PACKED_VERTEX: MeshVertexAttribute = MeshVertexAttribute::new(
+    "PackedVertex",
+    10461101531982425,
+    bevy::render::render_resource::VertexFormat::Uint32x2,
 );
 
+/// Terrain material and the daylight its instances share.
+pub struct TerrainMaterialPlugin;
+
+impl Plugin for TerrainMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<TerrainMaterial>::default());
+    }
+
+    fn finish(&self, app: &mut App) {
+        // The render device only exists once the render plugin is finished
+        app.init_resource::<TerrainDaylight>();
+    }
+}
+
+/// Shared terrain materials, created once the material registry is loaded.
+/// Opaque and cutout voxels are alpha tested, translucent voxels are blended in their own mesh.
+#[derive(Resource)]
+pub struct TerrainMaterials {
+    pub opaque: Handle<TerrainMaterial>,
+    pub translucent: Handle<TerrainMaterial>,
+}
+
+/// GPU side of `MaterialProperties`, indexed by voxel id in the terrain shader.
+#[derive(ShaderType, Clone, Debug)]
+pub struct VoxelMaterialProperties {
+    pub color: Vec4,
+    pub emissive: Vec4,
+    pub roughness: f32,
+    pub transparency: f32,
+}
+
+/// Scales the sky light baked in the terrain, between 0.0 (night) and 1.0 (day). Terrain materials bind its buffer
+/// rather than holding the value, it changes every frame and changing a material rebuilds its bind group.
+#[derive(Resource)]
+pub struct TerrainDaylight {
+    daylight: f32,
+    buffer: Buffer,
+}
+
+impl FromWorld for TerrainDaylight {
+    fn from_world(world: &mut World) -> Self {
+        let daylight = 1.0;
+        let buffer =
+            world
+                .resource::<RenderDevice>()
+                .create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("terrain_daylight_buffer"),
+                    contents: &f32::to_le_bytes(daylight),
+                    usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                });
+        Self { daylight, buffer }
+    }
+}
+
+impl TerrainDaylight {
+    /// Upload the daylight if it changed.
+    pub fn set(&mut self, daylight: f32, queue: &RenderQueue) {
+        if daylight != self.daylight {
+            queue.write_buffer(&self.buffer, 0, &f32::to_le_bytes(daylight));
+            self.daylight = daylight;
+        }
+    }
+}
+
 #[derive(Asset, AsBindGroup, TypePath, Debug, Clone)]
-pub struct StandardMaterialExtension {}
+pub struct StandardMaterialExtension {
+    #[storage(100, read_only)]
+    pub materials: Vec<VoxelMaterialProperties>,
+    /// `TerrainDaylight` buffer.
+    #[storage(101, read_only, buffer)]
+    pub daylight: Buffer,
+}
+
+impl StandardMaterialExtension {
+    pub fn new(registry: &MaterialRegistry, daylight: &TerrainDaylight) -> Self {
+        Self {
+            materials: Self::voxel_materials(registry),
+            daylight: daylight.buffer.clone(),
+        }
+    }
+
+    pub fn voxel_materials(registry: &MaterialRegistry) -> Vec<VoxelMaterialProperties> {
+        registry
+            .iter()
+            .map(|(_, properties)| VoxelMaterialProperties {
+                color: Vec4::from_array(properties.color),
+                emissive: Vec3::from_array(properties.emissive).extend(1.0),
+                roughness: properties.roughness,
+                transparency: properties.transparency,
+            })
+            .collect()
+    }
+}
 
 impl MaterialExtension for StandardMaterialExtension {
     fn specialize(
@@ -25,7 +125,7 @@ impl MaterialExtension for StandardMaterialExtension {
         layout: &bevy::render::mesh::MeshVertexBufferLayout,
         _key: bevy::pbr::MaterialExtensionKey<Self>,
     ) -> Result<(), bevy::render::render_resource::SpecializedMeshPipelineError> {
-        let vertex_layout = layout.get_layout(&[ATTRIBUTE_VOXEL_ID.at_shader_location(8)])?;
+        let vertex_layout = layout.get_layout(&[ATTRIBUTE_PACKED_VERTEX.at_shader_location(8)])?;
 
         let new_buffer_layout: VertexBufferLayout = VertexBufferLayout {
             array_stride: descriptor.vertex.buffers[0].array_stride,
@@ -38,6 +138,16 @@ impl MaterialExtension for StandardMaterialExtension {
         };
         descriptor.vertex.buffers = [new_buffer_layout].into();
 
+        // Atlas coordinates are decoded from the packed vertices, the prepass needs them for alpha testing
+        let shader_defs = [
+            "VERTEX_UVS".into(),
+            ShaderDefVal::UInt("ATLAS_TILE_COUNT".into(), ATLAS_TILE_COUNT),
+        ];
+        descriptor.vertex.shader_defs.extend(shader_defs.clone());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.extend(shader_defs);
+        }
+
         Ok(())
     }
 
@@ -48,4 +158,8 @@ impl MaterialExtension for StandardMaterialExtension {
     fn fragment_shader() -> bevy::render::render_resource::ShaderRef {
         "shaders/terrain.wgsl".into()
     }
+
+    fn prepass_vertex_shader() -> bevy::render::render_resource::ShaderRef {
+        "shaders/terrain_prepass.wgsl".into()
+    }
 }
@@ -0,0 +1,64 @@
+use bevy::{
+    math::{UVec3, Vec3},
+    prelude::Component,
+    utils::HashMap,
+};
+
+use crate::world::{
+    chunk::Terrain,
+    voxel::{
+        collision::ConvexPart,
+        shape::{ShapeDescriptor, Volume},
+    },
+};
+
+/// Welded triangle mesh of a chunk's surface, relative to the chunk origin. Made of the very triangles the chunk is
+/// rendered with, whatever their material.
+#[derive(Component, Clone, Debug, Default)]
+pub struct CollisionMesh {
+    pub vertices: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+/// Welds vertices as triangles are added, positions being whole numbers.
+#[derive(Default)]
+pub struct CollisionMeshBuilder {
+    mesh: CollisionMesh,
+    vertex_indices: HashMap<UVec3, u32>,
+}
+
+impl CollisionMeshBuilder {
+    pub fn add_triangle(&mut self, triangle: [UVec3; 3]) {
+        let indices = triangle.map(|vertex| {
+            *self.vertex_indices.entry(vertex).or_insert_with(|| {
+                self.mesh.vertices.push(vertex.as_vec3());
+                self.mesh.vertices.len() as u32 - 1
+            })
+        });
+        self.mesh.indices.push(indices);
+    }
+
+    pub fn build(self) -> CollisionMesh {
+        self.mesh
+    }
+}
+
+impl Terrain {
+    /// Convex parts of every voxel with their position in the chunk, to build compound colliders.
+    pub fn convex_parts(&self) -> impl Iterator<Item = (UVec3, &'static ConvexPart)> + '_ {
+        (0..self.size.x)
+            .flat_map(move |x| {
+                (0..self.size.y).flat_map(move |y| (0..self.size.z).map(move |z| UVec3 { x, y, z }))
+            })
+            .filter_map(|position| {
+                let voxel = (*self.voxel_at_pos(position.as_ivec3()))?;
+                (voxel.shape.volume != Volume::ZeroSixth).then_some((position, voxel.shape))
+            })
+            .flat_map(|(position, shape)| {
+                ShapeDescriptor::from(shape)
+                    .convex_parts()
+                    .iter()
+                    .map(move |part| (position, part))
+            })
+    }
+}
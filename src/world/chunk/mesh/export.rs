@@ -0,0 +1,330 @@
+use std::{collections::BTreeMap, fmt::Display, fmt::Write as _, path::Path};
+
+use bevy::{
+    math::{IVec3, Vec3},
+    render::color::Color,
+};
+
+use crate::world::{
+    chunk::{self, ChunkSize},
+    voxel::material::{registry::MaterialRegistry, Material},
+    World,
+};
+
+use super::{occlusion::AmbientOcclusion, ChunkMesh};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_JSON_CHUNK: u32 = 0x4E4F534A;
+const GLB_BIN_CHUNK: u32 = 0x004E4942;
+const GLTF_FLOAT: u32 = 5126;
+const GLTF_UNSIGNED_INT: u32 = 5125;
+const GLTF_ARRAY_BUFFER: u32 = 34962;
+const GLTF_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Triangles of a single material.
+#[derive(Default, Debug)]
+pub struct ExportPrimitive {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+/// Terrain geometry as meshed by `ChunkMesh`, split by material, to be written to OBJ or binary glTF files. Built from
+/// the `World` data alone, so it doesn't need a window or a render device.
+#[derive(Default, Debug)]
+pub struct MeshExport {
+    /// Keyed by material id.
+    pub primitives: BTreeMap<u32, ExportPrimitive>,
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(std::io::Error),
+    /// The file extension is neither `obj` nor `glb`.
+    Format(String),
+    Empty,
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not write export: {error}"),
+            Self::Format(extension) => write!(f, "unknown export format {extension:?}"),
+            Self::Empty => write!(f, "nothing to export"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl MeshExport {
+    /// Mesh the loaded voxels from `min` to `max` included, positions being relative to `min`. Faces against voxels
+    /// outside of the region are hidden as they are in game.
+    pub fn from_region(
+        world: &World,
+        materials: &MaterialRegistry,
+        min: IVec3,
+        max: IVec3,
+    ) -> Self {
+        let mut export = Self::default();
+        for coordinates in chunks_in_region(world.chunk_size, min, max) {
+            let Some(chunk) = world.get_chunk(coordinates) else { continue };
+            if chunk.read().terrain.is_none() {
+                continue;
+            }
+            let origin = chunk.read().absolute_position;
+            let chunk_min = (min - origin).max(IVec3::ZERO).as_uvec3();
+            let chunk_max = (max + IVec3::ONE - origin)
+                .min(world.chunk_size.as_ivec3())
+                .as_uvec3();
+
+            let mut chunk_mesh = ChunkMesh::new(materials.clone(), AmbientOcclusion::default());
+            chunk_mesh.add_voxels(&chunk, world, chunk_min, chunk_max);
+            let offset = (origin - min).as_vec3();
+            for buffers in [chunk_mesh.opaque, chunk_mesh.translucent] {
+                // Every triangle has its own three vertices
+                for vertices in buffers.vertices.chunks(3) {
+                    let primitive = export
+                        .primitives
+                        .entry(vertices[0].material.id)
+                        .or_default();
+                    for vertex in vertices {
+                        primitive.indices.push(primitive.positions.len() as u32);
+                        primitive
+                            .positions
+                            .push((vertex.position.as_vec3() + offset).to_array());
+                        primitive.normals.push(vertex.normal_vec3().to_array());
+                    }
+                }
+            }
+        }
+
+        export
+    }
+
+    /// Mesh a whole chunk, positions being relative to its origin.
+    pub fn from_chunk(
+        world: &World,
+        materials: &MaterialRegistry,
+        coordinates: chunk::Coordinates,
+    ) -> Self {
+        let min = world.chunk_size.origin(coordinates);
+        Self::from_region(
+            world,
+            materials,
+            min,
+            min + world.chunk_size.as_ivec3() - IVec3::ONE,
+        )
+    }
+
+    fn material_name(materials: &MaterialRegistry, id: u32) -> String {
+        materials
+            .get(Material { id })
+            .map(|properties| properties.name.clone())
+            .unwrap_or_else(|| format!("material_{id}"))
+    }
+
+    /// Write to an `.obj` or `.glb` file depending on its extension. With `colors`, primitives are colored like their
+    /// material, OBJ colors being written to a `.mtl` file next to it.
+    pub fn write(
+        &self,
+        path: &Path,
+        materials: &MaterialRegistry,
+        colors: bool,
+    ) -> Result<(), ExportError> {
+        if self.primitives.is_empty() {
+            return Err(ExportError::Empty);
+        }
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "obj" => {
+                let library = colors.then(|| path.with_extension("mtl"));
+                let library_name = library
+                    .as_ref()
+                    .and_then(|library| library.file_name())
+                    .map(|name| name.to_string_lossy().into_owned());
+                std::fs::write(path, self.to_obj(materials, library_name.as_deref()))?;
+                if let Some(library) = library {
+                    std::fs::write(library, self.to_mtl(materials))?;
+                }
+            }
+            "glb" => std::fs::write(path, self.to_glb(materials, colors))?,
+            _ => return Err(ExportError::Format(extension)),
+        }
+        Ok(())
+    }
+
+    /// Wavefront OBJ with an object per material, using `library` for their colors.
+    pub fn to_obj(&self, materials: &MaterialRegistry, library: Option<&str>) -> String {
+        let mut obj = String::new();
+        if let Some(library) = library {
+            writeln!(obj, "mtllib {library}").unwrap();
+        }
+        let mut first_index = 1;
+        for (id, primitive) in self.primitives.iter() {
+            let name = Self::material_name(materials, *id);
+            writeln!(obj, "o {name}").unwrap();
+            if library.is_some() {
+                writeln!(obj, "usemtl {name}").unwrap();
+            }
+            for [x, y, z] in primitive.positions.iter() {
+                writeln!(obj, "v {x} {y} {z}").unwrap();
+            }
+            for [x, y, z] in primitive.normals.iter() {
+                writeln!(obj, "vn {x} {y} {z}").unwrap();
+            }
+            for triangle in primitive.indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|vertex| triangle[vertex] + first_index);
+                writeln!(obj, "f {a}//{a} {b}//{b} {c}//{c}").unwrap();
+            }
+            first_index += primitive.positions.len() as u32;
+        }
+        obj
+    }
+
+    /// Material library of `to_obj`, in sRGB.
+    pub fn to_mtl(&self, materials: &MaterialRegistry) -> String {
+        let mut mtl = String::new();
+        for id in self.primitives.keys() {
+            let [r, g, b, a] = materials
+                .get(Material { id: *id })
+                .map(|properties| {
+                    let [r, g, b, a] = properties.color;
+                    Color::rgba_linear(r, g, b, a).as_rgba_f32()
+                })
+                .unwrap_or([1.0; 4]);
+            writeln!(mtl, "newmtl {}", Self::material_name(materials, *id)).unwrap();
+            writeln!(mtl, "Kd {r} {g} {b}").unwrap();
+            writeln!(mtl, "d {a}").unwrap();
+        }
+        mtl
+    }
+
+    /// Binary glTF with a primitive per material, colored in linear RGBA when `colors` is set.
+    pub fn to_glb(&self, materials: &MaterialRegistry, colors: bool) -> Vec<u8> {
+        let mut buffer: Vec<u8> = vec![];
+        let mut buffer_views = vec![];
+        let mut accessors = vec![];
+        let mut primitives = vec![];
+        let mut gltf_materials = vec![];
+
+        let mut add_view = |buffer: &mut Vec<u8>, bytes: Vec<u8>, target: u32| {
+            buffer_views.push(format!(
+                r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{target}}}"#,
+                buffer.len(),
+                bytes.len()
+            ));
+            buffer.extend(bytes);
+            buffer_views.len() - 1
+        };
+
+        for (index, (id, primitive)) in self.primitives.iter().enumerate() {
+            let floats = |vectors: &Vec<[f32; 3]>| -> Vec<u8> {
+                vectors
+                    .iter()
+                    .flatten()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect()
+            };
+            let (min, max) = primitive.positions.iter().fold(
+                (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                |(min, max), position| {
+                    let position = Vec3::from_array(*position);
+                    (min.min(position), max.max(position))
+                },
+            );
+            let count = primitive.positions.len();
+
+            let view = add_view(&mut buffer, floats(&primitive.positions), GLTF_ARRAY_BUFFER);
+            accessors.push(format!(
+                r#"{{"bufferView":{view},"componentType":{GLTF_FLOAT},"count":{count},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+                min.x, min.y, min.z, max.x, max.y, max.z
+            ));
+            let view = add_view(&mut buffer, floats(&primitive.normals), GLTF_ARRAY_BUFFER);
+            accessors.push(format!(
+                r#"{{"bufferView":{view},"componentType":{GLTF_FLOAT},"count":{count},"type":"VEC3"}}"#
+            ));
+            let indices = primitive
+                .indices
+                .iter()
+                .flat_map(|index| index.to_le_bytes())
+                .collect();
+            let view = add_view(&mut buffer, indices, GLTF_ELEMENT_ARRAY_BUFFER);
+            accessors.push(format!(
+                r#"{{"bufferView":{view},"componentType":{GLTF_UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#,
+                primitive.indices.len()
+            ));
+
+            let first = accessors.len() - 3;
+            primitives.push(format!(
+                r#"{{"attributes":{{"POSITION":{},"NORMAL":{}}},"indices":{},"material":{index}}}"#,
+                first,
+                first + 1,
+                first + 2
+            ));
+
+            let properties = materials.get(Material { id: *id });
+            let color = match properties {
+                Some(properties) if colors => properties.color,
+                _ => [1.0; 4],
+            };
+            let roughness = properties.map_or(1.0, |properties| properties.roughness);
+            gltf_materials.push(format!(
+                r#"{{"name":{:?},"pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},{}],"metallicFactor":0,"roughnessFactor":{roughness}}}}}"#,
+                Self::material_name(materials, *id),
+                color[0],
+                color[1],
+                color[2],
+                color[3]
+            ));
+        }
+
+        let json = format!(
+            r#"{{"asset":{{"version":"2.0"}},"scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"meshes":[{{"primitives":[{}]}}],"materials":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#,
+            primitives.join(","),
+            gltf_materials.join(","),
+            accessors.join(","),
+            buffer_views.join(","),
+            buffer.len()
+        );
+
+        // Chunks are padded to 4 bytes, with spaces for the JSON one
+        let mut json = json.into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+        let mut glb = GLB_MAGIC.to_vec();
+        glb.extend(2u32.to_le_bytes());
+        glb.extend(((12 + 8 + json.len() + 8 + buffer.len()) as u32).to_le_bytes());
+        for (chunk_type, chunk) in [(GLB_JSON_CHUNK, json), (GLB_BIN_CHUNK, buffer)] {
+            glb.extend((chunk.len() as u32).to_le_bytes());
+            glb.extend(chunk_type.to_le_bytes());
+            glb.extend(chunk);
+        }
+        glb
+    }
+}
+
+/// Chunks covering the voxels from `min` to `max` included.
+pub fn chunks_in_region(
+    chunk_size: ChunkSize,
+    min: IVec3,
+    max: IVec3,
+) -> impl Iterator<Item = chunk::Coordinates> {
+    let min = chunk_size.coordinates(min).0;
+    let max = chunk_size.coordinates(max).0;
+    (min.x..=max.x).flat_map(move |x| {
+        (min.y..=max.y).flat_map(move |y| {
+            (min.z..=max.z).map(move |z| chunk::Coordinates(IVec3::new(x, y, z)))
+        })
+    })
+}
@@ -0,0 +1,57 @@
+use bevy::{math::IVec3, utils::HashMap};
+
+use crate::world::{
+    light::{blocks_light, LightChannel, LightLevel, MAX_LIGHT_LEVEL},
+    voxel::{material::registry::MaterialRegistry, VoxelDescriptor},
+    World,
+};
+
+use super::{front_voxel_offsets, MeshBuffers};
+
+impl MeshBuffers {
+    /// Smooth lighting: every vertex gets the average light of the voxels in front of it that light can go through.
+    pub fn bake_light(&mut self, origin: IVec3, world: &World, materials: &MaterialRegistry) {
+        let mut voxel_lights = HashMap::new();
+
+        for vertex in self.vertices.iter_mut() {
+            let corner = origin + vertex.position.as_ivec3();
+
+            let (mut sky, mut block, mut lit_voxels) = (0, 0, 0);
+            for offset in front_voxel_offsets(vertex.normal_vec3()) {
+                let voxel_light = *voxel_lights
+                    .entry(corner + offset)
+                    .or_insert_with(|| Self::voxel_light(corner + offset, world, materials));
+                let Some(voxel_light) = voxel_light else { continue };
+                sky += voxel_light.get(LightChannel::Sky) as u32;
+                block += voxel_light.get(LightChannel::Block) as u32;
+                lit_voxels += 1;
+            }
+
+            vertex.light = if lit_voxels > 0 {
+                let max = (lit_voxels * MAX_LIGHT_LEVEL as u32) as f32;
+                [sky as f32 / max, block as f32 / max]
+            } else {
+                [0.0, 0.0]
+            };
+        }
+    }
+
+    /// Light of a voxel light can go through, `None` for unloaded or light blocking voxels.
+    fn voxel_light(
+        position: IVec3,
+        world: &World,
+        materials: &MaterialRegistry,
+    ) -> Option<LightLevel> {
+        // Above the world is the sky
+        if position.y >= world.chunk_size.sky_height() {
+            let mut light = LightLevel::default();
+            light.set(LightChannel::Sky, MAX_LIGHT_LEVEL);
+            return Some(light);
+        }
+        let voxel = world.get_voxel(position).map(VoxelDescriptor::from);
+        if voxel.is_some_and(|voxel| blocks_light(&voxel, materials)) {
+            return None;
+        }
+        world.get_light(position)
+    }
+}
@@ -1,123 +1,180 @@
-use crate::world::voxel::material::Material;
+use crate::world::voxel::material::{
+    registry::{MaterialRegistry, Opacity},
+    Material,
+};
 use crate::world::voxel::shape::Volume;
 use crate::world::{World, WorldChunk};
 
 use bevy::math::IVec3;
 use bevy::prelude::Mesh;
-use bevy::utils::HashMap;
 use bevy::{
-    math::UVec3,
+    math::{UVec2, UVec3},
     prelude::Vec3,
-    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    render::render_resource::PrimitiveTopology,
 };
 use rand::Rng;
 
-use super::material::ATTRIBUTE_VOXEL_ID;
-use super::Coordinates;
+use self::{
+    collision::{CollisionMesh, CollisionMeshBuilder},
+    occlusion::AmbientOcclusion,
+    vertex::TerrainVertex,
+};
+
+use super::material::ATTRIBUTE_PACKED_VERTEX;
+use super::{ChunkSize, Coordinates};
 
+pub mod collision;
+pub mod export;
+pub mod light;
+pub mod occlusion;
+pub mod vertex;
 pub mod voxel;
 
+// The 8 voxels sharing a corner, relative to the corner
+const CORNER_VOXEL_OFFSETS: [IVec3; 8] = [
+    IVec3::new(-1, -1, -1),
+    IVec3::new(0, -1, -1),
+    IVec3::new(-1, -1, 0),
+    IVec3::new(0, -1, 0),
+    IVec3::new(-1, 0, -1),
+    IVec3::new(0, 0, -1),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(0, 0, 0),
+];
+
+// Voxels whose center is at least this far in front of a face are the ones around its vertices. Axis aligned faces
+// have 4 of them, sloped faces 2 and corner slopes 1, leaving out the voxel the face belongs to.
+const FRONT_VOXEL_THRESHOLD: f32 = 0.4;
+
+/// Voxels around a vertex the face having `normal` looks at, relative to the vertex.
+fn front_voxel_offsets(normal: Vec3) -> impl Iterator<Item = IVec3> {
+    CORNER_VOXEL_OFFSETS.into_iter().filter(move |offset| {
+        (offset.as_vec3() + Vec3::splat(0.5)).dot(normal) >= FRONT_VOXEL_THRESHOLD
+    })
+}
+
+/// Vertices of a mesh, three per triangle.
+#[derive(Default)]
+pub struct MeshBuffers {
+    vertices: Vec<TerrainVertex>,
+}
+
+/// Chunk geometry, split between opaque (and cutout) voxels and translucent voxels which need to be blended.
 #[derive(Default)]
 pub struct ChunkMesh {
-    vertices: Vec<[f32; 3]>,
-    normals: Vec<[f32; 3]>,
-    uvs: Vec<[f32; 2]>,
-    voxel_ids: Vec<u32>,
-    indices: Vec<u32>,
+    opaque: MeshBuffers,
+    translucent: MeshBuffers,
+    collision: CollisionMeshBuilder,
+    materials: MaterialRegistry,
+    ambient_occlusion: AmbientOcclusion,
 }
 
+/// Chunks around a chunk being meshed, baked occlusion and light looking at the voxels around every corner of its
+/// borders.
 pub struct AdjacentChunks {
-    north: WorldChunk,
-    east: WorldChunk,
-    south: WorldChunk,
-    west: WorldChunk,
+    chunk_size: ChunkSize,
+    /// Face, edge and corner neighbours, missing past the top and bottom of the world.
+    neighbours: Vec<WorldChunk>,
 }
 
 impl World {
     pub fn from_adjacent_chunks(chunk: WorldChunk, adjacent_chunks: AdjacentChunks) -> Self {
+        let AdjacentChunks {
+            chunk_size,
+            neighbours,
+        } = adjacent_chunks;
         Self {
-            chunks: HashMap::from([
-                (chunk.read().coordinates, chunk.clone()),
-                (
-                    adjacent_chunks.north.read().coordinates,
-                    adjacent_chunks.north.clone(),
-                ),
-                (
-                    adjacent_chunks.east.read().coordinates,
-                    adjacent_chunks.east.clone(),
-                ),
-                (
-                    adjacent_chunks.south.read().coordinates,
-                    adjacent_chunks.south.clone(),
-                ),
-                (
-                    adjacent_chunks.west.read().coordinates,
-                    adjacent_chunks.west.clone(),
-                ),
-            ]),
+            chunks: std::iter::once(chunk)
+                .chain(neighbours)
+                .map(|chunk| (chunk.read().coordinates, chunk.clone()))
+                .collect(),
+            chunk_size,
         }
     }
 
     pub fn get_adjacent_chunks(&self, chunk: WorldChunk) -> Result<AdjacentChunks, ()> {
         let base_coordinates = chunk.read().coordinates;
-
-        let north = self.get_chunk(base_coordinates + Coordinates(IVec3::new(0, 0, 1)));
-        let east = self.get_chunk(base_coordinates + Coordinates(IVec3::new(1, 0, 0)));
-        let south = self.get_chunk(base_coordinates + Coordinates(IVec3::new(0, 0, -1)));
-        let west = self.get_chunk(base_coordinates + Coordinates(IVec3::new(-1, 0, 0)));
-
-        if let (Some(north), Some(east), Some(south), Some(west)) = (north, east, south, west) {
-            if north.read().terrain.is_some()
-                && east.read().terrain.is_some()
-                && south.read().terrain.is_some()
-                && west.read().terrain.is_some()
-            {
-                Ok(AdjacentChunks {
-                    north,
-                    east,
-                    south,
-                    west,
-                })
-            } else {
-                Err(())
+        let mut neighbours = Vec::with_capacity(26);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let offset = IVec3::new(x, y, z);
+                    // Layers past the top and bottom of the world are never loaded
+                    let layer = base_coordinates.0.y + y;
+                    if offset == IVec3::ZERO || layer < 0 || layer >= self.chunk_size.layers() {
+                        continue;
+                    }
+                    let neighbour = self
+                        .get_chunk(base_coordinates + Coordinates(offset))
+                        .filter(|chunk| chunk.read().terrain.is_some())
+                        .ok_or(())?;
+                    neighbours.push(neighbour);
+                }
             }
-        } else {
-            Err(())
         }
+
+        Ok(AdjacentChunks {
+            chunk_size: self.chunk_size,
+            neighbours,
+        })
     }
 }
 
 impl ChunkMesh {
+    pub fn new(materials: MaterialRegistry, ambient_occlusion: AmbientOcclusion) -> Self {
+        Self {
+            materials,
+            ambient_occlusion,
+            ..Default::default()
+        }
+    }
+
     pub fn mesh_chunk(mut self, chunk: WorldChunk, world: &World) -> Self {
+        let chunk_lock = chunk.read();
+        let size = chunk_lock.terrain.as_ref().unwrap().size;
+        self.add_voxels(&chunk, world, UVec3::ZERO, size);
+
+        let origin = chunk_lock.absolute_position;
+        for buffers in [&mut self.opaque, &mut self.translucent] {
+            buffers.bake_light(origin, world, &self.materials);
+            if self.ambient_occlusion.baked {
+                self.ambient_occlusion
+                    .bake(buffers, origin, world, &self.materials);
+            }
+        }
+        self
+    }
+
+    /// Mesh the voxels of a chunk from `min` included to `max` excluded, relative to the chunk.
+    pub fn add_voxels(&mut self, chunk: &WorldChunk, world: &World, min: UVec3, max: UVec3) {
         let chunk_lock = chunk.read();
         let terrain = &chunk_lock.terrain.as_ref().unwrap();
 
-        for x in 0..terrain.size.x {
-            for z in 0..terrain.size.z {
-                for y in 0..terrain.size.y {
+        for x in min.x..max.x {
+            for z in min.z..max.z {
+                for y in min.y..max.y {
                     let pos = UVec3 { x, y, z };
                     let voxel_mesh = terrain.voxel_mesh_at_pos(pos);
                     if let Some(voxel_mesh) = voxel_mesh {
                         if voxel_mesh.voxel.shape.volume == Volume::ZeroSixth {
                             continue;
                         }
-                        voxel_mesh.mesh(&mut self, chunk.clone(), world);
+                        voxel_mesh.mesh(self, chunk.clone(), world);
                     }
                 }
             }
         }
-        self
     }
 
-    pub fn mesh(self) -> Mesh {
-        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-
-        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.vertices);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
-        // mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
-        mesh.insert_attribute(ATTRIBUTE_VOXEL_ID, self.voxel_ids);
-        mesh.set_indices(Some(Indices::U32(self.indices)));
-        mesh
+    /// Opaque and translucent meshes, the latter being `None` when the chunk has no translucent voxel, and the
+    /// collision mesh of both.
+    pub fn mesh(self) -> (Mesh, Option<Mesh>, CollisionMesh) {
+        let translucent = if self.translucent.vertices.is_empty() {
+            None
+        } else {
+            Some(self.translucent.mesh())
+        };
+        (self.opaque.mesh(), translucent, self.collision.build())
     }
 
     pub fn add_vertices_at_pos(
@@ -134,36 +191,74 @@ impl ChunkMesh {
         // );
 
         for tri in triangles {
-            let tri_vertices = tri
-                .iter()
-                .map(|vertex| (*vertex + pos).as_vec3())
-                .collect::<Vec<Vec3>>();
-            let mut tri_vertices_array = tri_vertices
-                .iter()
-                .map(|vertex| vertex.to_array())
-                .collect::<Vec<[f32; 3]>>();
-            self.vertices.append(&mut tri_vertices_array);
-
-            let normal = Self::normal(tri_vertices[0], tri_vertices[2], tri_vertices[1]);
-            // let normal = (normal + randomize_offset).to_array();
-            let normal = (normal).to_array();
+            self.collision.add_triangle(tri.map(|vertex| vertex + pos));
+            let normal = TerrainVertex::normal(tri[0], tri[2], tri[1]);
+            let tile = self.tile(normal, material);
 
-            // All three vertices should share the same normal because that's how lowpoly works
-            self.normals.append(&mut vec![normal, normal, normal]);
-            self.uvs
-                .append(&mut vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]]);
-            self.voxel_ids
-                .append(&mut vec![material.id, material.id, material.id]);
-            let next_index = match self.indices.last() {
-                Some(n) => n + 1,
-                None => 0,
+            let buffers = if self.materials.opacity(*material) == Opacity::Translucent {
+                &mut self.translucent
+            } else {
+                &mut self.opaque
             };
-            self.indices
-                .append(&mut [next_index, next_index + 1, next_index + 2].into());
+            // All three vertices should share the same normal because that's how lowpoly works
+            buffers
+                .vertices
+                .extend(tri.iter().map(|vertex| TerrainVertex {
+                    position: *vertex + pos,
+                    normal,
+                    uv: Self::uv(*vertex, normal),
+                    tile,
+                    material: *material,
+                    // Unoccluded and lit by the sky until baked
+                    occlusion: 1.0,
+                    light: [1.0, 0.0],
+                }));
         }
     }
 
-    pub fn normal(a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
-        (c - a).cross(b - a).normalize()
+    /// Atlas tile of a face, slanted faces pointing upwards using the top texture so that slopes blend with flat
+    /// ground.
+    pub fn tile(&self, normal: IVec3, material: &Material) -> u32 {
+        let textures = self.materials.textures(*material);
+        let normal = normal.as_vec3().normalize();
+        if normal.y > 0.5 {
+            textures.top
+        } else if normal.y < -0.5 {
+            textures.bottom
+        } else {
+            textures.side
+        }
+    }
+
+    /// Corner of the atlas tile of a vertex relative to its voxel. Faces are projected on the plane closest to their
+    /// normal.
+    pub fn uv(vertex: UVec3, normal: IVec3) -> UVec2 {
+        let abs_normal = normal.abs();
+        if abs_normal.y >= abs_normal.x && abs_normal.y >= abs_normal.z {
+            UVec2::new(vertex.x, vertex.z)
+        } else if abs_normal.x >= abs_normal.z {
+            UVec2::new(vertex.z, 1 - vertex.y)
+        } else {
+            UVec2::new(vertex.x, 1 - vertex.y)
+        }
+    }
+}
+
+impl MeshBuffers {
+    pub fn mesh(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        let vertices: Vec<[u32; 2]> = self.vertices.iter().map(TerrainVertex::pack).collect();
+        mesh.insert_attribute(ATTRIBUTE_PACKED_VERTEX, vertices);
+        // The normal prepass added by screen space ambient occlusion requires mesh normals, which the terrain shaders
+        // still decode from the packed vertices
+        #[cfg(feature = "ssao")]
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            self.vertices
+                .iter()
+                .map(|vertex| vertex.normal_vec3().to_array())
+                .collect::<Vec<_>>(),
+        );
+        mesh
     }
 }
@@ -0,0 +1,91 @@
+use bevy::{
+    math::{IVec3, UVec3},
+    prelude::{ReflectResource, Resource},
+    reflect::Reflect,
+    utils::HashMap,
+};
+
+use crate::world::{
+    voxel::{
+        material::registry::{MaterialRegistry, Opacity},
+        shape::{vertex_to_index, ShapeDescriptor, SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP},
+    },
+    World,
+};
+
+use super::{front_voxel_offsets, MeshBuffers};
+
+/// Per-vertex ambient occlusion baked while meshing chunks, an alternative to the `ssao` feature.
+/// Changing it remeshes every chunk.
+#[derive(Resource, Reflect, Clone, Copy, Debug)]
+#[reflect(Resource)]
+pub struct AmbientOcclusion {
+    pub baked: bool,
+    /// Light removed from a fully occluded vertex, between 0.0 and 1.0
+    pub strength: f32,
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        Self {
+            baked: true,
+            strength: 0.6,
+        }
+    }
+}
+
+impl AmbientOcclusion {
+    pub fn bake(
+        &self,
+        buffers: &mut MeshBuffers,
+        origin: IVec3,
+        world: &World,
+        materials: &MaterialRegistry,
+    ) {
+        let mut corner_masks = HashMap::new();
+
+        for vertex in buffers.vertices.iter_mut() {
+            let corner = origin + vertex.position.as_ivec3();
+            let normal = vertex.normal_vec3();
+
+            let (mut occluded, mut front_voxels) = (0.0, 0);
+            for offset in front_voxel_offsets(normal) {
+                let corner_mask = *corner_masks
+                    .entry(corner + offset)
+                    .or_insert_with(|| Self::corner_mask(corner + offset, world, materials));
+                occluded += Self::corner_solidity(corner_mask, (-offset).as_uvec3());
+                front_voxels += 1;
+            }
+
+            if front_voxels > 0 {
+                vertex.occlusion = 1.0 - self.strength * occluded / front_voxels as f32;
+            }
+        }
+    }
+
+    /// Corners of the voxel at `position` that block light, see-through voxels don't block anything.
+    fn corner_mask(position: IVec3, world: &World, materials: &MaterialRegistry) -> u8 {
+        let Some(voxel) = world.get_voxel(position) else { return 0 };
+        if materials.opacity(voxel.material) != Opacity::Opaque {
+            return 0;
+        }
+        let shape_descriptor: ShapeDescriptor = voxel.shape.into();
+        SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP[shape_descriptor.0 as usize]
+    }
+
+    /// How much of a voxel surrounds one of its corners: the corner itself and the three corners sharing an edge with
+    /// it. A full cube gives 1.0, a slope leaning away from the corner barely anything.
+    fn corner_solidity(corner_mask: u8, corner: UVec3) -> f32 {
+        let neighbours = [
+            corner,
+            UVec3::new(1 - corner.x, corner.y, corner.z),
+            UVec3::new(corner.x, 1 - corner.y, corner.z),
+            UVec3::new(corner.x, corner.y, 1 - corner.z),
+        ];
+        let solid_corners = neighbours
+            .iter()
+            .filter(|neighbour| corner_mask & (1 << vertex_to_index(**neighbour)) != 0)
+            .count();
+        solid_corners as f32 / neighbours.len() as f32
+    }
+}
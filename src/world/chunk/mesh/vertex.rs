@@ -0,0 +1,153 @@
+use bevy::math::{IVec3, UVec2, UVec3, Vec3};
+
+use crate::world::voxel::material::Material;
+
+// Bits of every field of a packed vertex, mirrored by `terrain.wgsl`
+const X_BITS: u32 = 8;
+const Y_BITS: u32 = 9;
+const Z_BITS: u32 = 8;
+const MATERIAL_BITS: u32 = 10;
+const TILE_BITS: u32 = 6;
+const OCCLUSION_BITS: u32 = 4;
+const LIGHT_BITS: u32 = 5;
+
+/// Largest chunk the packed positions can address, voxel corners going up to the chunk size included.
+pub const MAX_CHUNK_SIZE: UVec3 =
+    UVec3::new((1 << X_BITS) - 1, (1 << Y_BITS) - 1, (1 << Z_BITS) - 1);
+
+/// Components of the integer normals go from -2 to 2, the voxel corners every face is made of being next to each
+/// other.
+const NORMAL_RANGE: i32 = 5;
+
+/// Terrain vertex as it is meshed, packed once baked.
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainVertex {
+    /// Voxel corner, relative to the chunk.
+    pub position: UVec3,
+    /// Smallest integer vector along the face normal.
+    pub normal: IVec3,
+    /// Corner of the atlas tile, 0 or 1 on both axes.
+    pub uv: UVec2,
+    pub tile: u32,
+    pub material: Material,
+    /// Light left by baked ambient occlusion, between 0.0 and 1.0.
+    pub occlusion: f32,
+    /// Sky and block light, between 0.0 and 1.0.
+    pub light: [f32; 2],
+}
+
+impl TerrainVertex {
+    /// Normal of the triangle `a`, `b`, `c` as the smallest integer vector.
+    pub fn normal(a: UVec3, b: UVec3, c: UVec3) -> IVec3 {
+        let normal = (c.as_ivec3() - a.as_ivec3()).cross(b.as_ivec3() - a.as_ivec3());
+        let divisor = normal
+            .to_array()
+            .into_iter()
+            .fold(0, |divisor, component| gcd(divisor, component.abs()));
+        normal / divisor.max(1)
+    }
+
+    pub fn normal_vec3(&self) -> Vec3 {
+        self.normal.as_vec3().normalize()
+    }
+
+    /// Both words of the `ATTRIBUTE_PACKED_VERTEX` attribute:
+    /// - x, y and z of the position, then the normal index
+    /// - material, atlas tile, u and v, occlusion, then sky and block light
+    pub fn pack(&self) -> [u32; 2] {
+        let normal = self.normal + IVec3::splat(NORMAL_RANGE / 2);
+        let normal_index = (normal.x * NORMAL_RANGE + normal.y) * NORMAL_RANGE + normal.z;
+        let first = Packer::default()
+            .push(self.position.x, X_BITS)
+            .push(self.position.y, Y_BITS)
+            .push(self.position.z, Z_BITS)
+            .push(normal_index as u32, 32 - X_BITS - Y_BITS - Z_BITS);
+
+        let quantize = |value: f32, bits: u32| {
+            (value.clamp(0.0, 1.0) * ((1 << bits) - 1) as f32).round() as u32
+        };
+        let second = Packer::default()
+            .push(self.material.id, MATERIAL_BITS)
+            .push(self.tile, TILE_BITS)
+            .push(self.uv.x, 1)
+            .push(self.uv.y, 1)
+            .push(quantize(self.occlusion, OCCLUSION_BITS), OCCLUSION_BITS)
+            .push(quantize(self.light[0], LIGHT_BITS), LIGHT_BITS)
+            .push(quantize(self.light[1], LIGHT_BITS), LIGHT_BITS);
+        [first.0, second.0]
+    }
+}
+
+/// Fills a word from its lowest bits.
+#[derive(Default)]
+struct Packer(u32, u32);
+
+impl Packer {
+    fn push(self, value: u32, bits: u32) -> Self {
+        debug_assert!(value < 1 << bits, "{value} doesn't fit in {bits} bits");
+        Self(
+            self.0 | (value & ((1 << bits) - 1)) << self.1,
+            self.1 + bits,
+        )
+    }
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{IVec3, UVec2, UVec3};
+
+    use crate::world::{
+        chunk::mesh::ChunkMesh,
+        voxel::{material::GRASS, shape::SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP},
+    };
+
+    use super::{TerrainVertex, NORMAL_RANGE};
+
+    #[test]
+    fn shape_normals_fit_the_packed_range() {
+        for shape in 0..=u8::MAX {
+            let triangles = &SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP[shape as usize];
+            let mut chunk_mesh = ChunkMesh::default();
+            chunk_mesh.add_vertices_at_pos(UVec3::ONE, triangles, &GRASS);
+            for vertex in chunk_mesh.opaque.vertices {
+                assert!(
+                    vertex.normal.abs().max_element() <= NORMAL_RANGE / 2,
+                    "{shape:#010b} has a normal out of range: {}",
+                    vertex.normal
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pack_fields_in_order() {
+        let vertex = TerrainVertex {
+            position: UVec3::new(255, 511, 3),
+            normal: IVec3::Y,
+            uv: UVec2::X,
+            tile: 2,
+            material: GRASS,
+            occlusion: 1.0,
+            light: [1.0, 0.0],
+        };
+        let [first, second] = vertex.pack();
+        assert_eq!(first & 0xff, 255);
+        assert_eq!((first >> 8) & 0x1ff, 511);
+        assert_eq!((first >> 17) & 0xff, 3);
+        assert_eq!(first >> 25, (2 * 5 + 3) * 5 + 2);
+        assert_eq!(second & 0x3ff, GRASS.id);
+        assert_eq!((second >> 10) & 0x3f, 2);
+        assert_eq!((second >> 16) & 0b11, 0b01);
+        assert_eq!((second >> 18) & 0xf, 15);
+        assert_eq!((second >> 22) & 0x1f, 31);
+        assert_eq!(second >> 27, 0);
+    }
+}
@@ -5,7 +5,7 @@ use crate::world::{
     voxel::{
         material::Material,
         shape::{
-            Shape, ShapeDescriptor, SHAPE_DESCRIPTOR_TO_FACE_FLAGS_MAP,
+            Shape, ShapeDescriptor, FACE_FLAGS_OCCLUSION_MAP, SHAPE_DESCRIPTOR_TO_FACE_FLAGS_MAP,
             SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP,
         },
         Side, VoxelDescriptor,
@@ -46,6 +46,11 @@ impl VoxelMesh {
                 .get_voxel(chunk.read().absolute_position + side.adjacent_position(self.position));
 
             let adjacent_shape = adjacent_voxel
+                .filter(|voxel| {
+                    chunk_mesh
+                        .materials
+                        .occludes(voxel.material, self.voxel.material)
+                })
                 .map(|voxel| voxel.shape)
                 .unwrap_or(Shape::EMPTY);
             let adjacent_side_descriptor =
@@ -69,7 +74,7 @@ pub const SIDES: [Side; 6] = [
 ];
 
 #[derive(Debug)]
-struct SideDescriptor {
+pub struct SideDescriptor {
     side: Side,
     descriptor: u8,
 }
@@ -94,30 +99,10 @@ impl SideDescriptor {
     }
 
     pub fn from_adjacent_sides(side: &SideDescriptor, adjacent_side: &SideDescriptor) -> Self {
-        let result = if adjacent_side.descriptor == 0 {
-            side.descriptor
-        } else {
-            !adjacent_side.descriptor & side.descriptor
-        };
-
-        let result = match result {
-            // x axis
-            0b0001_0101 => 0b0001,
-            0b0101_0001 => 0b0010,
-            0b0101_0100 => 0b0100,
-            0b0100_0101 => 0b1000,
-            // y axis
-            0b1011 => 0b0001,
-            0b0111 => 0b0010,
-            0b1110 => 0b0100,
-            0b1101 => 0b1000,
-            e => e,
-            // TODO: Z ????
-        };
-
         Self {
             side: side.side,
-            descriptor: result,
+            descriptor: FACE_FLAGS_OCCLUSION_MAP[side.descriptor as usize]
+                [adjacent_side.descriptor as usize],
         }
     }
 
@@ -338,3 +323,175 @@ impl SideDescriptor {
         chunk_mesh.add_vertices_at_pos(pos, &vertices, material);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{UVec3, Vec2};
+
+    use crate::world::{
+        chunk::mesh::{vertex::TerrainVertex, ChunkMesh},
+        voxel::{
+            material::GRASS,
+            shape::{Rotation, Shape, ShapeDescriptor, Volume},
+            Side,
+        },
+    };
+
+    use super::{SideDescriptor, SIDES};
+
+    const POSITION: UVec3 = UVec3::ONE;
+    const SAMPLES: u32 = 12;
+
+    fn side_triangles(side_descriptor: &SideDescriptor, position: UVec3) -> Vec<[Vec2; 3]> {
+        let mut chunk_mesh = ChunkMesh::default();
+        side_descriptor.mesh_side(&mut chunk_mesh, position, &GRASS);
+
+        // Project every vertex on the side plane
+        let project = |vertex: &TerrainVertex| {
+            let position = vertex.position.as_vec3();
+            match side_descriptor.side {
+                Side::North | Side::South => Vec2::new(position.x, position.y),
+                Side::Top | Side::Bottom => Vec2::new(position.x, position.z),
+                Side::West | Side::East => Vec2::new(position.y, position.z),
+            }
+        };
+        chunk_mesh
+            .opaque
+            .vertices
+            .chunks(3)
+            .map(|triangle| {
+                [
+                    project(&triangle[0]),
+                    project(&triangle[1]),
+                    project(&triangle[2]),
+                ]
+            })
+            .collect()
+    }
+
+    fn contains(triangles: &[[Vec2; 3]], point: Vec2) -> bool {
+        let edge = |a: Vec2, b: Vec2| (b - a).perp_dot(point - a);
+        triangles.iter().any(|[a, b, c]| {
+            let (ab, bc, ca) = (edge(*a, *b), edge(*b, *c), edge(*c, *a));
+            (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+        })
+    }
+
+    // Sample points avoid the face diagonals, where triangles touch each other
+    fn sample_points(side: Side) -> impl Iterator<Item = Vec2> {
+        let origin = match side {
+            Side::North | Side::South => Vec2::new(POSITION.x as f32, POSITION.y as f32),
+            Side::Top | Side::Bottom => Vec2::new(POSITION.x as f32, POSITION.z as f32),
+            Side::West | Side::East => Vec2::new(POSITION.y as f32, POSITION.z as f32),
+        };
+        (0..SAMPLES * SAMPLES).map(move |i| {
+            origin
+                + Vec2::new(
+                    ((i % SAMPLES) as f32 + 0.37) / SAMPLES as f32,
+                    ((i / SAMPLES) as f32 + 0.61) / SAMPLES as f32,
+                )
+        })
+    }
+
+    #[test]
+    fn adjacent_sides_cull_every_shape_pair() {
+        for shape in 0..=u8::MAX {
+            for adjacent_shape in 0..=u8::MAX {
+                for side in SIDES {
+                    let side_descriptor =
+                        SideDescriptor::from_shape_descriptor(&ShapeDescriptor(shape), side);
+                    let adjacent_side_descriptor = SideDescriptor::from_shape_descriptor(
+                        &ShapeDescriptor(adjacent_shape),
+                        side.opposite(),
+                    );
+                    let result = SideDescriptor::from_adjacent_sides(
+                        &side_descriptor,
+                        &adjacent_side_descriptor,
+                    );
+
+                    assert_eq!(result.side, side);
+                    assert!(
+                        [0b0000, 0b0001, 0b0010, 0b0100, 0b1000, 0b1111]
+                            .contains(&result.descriptor),
+                        "{shape:#010b} against {adjacent_shape:#010b} on {side:?} cannot be meshed: {:#06b}",
+                        result.descriptor
+                    );
+
+                    let adjacent_position = side.adjacent_position(POSITION).as_uvec3();
+                    let face = side_triangles(&side_descriptor, POSITION);
+                    let adjacent_face =
+                        side_triangles(&adjacent_side_descriptor, adjacent_position);
+                    let visible_face = side_triangles(&result, POSITION);
+
+                    let mut hidden = true;
+                    for point in sample_points(side) {
+                        let in_face = contains(&face, point);
+                        let in_adjacent_face = contains(&adjacent_face, point);
+                        let in_visible_face = contains(&visible_face, point);
+                        hidden &= !in_face || in_adjacent_face;
+
+                        assert!(
+                            !in_face || in_adjacent_face || in_visible_face,
+                            "{shape:#010b} against {adjacent_shape:#010b} on {side:?} leaves a gap at {point}"
+                        );
+                        assert!(
+                            in_face || !in_visible_face,
+                            "{shape:#010b} against {adjacent_shape:#010b} on {side:?} meshes outside its face at {point}"
+                        );
+                    }
+                    assert!(
+                        !hidden || result.descriptor == 0,
+                        "{shape:#010b} against {adjacent_shape:#010b} on {side:?} meshes a hidden face"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn identical_full_sides_are_hidden() {
+        for side in SIDES {
+            let full = SideDescriptor {
+                side,
+                descriptor: 0b1111,
+            };
+            let adjacent_full = SideDescriptor {
+                side: side.opposite(),
+                descriptor: 0b1111,
+            };
+            assert_eq!(
+                SideDescriptor::from_adjacent_sides(&full, &adjacent_full).descriptor,
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn four_sixth_north_face_is_folded_inside() {
+        for rotation in 0..24 {
+            let shape_descriptor: ShapeDescriptor =
+                Shape::new(Rotation::from(rotation), Volume::FourSixth).into();
+
+            // Bottom, south and east are full faces, top and west are half faces
+            let half_faces = SIDES
+                .iter()
+                .map(|side| {
+                    match SideDescriptor::from_shape_descriptor(&shape_descriptor, *side).descriptor
+                    {
+                        0b1111 => 2,
+                        0 => 0,
+                        _ => 1,
+                    }
+                })
+                .sum::<u32>();
+            assert_eq!(half_faces, 8, "{:?}", Rotation::from(rotation));
+        }
+
+        let canonical: ShapeDescriptor =
+            Shape::new(Rotation::FacingNorth0Degrees, Volume::FourSixth).into();
+        assert_eq!(
+            SideDescriptor::from_shape_descriptor(&canonical, Side::North).descriptor,
+            0
+        );
+    }
+}
@@ -1,4 +1,10 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    render::{
+        primitives::Aabb,
+        texture::{ImageLoaderSettings, ImageSampler},
+    },
+};
 use derive_more::{Add, Debug, Div, From};
 use futures_lite::future;
 use ndshape::Shape as NdShape;
@@ -9,12 +15,18 @@ use crate::debug::stats::Average;
 
 use self::{
     generator::Grid,
-    material::{StandardMaterialExtension, TerrainMaterial},
+    material::{
+        StandardMaterialExtension, TerrainDaylight, TerrainMaterial, TerrainMaterials,
+        TERRAIN_ATLAS_PATH,
+    },
+    mesh::vertex::MAX_CHUNK_SIZE,
     tasks::{AsyncPool, ComputePool},
+    visibility::FaceConnections,
 };
 
 use super::{
-    voxel::{Voxel, VoxelDescriptor},
+    light::{LightChannel, LightLevel},
+    voxel::{material::registry::MaterialRegistry, Voxel, VoxelDescriptor},
     World,
 };
 
@@ -23,11 +35,56 @@ pub mod loader;
 pub mod material;
 pub mod mesh;
 pub mod tasks;
+pub mod visibility;
+
+/// Height of the world in voxels from y = 0, made of as many layers of chunks as it takes.
+pub const WORLD_HEIGHT: u32 = 128;
+pub const DEFAULT_CHUNK_SIZE: UVec3 = UVec3::new(32, WORLD_HEIGHT, 32);
+pub type Shape = ndshape::RuntimeShape<u32, 3>;
+
+/// Dimensions of the chunks of a world, in voxels. Read from the `World`, every other size derives from it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deref)]
+pub struct ChunkSize(UVec3);
 
-pub const CHUNK_LENGTH: u32 = 32;
-pub const CHUNK_HEIGHT: u32 = 128;
-pub const CHUNK_SIZE: UVec3 = UVec3::new(CHUNK_LENGTH, CHUNK_HEIGHT, CHUNK_LENGTH);
-pub type Shape = ndshape::ConstShape3u32<CHUNK_LENGTH, CHUNK_HEIGHT, CHUNK_LENGTH>;
+impl Default for ChunkSize {
+    fn default() -> Self {
+        Self(DEFAULT_CHUNK_SIZE)
+    }
+}
+
+impl ChunkSize {
+    pub fn new(size: UVec3) -> Self {
+        assert!(size.cmpgt(UVec3::ZERO).all(), "chunks can't be empty");
+        assert!(
+            size.cmple(MAX_CHUNK_SIZE).all(),
+            "chunks can't be larger than {MAX_CHUNK_SIZE}"
+        );
+        Self(size)
+    }
+
+    /// Layers of chunks stacked from y = 0 to make up the world height.
+    pub fn layers(&self) -> i32 {
+        WORLD_HEIGHT.div_ceil(self.y) as i32
+    }
+
+    /// Height of the top of the highest chunks, where the sky begins.
+    pub fn sky_height(&self) -> i32 {
+        self.layers() * self.y as i32
+    }
+
+    pub fn origin(&self, coordinates: Coordinates) -> IVec3 {
+        coordinates.0 * self.as_ivec3()
+    }
+
+    /// Coordinates of the chunk a voxel is in.
+    pub fn coordinates(&self, position: IVec3) -> Coordinates {
+        Coordinates(position.div_euclid(self.as_ivec3()))
+    }
+
+    pub fn shape(&self) -> Shape {
+        Shape::new(self.to_array())
+    }
+}
 
 #[derive(Default, Add, Div, From, Copy, Clone, Debug)]
 #[debug("{_0:?}")]
@@ -43,6 +100,8 @@ pub struct Chunk {
     pub absolute_position: IVec3,
     pub grid: Option<Grid>,
     pub terrain: Option<Terrain>,
+    /// Faces seeing each other through the chunk as of its last meshing.
+    pub visibility: Option<FaceConnections>,
     pub dirty: bool,
 }
 
@@ -61,13 +120,14 @@ impl Chunk {
     }
 
     pub fn get_relative_position(&self, position: IVec3) -> UVec3 {
-        (position - self.coordinates.0 * CHUNK_SIZE.as_ivec3()).as_uvec3()
+        (position - self.absolute_position).as_uvec3()
     }
 
     fn handle_generation_tasks(
         mut commands: Commands,
         mut generation_tasks: Query<(Entity, &mut tasks::AsyncGenerateChunk)>,
         world: Res<World>,
+        registry: Res<MaterialRegistry>,
         #[cfg(feature = "debug")] mut generation_average: ResMut<Average<GenerationDuration>>,
     ) {
         for (entity, mut generation_task) in &mut generation_tasks.iter_mut() {
@@ -75,40 +135,103 @@ impl Chunk {
                 future::block_on(future::poll_once(&mut generation_task.0))
             {
                 let Some(chunk) = world.get_chunk_by_entity(entity) else { continue };
-                let mut lock = chunk.write();
+                let coordinates = chunk.read().coordinates;
+                let Some(neighbourhood) = world.get_chunk_neighbourhood(coordinates) else { continue };
 
                 #[cfg(feature = "debug")]
                 generation_average.add(generation_task.generation_duration);
 
-                lock.state = State::Generated;
-                lock.terrain = Some(generation_task.terrain);
-
+                let task = tasks::new_light_chunk_task(
+                    neighbourhood,
+                    generation_task.terrain,
+                    registry.clone(),
+                );
                 commands
                     .entity(entity)
-                    .remove::<tasks::AsyncGenerateChunk>();
+                    .remove::<tasks::AsyncGenerateChunk>()
+                    .insert(tasks::AsyncLightChunk(task));
             }
         }
     }
 
-    fn handle_meshing_tasks(
+    /// Chunks get their terrain and become `Generated` in the lighting task itself.
+    fn handle_lighting_tasks(
         mut commands: Commands,
-        mut compute_meshing_tasks: Query<(Entity, &mut tasks::MeshChunk<ComputePool>)>,
-        mut async_meshing_tasks: Query<(Entity, &mut tasks::MeshChunk<AsyncPool>)>,
-        mut meshes: ResMut<Assets<Mesh>>,
+        mut lighting_tasks: Query<(Entity, &mut tasks::AsyncLightChunk)>,
+    ) {
+        for (entity, mut lighting_task) in &mut lighting_tasks.iter_mut() {
+            if future::block_on(future::poll_once(&mut lighting_task.0)).is_some() {
+                commands.entity(entity).remove::<tasks::AsyncLightChunk>();
+            }
+        }
+    }
+
+    fn update_terrain_material(
+        mut commands: Commands,
+        registry: Res<MaterialRegistry>,
+        terrain_materials: Option<Res<TerrainMaterials>>,
         mut materials: ResMut<Assets<TerrainMaterial>>,
+        asset_server: Res<AssetServer>,
+        daylight: Res<TerrainDaylight>,
         world: Res<World>,
-        #[cfg(feature = "debug")] mut meshing_average: ResMut<Average<MeshingDuration>>,
     ) {
-        let material = TerrainMaterial {
-            base: StandardMaterial {
+        let Some(terrain_materials) = terrain_materials else {
+            let extension = StandardMaterialExtension::new(&registry, &daylight);
+            let base = StandardMaterial {
+                base_color_texture: Some(asset_server.load_with_settings(
+                    TERRAIN_ATLAS_PATH,
+                    |settings: &mut ImageLoaderSettings| settings.sampler = ImageSampler::nearest(),
+                )),
                 metallic: 0.0,
                 reflectance: 0.0,
                 perceptual_roughness: 1.0,
                 ..default()
-            },
-            extension: StandardMaterialExtension {},
+            };
+            let opaque = materials.add(TerrainMaterial {
+                base: StandardMaterial {
+                    // Cutout textures (leaves) have holes in their alpha channel
+                    alpha_mode: AlphaMode::Mask(0.5),
+                    ..base.clone()
+                },
+                extension: extension.clone(),
+            });
+            let translucent = materials.add(TerrainMaterial {
+                base: StandardMaterial {
+                    alpha_mode: AlphaMode::Blend,
+                    ..base
+                },
+                extension,
+            });
+            commands.insert_resource(TerrainMaterials {
+                opaque,
+                translucent,
+            });
+            return;
         };
 
+        let voxel_materials = StandardMaterialExtension::voxel_materials(&registry);
+        for handle in [&terrain_materials.opaque, &terrain_materials.translucent] {
+            if let Some(material) = materials.get_mut(handle) {
+                material.extension.materials = voxel_materials.clone();
+            }
+        }
+        // Texture tiles are baked in the meshes
+        world.mark_meshed_chunks_dirty();
+    }
+
+    fn update_ambient_occlusion(world: Res<World>) {
+        world.mark_meshed_chunks_dirty();
+    }
+
+    fn handle_meshing_tasks(
+        mut commands: Commands,
+        mut compute_meshing_tasks: Query<(Entity, &mut tasks::MeshChunk<ComputePool>)>,
+        mut async_meshing_tasks: Query<(Entity, &mut tasks::MeshChunk<AsyncPool>)>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        terrain_materials: Res<TerrainMaterials>,
+        world: Res<World>,
+        #[cfg(feature = "debug")] mut meshing_average: ResMut<Average<MeshingDuration>>,
+    ) {
         for (entity, mut meshing_task) in &mut async_meshing_tasks.iter_mut() {
             if let Some(meshing_task) = future::block_on(future::poll_once(&mut meshing_task.0)) {
                 let Some(chunk) = world.get_chunk_by_entity(entity) else { continue };
@@ -120,16 +243,14 @@ impl Chunk {
 
                 lock.state = State::Meshed;
                 lock.dirty = false;
-                entity.insert((MaterialMeshBundle {
-                    mesh: meshes.add(meshing_task.mesh),
-                    material: materials.add(material.clone()),
-                    transform: Transform::from_xyz(
-                        meshing_task.absolute_position.x as f32,
-                        meshing_task.absolute_position.y as f32,
-                        meshing_task.absolute_position.z as f32,
-                    ),
-                    ..default()
-                },));
+                lock.visibility = Some(meshing_task.visibility);
+                Self::insert_meshes(
+                    &mut entity,
+                    meshing_task,
+                    world.chunk_size,
+                    &mut meshes,
+                    &terrain_materials,
+                );
                 entity.remove::<tasks::MeshChunk<AsyncPool>>();
             }
         }
@@ -144,25 +265,70 @@ impl Chunk {
 
                 lock.state = State::Meshed;
                 lock.dirty = false;
-                entity.insert((MaterialMeshBundle {
-                    mesh: meshes.add(meshing_task.mesh),
-                    material: materials.add(material.clone()),
-                    transform: Transform::from_xyz(
-                        meshing_task.absolute_position.x as f32,
-                        meshing_task.absolute_position.y as f32,
-                        meshing_task.absolute_position.z as f32,
-                    ),
-                    ..default()
-                },));
+                lock.visibility = Some(meshing_task.visibility);
+                Self::insert_meshes(
+                    &mut entity,
+                    meshing_task,
+                    world.chunk_size,
+                    &mut meshes,
+                    &terrain_materials,
+                );
                 entity.remove::<tasks::MeshChunk<ComputePool>>();
             }
         }
     }
+
+    /// The opaque mesh lives on the chunk entity with the collision mesh, the translucent one on a child entity so
+    /// that it can be sorted and blended separately. Packed vertices have no position Bevy could compute bounds
+    /// from, both get the bounds of the chunk.
+    fn insert_meshes(
+        entity: &mut bevy::ecs::system::EntityCommands,
+        meshing_task: tasks::MeshChunkResult,
+        chunk_size: ChunkSize,
+        meshes: &mut Assets<Mesh>,
+        terrain_materials: &TerrainMaterials,
+    ) {
+        let aabb = Aabb::from_min_max(Vec3::ZERO, chunk_size.as_vec3());
+        entity.insert((
+            aabb,
+            meshing_task.collision_mesh,
+            MaterialMeshBundle {
+                mesh: meshes.add(meshing_task.mesh),
+                material: terrain_materials.opaque.clone(),
+                transform: Transform::from_xyz(
+                    meshing_task.absolute_position.x as f32,
+                    meshing_task.absolute_position.y as f32,
+                    meshing_task.absolute_position.z as f32,
+                ),
+                ..default()
+            },
+        ));
+        entity.despawn_descendants();
+        if let Some(translucent_mesh) = meshing_task.translucent_mesh {
+            let mesh = meshes.add(translucent_mesh);
+            let material = terrain_materials.translucent.clone();
+            entity.with_children(|parent| {
+                parent.spawn((
+                    TranslucentMarker,
+                    aabb,
+                    MaterialMeshBundle {
+                        mesh,
+                        material,
+                        ..default()
+                    },
+                ));
+            });
+        }
+    }
 }
 
 #[derive(Component)]
 pub struct Marker;
 
+/// Child of a chunk entity holding its translucent voxels.
+#[derive(Component)]
+pub struct TranslucentMarker;
+
 #[derive(Component, PartialEq, Clone, Copy, Eq, Hash, Add, Debug)]
 pub struct Coordinates(pub IVec3);
 
@@ -181,12 +347,28 @@ pub type VoxelIndex = u8;
 pub struct Terrain {
     pub size: UVec3,
     pub voxels: Vec<Option<VoxelDescriptor>>,
-    shape: ndshape::ConstShape3u32<CHUNK_LENGTH, CHUNK_HEIGHT, CHUNK_LENGTH>,
+    pub light: Vec<LightLevel>,
+    shape: Shape,
 }
 
 impl Terrain {
+    /// Unlit terrain of `size` full of air.
+    pub fn new(size: UVec3) -> Self {
+        let shape = Shape::new(size.to_array());
+        let air = VoxelDescriptor {
+            shape: super::voxel::shape::Shape::EMPTY,
+            material: super::voxel::material::GRASS,
+        };
+        Self {
+            size,
+            voxels: vec![Some(air); shape.size() as usize],
+            light: vec![LightLevel::default(); shape.size() as usize],
+            shape,
+        }
+    }
+
     pub fn voxel_at_pos(&self, pos: IVec3) -> &Option<VoxelDescriptor> {
-        if unlikely(pos.cmplt(IVec3::ZERO).any() || pos.cmpge(CHUNK_SIZE.as_ivec3()).any()) {
+        if unlikely(pos.cmplt(IVec3::ZERO).any() || pos.cmpge(self.size.as_ivec3()).any()) {
             return &None;
         }
         self.voxels
@@ -194,6 +376,23 @@ impl Terrain {
             .unwrap()
     }
 
+    pub fn light_at_pos(&self, pos: IVec3) -> Option<LightLevel> {
+        if unlikely(pos.cmplt(IVec3::ZERO).any() || pos.cmpge(self.size.as_ivec3()).any()) {
+            return None;
+        }
+        self.light
+            .get(self.shape.linearize(pos.as_uvec3().to_array()) as usize)
+            .copied()
+    }
+
+    pub fn set_light_at_pos(&mut self, pos: IVec3, channel: LightChannel, level: u8) {
+        if unlikely(pos.cmplt(IVec3::ZERO).any() || pos.cmpge(self.size.as_ivec3()).any()) {
+            return;
+        }
+        let index = self.shape.linearize(pos.as_uvec3().to_array()) as usize;
+        self.light[index].set(channel, level);
+    }
+
     pub fn voxel_at_pos_mut(&mut self, pos: IVec3) -> &mut Option<VoxelDescriptor> {
         self.voxels
             .get_mut(self.shape.linearize(pos.as_uvec3().to_array()) as usize)
@@ -4,16 +4,22 @@ use bevy::{
     prelude::*,
     tasks::{AsyncComputeTaskPool, ComputeTaskPool, Task, TaskPool},
 };
+use futures_lite::future;
 
 use super::{
     generator::{
-        default_materializator::DefaultMaterializator,
-        noise_terrain_generator::NoiseTerrainGenerator, Materializator, TerrainGenerator,
+        default_materializator::{DefaultMaterializator, SURFACE_DEPTH},
+        heightmap_terrain::HeightmapMaterializator,
+        Materializator, TerrainGeneratorKind,
     },
-    mesh::{AdjacentChunks, ChunkMesh},
-    GenerationDuration, MeshingDuration, CHUNK_SIZE,
+    mesh::{collision::CollisionMesh, occlusion::AmbientOcclusion, AdjacentChunks, ChunkMesh},
+    visibility::FaceConnections,
+    ChunkSize, GenerationDuration, MeshingDuration,
+};
+use crate::world::{
+    chunk, light::ChunkNeighbourhood, voxel::material::registry::MaterialRegistry, World,
+    WorldChunk,
 };
-use crate::world::{chunk, World, WorldChunk};
 
 pub struct AsyncGenerateChunkResult {
     pub chunk: WorldChunk,
@@ -24,6 +30,9 @@ pub struct AsyncGenerateChunkResult {
 #[derive(Component)]
 pub struct AsyncGenerateChunk(pub Task<AsyncGenerateChunkResult>);
 
+#[derive(Component)]
+pub struct AsyncLightChunk(pub Task<()>);
+
 pub trait BevyPool {
     fn get<'a>() -> &'a TaskPool;
 }
@@ -46,6 +55,9 @@ impl BevyPool for AsyncPool {
 pub struct MeshChunkResult {
     pub absolute_position: IVec3,
     pub mesh: Mesh,
+    pub translucent_mesh: Option<Mesh>,
+    pub collision_mesh: CollisionMesh,
+    pub visibility: FaceConnections,
     pub meshing_duration: MeshingDuration,
 }
 
@@ -58,25 +70,43 @@ impl<T> MeshChunk<T> {
     }
 }
 
+/// Generate, materialize and light a chunk's terrain on the current thread.
+pub fn generate_terrain(
+    chunk_coordinates: chunk::Coordinates,
+    chunk_size: ChunkSize,
+    generator: &TerrainGeneratorKind,
+    materials: &MaterialRegistry,
+) -> chunk::Terrain {
+    let absolute_position = chunk_size.origin(chunk_coordinates);
+    // Chunks below the top layer are generated a bit higher to know how deep their surface is
+    let under_sky = chunk_coordinates.0.y == chunk_size.layers() - 1;
+    let margin = if under_sky { 0 } else { SURFACE_DEPTH };
+    let grid = generator.generate(absolute_position, *chunk_size + UVec3::Y * margin);
+    let mut terrain = match generator {
+        TerrainGeneratorKind::Heightmap(heightmap) => HeightmapMaterializator {
+            heightmap,
+            origin: absolute_position,
+            materials,
+        }
+        .materialize(&grid, *chunk_size),
+        _ => DefaultMaterializator {}.materialize(&grid, *chunk_size),
+    };
+    terrain.compute_light(materials, under_sky);
+    terrain
+}
+
 pub fn new_generate_chunk_task(
     chunk: WorldChunk,
     chunk_coordinates: chunk::Coordinates,
+    chunk_size: ChunkSize,
+    generator: TerrainGeneratorKind,
+    materials: MaterialRegistry,
 ) -> Task<AsyncGenerateChunkResult> {
     let thread_pool = AsyncComputeTaskPool::get();
 
     thread_pool.spawn(async move {
         let generation_timer = Instant::now();
-
-        let absolute_position = IVec3::new(
-            chunk_coordinates.0.x * CHUNK_SIZE.x as i32,
-            chunk_coordinates.0.y * CHUNK_SIZE.y as i32,
-            chunk_coordinates.0.z * CHUNK_SIZE.z as i32,
-        );
-        let generator = NoiseTerrainGenerator::new(absolute_position);
-        let materializator = DefaultMaterializator {};
-
-        let grid = generator.generate(crate::world::chunk::Shape {});
-        let terrain = materializator.materialize(&grid);
+        let terrain = generate_terrain(chunk_coordinates, chunk_size, &generator, &materials);
 
         let generation_duration = generation_timer.elapsed();
         AsyncGenerateChunkResult {
@@ -87,26 +117,50 @@ pub fn new_generate_chunk_task(
     })
 }
 
+/// Hand a generated terrain over to its chunk and let light flow between it and the chunks around. Both happen under
+/// the same locks, chunks around never see the terrain without its light.
+pub fn new_light_chunk_task(
+    neighbourhood: ChunkNeighbourhood,
+    terrain: chunk::Terrain,
+    materials: MaterialRegistry,
+) -> Task<()> {
+    let thread_pool = AsyncComputeTaskPool::get();
+
+    thread_pool.spawn(async move {
+        let mut light = loop {
+            if let Some(light) = neighbourhood.try_lock(&materials) {
+                break light;
+            }
+            future::yield_now().await;
+        };
+        let chunk = light.chunk();
+        chunk.terrain = Some(terrain);
+        chunk.state = chunk::State::Generated;
+        light.spread_across_borders();
+    })
+}
+
 pub fn new_mesh_chunk_task<T: BevyPool + Send + 'static>(
     chunk: WorldChunk,
     adjacent_chunks: AdjacentChunks,
-    chunk_coordinates: chunk::Coordinates,
+    materials: MaterialRegistry,
+    ambient_occlusion: AmbientOcclusion,
 ) -> Task<MeshChunkResult> {
     T::get().spawn(async move {
-        let absolute_position = IVec3::new(
-            chunk_coordinates.0.x * CHUNK_SIZE.x as i32,
-            chunk_coordinates.0.y * CHUNK_SIZE.y as i32,
-            chunk_coordinates.0.z * CHUNK_SIZE.z as i32,
-        );
+        let absolute_position = chunk.read().absolute_position;
 
         let meshing_timer = Instant::now();
         let world = World::from_adjacent_chunks(chunk.clone(), adjacent_chunks);
-        let mesh = ChunkMesh::default()
+        let visibility = FaceConnections::new(chunk.read().terrain.as_ref().unwrap(), &materials);
+        let (mesh, translucent_mesh, collision_mesh) = ChunkMesh::new(materials, ambient_occlusion)
             .mesh_chunk(chunk.clone(), &world)
             .mesh();
         let meshing_duration = meshing_timer.elapsed();
         MeshChunkResult {
             mesh,
+            translucent_mesh,
+            collision_mesh,
+            visibility,
             absolute_position,
             meshing_duration: meshing_duration.into(),
         }
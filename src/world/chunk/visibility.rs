@@ -0,0 +1,281 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    render::view::{VisibilitySystems, VisibleEntities},
+    utils::{HashMap, HashSet},
+};
+use ndshape::Shape as NdShape;
+
+use crate::{
+    debug::app::DebugApp,
+    world::{light::blocks_light, voxel::material::registry::MaterialRegistry, World},
+};
+
+use super::{Coordinates, Terrain, TranslucentMarker};
+
+// Faces of a chunk, opposite faces being next to each other
+const FACES: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+const fn opposite(face: usize) -> usize {
+    face ^ 1
+}
+
+/// Which faces of a chunk can see each other through the voxels that don't block the view, computed when meshing.
+/// Slopes and see-through materials are looked through, like light goes through them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FaceConnections(u64);
+
+impl FaceConnections {
+    pub const ALL: Self = Self((1 << 36) - 1);
+
+    pub fn new(terrain: &Terrain, materials: &MaterialRegistry) -> Self {
+        let size = terrain.size.as_ivec3();
+        let mut connections = Self(0);
+        let mut visited = vec![false; terrain.voxels.len()];
+        let see_through = |position: IVec3| {
+            terrain
+                .voxel_at_pos(position)
+                .as_ref()
+                .is_none_or(|voxel| !blocks_light(voxel, materials))
+        };
+
+        // Pockets of air not touching any face don't connect anything, floods start from the faces
+        for x in 0..size.x {
+            for y in 0..size.y {
+                for z in 0..size.z {
+                    let start = IVec3::new(x, y, z);
+                    if start.cmpgt(IVec3::ZERO).all() && start.cmplt(size - IVec3::ONE).all() {
+                        continue;
+                    }
+                    let index = terrain.shape.linearize(start.as_uvec3().to_array()) as usize;
+                    if visited[index] || !see_through(start) {
+                        continue;
+                    }
+
+                    visited[index] = true;
+                    let mut faces = 0u8;
+                    let mut queue = VecDeque::from([start]);
+                    while let Some(position) = queue.pop_front() {
+                        for (face, offset) in FACES.iter().enumerate() {
+                            let neighbour = position + *offset;
+                            if neighbour.cmplt(IVec3::ZERO).any() || neighbour.cmpge(size).any() {
+                                faces |= 1 << face;
+                                continue;
+                            }
+                            let index =
+                                terrain.shape.linearize(neighbour.as_uvec3().to_array()) as usize;
+                            if !visited[index] && see_through(neighbour) {
+                                visited[index] = true;
+                                queue.push_back(neighbour);
+                            }
+                        }
+                    }
+
+                    for from in 0..FACES.len() {
+                        for to in 0..FACES.len() {
+                            if faces & (1 << from) != 0 && faces & (1 << to) != 0 {
+                                connections.connect(from, to);
+                            }
+                        }
+                    }
+                    if connections == Self::ALL {
+                        return connections;
+                    }
+                }
+            }
+        }
+        connections
+    }
+
+    fn connect(&mut self, from: usize, to: usize) {
+        self.0 |= 1 << (from * 6 + to);
+    }
+
+    pub fn connected(&self, from: usize, to: usize) -> bool {
+        self.0 & (1 << (from * 6 + to)) != 0
+    }
+}
+
+/// Only render the chunks the camera could see, walking from the chunk it is in through the faces chunks can be seen
+/// through, away from the camera. Chunks hidden behind terrain are left out.
+#[derive(Resource, Reflect, Clone, Copy, Debug)]
+#[reflect(Resource)]
+pub struct ChunkCulling {
+    pub enabled: bool,
+}
+
+impl Default for ChunkCulling {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+pub struct ChunkVisibilityPlugin;
+
+impl Plugin for ChunkVisibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkCulling>()
+            .debug_resource::<ChunkCulling>()
+            .add_systems(
+                PostUpdate,
+                ChunkVisibilityPlugin::cull_chunks.after(VisibilitySystems::CheckVisibility),
+            );
+    }
+}
+
+impl ChunkVisibilityPlugin {
+    /// Leave the chunks hidden from a camera out of the entities it renders. They stay visible to the lights, casting
+    /// shadows into the view, and Bevy already leaves out those outside of the frustum.
+    fn cull_chunks(
+        mut cameras: Query<(&GlobalTransform, &mut VisibleEntities), With<Camera3d>>,
+        translucent_chunks: Query<&Parent, With<TranslucentMarker>>,
+        culling: Res<ChunkCulling>,
+        world: Res<World>,
+    ) {
+        if !culling.enabled {
+            return;
+        }
+        for (transform, mut visible_entities) in &mut cameras {
+            let Some(visible) = Self::visible_chunks(&world, transform.translation()) else { continue };
+            let hidden: HashSet<Entity> = world
+                .chunks
+                .iter()
+                .filter(|(coordinates, _)| !visible.contains(*coordinates))
+                .map(|(_, chunk)| chunk.read().entity)
+                .collect();
+            visible_entities.entities.retain(|entity| {
+                let chunk = translucent_chunks.get(*entity).map_or(*entity, Parent::get);
+                !hidden.contains(&chunk)
+            });
+        }
+    }
+
+    /// Breadth first walk from the camera chunk, never going back towards the camera. Chunks are walked through again
+    /// when entered from another face, which may lead further. `None` when the camera is not in a loaded chunk.
+    fn visible_chunks(world: &World, camera: Vec3) -> Option<HashSet<Coordinates>> {
+        let chunk_size = world.chunk_size;
+        // Above or below the world, the walk starts from the closest layer
+        let mut start = chunk_size.coordinates(camera.floor().as_ivec3());
+        start.0.y = start.0.y.clamp(0, chunk_size.layers() - 1);
+        world.get_chunk(start)?;
+
+        // Faces every chunk was entered from
+        let mut entries = HashMap::from([(start, 0u8)]);
+        let mut queue = VecDeque::from([(start, None, 0u8)]);
+        while let Some((coordinates, entry, directions)) = queue.pop_front() {
+            let connections = world
+                .get_chunk(coordinates)
+                .and_then(|chunk| chunk.read().visibility)
+                .unwrap_or(FaceConnections::ALL);
+            for (face, offset) in FACES.iter().enumerate() {
+                if directions & (1 << opposite(face)) != 0 {
+                    continue;
+                }
+                if entry.is_some_and(|entry| !connections.connected(entry, face)) {
+                    continue;
+                }
+                let neighbour = coordinates + Coordinates(*offset);
+                if neighbour == start || world.get_chunk(neighbour).is_none() {
+                    continue;
+                }
+                let entry_face = 1 << opposite(face);
+                if entries
+                    .get(&neighbour)
+                    .is_some_and(|neighbour_entries| neighbour_entries & entry_face != 0)
+                {
+                    continue;
+                }
+                *entries.entry(neighbour).or_default() |= entry_face;
+                queue.push_back((neighbour, Some(opposite(face)), directions | 1 << face));
+            }
+        }
+        Some(entries.into_keys().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{IVec3, UVec3};
+
+    use crate::world::{
+        chunk::Terrain,
+        voxel::{
+            material::{registry::MaterialRegistry, STONE},
+            shape::Shape,
+            VoxelDescriptor,
+        },
+    };
+
+    use super::{opposite, FaceConnections, FACES};
+
+    /// Face connections of a chunk of air, with stone where `solid` is true.
+    fn connections(solid: impl Fn(IVec3) -> bool) -> FaceConnections {
+        let size = UVec3::splat(8);
+        let mut terrain = Terrain::new(size);
+        for x in 0..size.x as i32 {
+            for y in 0..size.y as i32 {
+                for z in 0..size.z as i32 {
+                    let position = IVec3::new(x, y, z);
+                    if solid(position) {
+                        *terrain.voxel_at_pos_mut(position) = Some(VoxelDescriptor {
+                            shape: Shape::FULL,
+                            material: STONE,
+                        });
+                    }
+                }
+            }
+        }
+        FaceConnections::new(&terrain, &MaterialRegistry::default())
+    }
+
+    fn face(offset: IVec3) -> usize {
+        FACES.iter().position(|face| *face == offset).unwrap()
+    }
+
+    #[test]
+    fn solid_terrain_connects_nothing() {
+        let connections = connections(|_| true);
+        for from in 0..FACES.len() {
+            for to in 0..FACES.len() {
+                assert!(!connections.connected(from, to), "{from} to {to}");
+            }
+        }
+    }
+
+    #[test]
+    fn air_connects_every_face() {
+        let connections = connections(|_| false);
+        assert_eq!(connections, FaceConnections::ALL);
+        for from in 0..FACES.len() {
+            assert_eq!(FACES[opposite(from)], -FACES[from]);
+            for to in 0..FACES.len() {
+                assert!(connections.connected(from, to), "{from} to {to}");
+            }
+        }
+    }
+
+    #[test]
+    fn slab_separates_top_from_bottom() {
+        let connections = connections(|position| position.y == 3);
+        let (top, bottom) = (face(IVec3::Y), face(IVec3::NEG_Y));
+        assert!(!connections.connected(top, bottom));
+        assert!(!connections.connected(bottom, top));
+
+        let sides = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z].map(face);
+        for from in sides {
+            for to in sides {
+                assert!(connections.connected(from, to), "{from} to {to}");
+            }
+            // Both sides of the slab reach the sides of the chunk
+            assert!(connections.connected(top, from) && connections.connected(from, bottom));
+        }
+    }
+}
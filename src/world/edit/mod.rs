@@ -0,0 +1,87 @@
+use bevy::{
+    math::IVec3,
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    chunk,
+    voxel::{material::registry::MaterialRegistry, VoxelDescriptor},
+    World,
+};
+
+/// Edits up to this many voxels are relit voxel by voxel, larger ones relight every chunk they touch.
+const RELIGHT_VOXEL_LIMIT: usize = 256;
+
+/// Voxel replaced by an edit.
+#[derive(Clone, Copy, Debug)]
+pub struct VoxelChange {
+    pub position: IVec3,
+    pub previous: VoxelDescriptor,
+    pub voxel: VoxelDescriptor,
+}
+
+/// Voxel changes applied together, every affected chunk being remeshed once.
+#[derive(Clone, Debug, Default)]
+pub struct WorldEdit {
+    changes: HashMap<IVec3, VoxelDescriptor>,
+}
+
+impl WorldEdit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Change a voxel, replacing any change made to it earlier in the edit.
+    pub fn set(&mut self, position: IVec3, voxel: VoxelDescriptor) {
+        self.changes.insert(position, voxel);
+    }
+
+    /// Write every change to the world, returning the voxels that were replaced. Changes to unloaded voxels and
+    /// changes leaving a voxel as it was are dropped.
+    pub fn apply(self, world: &World, materials: &MaterialRegistry) -> Vec<VoxelChange> {
+        let mut chunk_changes: HashMap<chunk::Coordinates, Vec<(IVec3, VoxelDescriptor)>> =
+            HashMap::new();
+        for (position, voxel) in self.changes {
+            chunk_changes
+                .entry(world.position_to_chunk_coordinates(position))
+                .or_default()
+                .push((position, voxel));
+        }
+
+        let mut replaced = vec![];
+        let mut edited_chunks = HashSet::new();
+        for (coordinates, changes) in chunk_changes {
+            let Some(chunk) = world.get_chunk(coordinates) else { continue };
+            let mut chunk = chunk.write();
+            let origin = chunk.absolute_position;
+            let Some(terrain) = chunk.terrain.as_mut() else { continue };
+
+            let replaced_before = replaced.len();
+            for (position, voxel) in changes {
+                let slot = terrain.voxel_at_pos_mut(position - origin);
+                let Some(previous) = *slot else { continue };
+                if previous != voxel {
+                    *slot = Some(voxel);
+                    replaced.push(VoxelChange {
+                        position,
+                        previous,
+                        voxel,
+                    });
+                }
+            }
+            if replaced.len() > replaced_before {
+                chunk.dirty = true;
+                edited_chunks.insert(coordinates);
+            }
+        }
+
+        if replaced.len() <= RELIGHT_VOXEL_LIMIT {
+            for change in replaced.iter() {
+                world.update_light(change.position, materials);
+            }
+        } else {
+            world.relight_chunks(&edited_chunks, materials);
+        }
+        replaced
+    }
+}
@@ -0,0 +1,684 @@
+use std::collections::VecDeque;
+
+use bevy::{math::IVec3, utils::HashSet};
+use parking_lot::RwLockWriteGuard;
+
+use super::{
+    chunk::{self, Chunk, ChunkSize, Terrain},
+    voxel::{
+        material::registry::{MaterialRegistry, Opacity},
+        shape::Volume,
+        VoxelDescriptor,
+    },
+    World, WorldChunk,
+};
+
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+const NEIGHBOURS: [IVec3; 6] = [
+    IVec3::new(1, 0, 0),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(0, -1, 0),
+    IVec3::new(0, 0, 1),
+    IVec3::new(0, 0, -1),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightChannel {
+    /// Light coming from above the world. Goes straight down without fading.
+    Sky,
+    /// Light emitted by emissive materials.
+    Block,
+}
+
+const CHANNELS: [LightChannel; 2] = [LightChannel::Sky, LightChannel::Block];
+
+/// Sky and block light levels of a voxel, between 0 and `MAX_LIGHT_LEVEL`, packed in a byte.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct LightLevel(u8);
+
+impl LightLevel {
+    pub fn get(&self, channel: LightChannel) -> u8 {
+        match channel {
+            LightChannel::Sky => self.0 >> 4,
+            LightChannel::Block => self.0 & 0b1111,
+        }
+    }
+
+    pub fn set(&mut self, channel: LightChannel, level: u8) {
+        self.0 = match channel {
+            LightChannel::Sky => (self.0 & 0b1111) | (level << 4),
+            LightChannel::Block => (self.0 & 0b1111_0000) | level,
+        }
+    }
+}
+
+/// Only full opaque voxels stop light, slopes and see-through materials let it in.
+pub fn blocks_light(voxel: &VoxelDescriptor, materials: &MaterialRegistry) -> bool {
+    voxel.shape.volume == Volume::SixSixth && materials.opacity(voxel.material) == Opacity::Opaque
+}
+
+/// Voxels light is flooded through. Positions outside of it are ignored.
+trait LightVolume {
+    fn light(&self, position: IVec3) -> Option<LightLevel>;
+    fn set_light(&mut self, position: IVec3, channel: LightChannel, level: u8);
+    fn voxel(&self, position: IVec3) -> Option<VoxelDescriptor>;
+    fn materials(&self) -> &MaterialRegistry;
+    /// Height of the top of the world within the volume, if it reaches it.
+    fn sky_height(&self) -> Option<i32>;
+
+    fn blocks_light(&self, position: IVec3) -> bool {
+        self.voxel(position)
+            .is_some_and(|voxel| blocks_light(&voxel, self.materials()))
+    }
+
+    fn emission(&self, position: IVec3) -> u8 {
+        self.voxel(position)
+            .filter(|voxel| voxel.shape.volume != Volume::ZeroSixth)
+            .map(|voxel| self.materials().light_emission(voxel.material))
+            .unwrap_or(0)
+    }
+
+    /// Sky light reaches the top of the world unobstructed.
+    fn is_sky(&self, position: IVec3) -> bool {
+        self.sky_height() == Some(position.y + 1) && !self.blocks_light(position)
+    }
+
+    /// Flood light from already lit voxels, only ever increasing levels.
+    fn spread(&mut self, channel: LightChannel, mut queue: VecDeque<IVec3>) {
+        while let Some(position) = queue.pop_front() {
+            let Some(light) = self.light(position) else { continue };
+            let level = light.get(channel);
+            if level <= 1 {
+                continue;
+            }
+            for offset in NEIGHBOURS {
+                let neighbour = position + offset;
+                let Some(neighbour_light) = self.light(neighbour) else { continue };
+                if self.blocks_light(neighbour) {
+                    continue;
+                }
+                let neighbour_level = Self::propagated_level(channel, offset, level);
+                if neighbour_light.get(channel) < neighbour_level {
+                    self.set_light(neighbour, channel, neighbour_level);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    /// Darken every voxel lit through `position`, then light them again from the voxels around that are lit
+    /// by something else.
+    fn unspread(&mut self, channel: LightChannel, position: IVec3) {
+        let Some(light) = self.light(position) else { return };
+        let mut removal_queue = VecDeque::from([(position, light.get(channel))]);
+        let mut spread_queue = VecDeque::new();
+        self.set_light(position, channel, 0);
+
+        while let Some((position, level)) = removal_queue.pop_front() {
+            for offset in NEIGHBOURS {
+                let neighbour = position + offset;
+                let Some(neighbour_light) = self.light(neighbour) else { continue };
+                let neighbour_level = neighbour_light.get(channel);
+                if neighbour_level == 0 {
+                    continue;
+                }
+                if neighbour_level < level
+                    || neighbour_level == Self::propagated_level(channel, offset, level)
+                {
+                    self.set_light(neighbour, channel, 0);
+                    removal_queue.push_back((neighbour, neighbour_level));
+                    // Light sources keep shining on their own
+                    let emission = self.emission(neighbour);
+                    if channel == LightChannel::Block && emission > 0 {
+                        self.set_light(neighbour, channel, emission);
+                        spread_queue.push_back(neighbour);
+                    }
+                } else {
+                    spread_queue.push_back(neighbour);
+                }
+            }
+        }
+
+        self.spread(channel, spread_queue);
+    }
+
+    fn propagated_level(channel: LightChannel, offset: IVec3, level: u8) -> u8 {
+        if channel == LightChannel::Sky && offset.y == -1 && level == MAX_LIGHT_LEVEL {
+            MAX_LIGHT_LEVEL
+        } else {
+            level.saturating_sub(1)
+        }
+    }
+
+    /// Relight a voxel after it has been edited.
+    fn relight(&mut self, position: IVec3) {
+        for channel in CHANNELS {
+            self.unspread(channel, position);
+        }
+        if self.blocks_light(position) {
+            self.set_light(position, LightChannel::Sky, 0);
+            self.set_light(position, LightChannel::Block, 0);
+        } else {
+            // Voxels around are lit already, spreading from them lights the edited one
+            let neighbours = NEIGHBOURS.iter().map(|offset| position + *offset);
+            let sky_sources = neighbours
+                .clone()
+                .chain(self.is_sky(position).then_some(position));
+            if self.is_sky(position) {
+                self.set_light(position, LightChannel::Sky, MAX_LIGHT_LEVEL);
+            }
+            self.spread(LightChannel::Sky, sky_sources.collect());
+            self.spread(LightChannel::Block, neighbours.collect());
+        }
+
+        let emission = self.emission(position);
+        if emission > 0 {
+            self.set_light(position, LightChannel::Block, emission);
+            self.spread(LightChannel::Block, VecDeque::from([position]));
+        }
+    }
+}
+
+struct TerrainLight<'a> {
+    terrain: &'a mut Terrain,
+    materials: &'a MaterialRegistry,
+    under_sky: bool,
+}
+
+impl LightVolume for TerrainLight<'_> {
+    fn light(&self, position: IVec3) -> Option<LightLevel> {
+        self.terrain.light_at_pos(position)
+    }
+
+    fn set_light(&mut self, position: IVec3, channel: LightChannel, level: u8) {
+        self.terrain.set_light_at_pos(position, channel, level);
+    }
+
+    fn voxel(&self, position: IVec3) -> Option<VoxelDescriptor> {
+        *self.terrain.voxel_at_pos(position)
+    }
+
+    fn materials(&self) -> &MaterialRegistry {
+        self.materials
+    }
+
+    fn sky_height(&self) -> Option<i32> {
+        self.under_sky.then_some(self.terrain.size.y as i32)
+    }
+}
+
+/// Light across every loaded chunk, remembering which chunks were changed.
+struct WorldLight<'a> {
+    world: &'a World,
+    materials: &'a MaterialRegistry,
+    changed_chunks: HashSet<chunk::Coordinates>,
+}
+
+impl LightVolume for WorldLight<'_> {
+    fn light(&self, position: IVec3) -> Option<LightLevel> {
+        self.world.get_light(position)
+    }
+
+    fn set_light(&mut self, position: IVec3, channel: LightChannel, level: u8) {
+        let Some(chunk) = self.world.get_chunk_at_pos(position) else { return };
+        let mut chunk = chunk.write();
+        let relative_position = chunk.get_relative_position(position);
+        let coordinates = chunk.coordinates;
+        let Some(terrain) = chunk.terrain.as_mut() else { return };
+        terrain.set_light_at_pos(relative_position.as_ivec3(), channel, level);
+        self.changed_chunks.insert(coordinates);
+    }
+
+    fn voxel(&self, position: IVec3) -> Option<VoxelDescriptor> {
+        self.world.get_voxel(position).map(VoxelDescriptor::from)
+    }
+
+    fn materials(&self) -> &MaterialRegistry {
+        self.materials
+    }
+
+    fn sky_height(&self) -> Option<i32> {
+        Some(self.world.chunk_size.sky_height())
+    }
+}
+
+impl WorldLight<'_> {
+    /// Remesh displayed chunks whose light changed.
+    fn mark_changed_chunks_dirty(&self) {
+        for coordinates in self.changed_chunks.iter() {
+            let Some(chunk) = self.world.get_chunk(*coordinates) else { continue };
+            let mut chunk = chunk.write();
+            if chunk.state == chunk::State::Meshed {
+                chunk.dirty = true;
+            }
+        }
+    }
+}
+
+/// Index of a chunk in a `ChunkNeighbourhood`, from its offset to the chunk in the middle.
+fn neighbourhood_index(offset: IVec3) -> Option<usize> {
+    let offset = offset + IVec3::ONE;
+    (offset.cmpge(IVec3::ZERO).all() && offset.cmplt(IVec3::splat(3)).all())
+        .then_some((offset.x + offset.y * 3 + offset.z * 9) as usize)
+}
+
+/// A chunk and the 3×3×3 block of chunks around it, for light to flow across the borders of the chunk. Light fades
+/// out `MAX_LIGHT_LEVEL` voxels past the borders, so the block holds all of it as long as chunks are at least as large.
+pub struct ChunkNeighbourhood {
+    chunk_size: ChunkSize,
+    coordinates: chunk::Coordinates,
+    /// Indexed by `neighbourhood_index`, missing where chunks aren't loaded.
+    chunks: Vec<Option<WorldChunk>>,
+}
+
+impl ChunkNeighbourhood {
+    /// Write lock every chunk at once, or none of them if any is locked already. Meshing tasks keep their chunk locked
+    /// while reading the ones around it, waiting for chunks one by one could deadlock with them.
+    pub fn try_lock<'a>(
+        &'a self,
+        materials: &'a MaterialRegistry,
+    ) -> Option<NeighbourhoodLight<'a>> {
+        let chunks = self
+            .chunks
+            .iter()
+            .map(|chunk| match chunk {
+                Some(chunk) => chunk.try_write().map(Some),
+                None => Some(None),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(NeighbourhoodLight {
+            chunk_size: self.chunk_size,
+            coordinates: self.coordinates,
+            chunks,
+            materials,
+            changed_chunks: 0,
+        })
+    }
+
+    /// Wait until every chunk can be locked at once.
+    pub fn lock<'a>(&'a self, materials: &'a MaterialRegistry) -> NeighbourhoodLight<'a> {
+        loop {
+            if let Some(light) = self.try_lock(materials) {
+                return light;
+            }
+            std::thread::yield_now();
+        }
+    }
+}
+
+/// Light across the locked chunks of a `ChunkNeighbourhood`, remembering which chunks were changed.
+pub struct NeighbourhoodLight<'a> {
+    chunk_size: ChunkSize,
+    coordinates: chunk::Coordinates,
+    chunks: Vec<Option<RwLockWriteGuard<'a, Chunk>>>,
+    materials: &'a MaterialRegistry,
+    /// One bit per chunk, by `neighbourhood_index`.
+    changed_chunks: u32,
+}
+
+impl LightVolume for NeighbourhoodLight<'_> {
+    fn light(&self, position: IVec3) -> Option<LightLevel> {
+        let (index, relative_position) = self.locate(position)?;
+        self.chunks[index]
+            .as_ref()?
+            .terrain
+            .as_ref()?
+            .light_at_pos(relative_position)
+    }
+
+    fn set_light(&mut self, position: IVec3, channel: LightChannel, level: u8) {
+        let Some((index, relative_position)) = self.locate(position) else { return };
+        let Some(chunk) = self.chunks[index].as_mut() else { return };
+        let Some(terrain) = chunk.terrain.as_mut() else { return };
+        terrain.set_light_at_pos(relative_position, channel, level);
+        self.changed_chunks |= 1 << index;
+    }
+
+    fn voxel(&self, position: IVec3) -> Option<VoxelDescriptor> {
+        let (index, relative_position) = self.locate(position)?;
+        *self.chunks[index]
+            .as_ref()?
+            .terrain
+            .as_ref()?
+            .voxel_at_pos(relative_position)
+    }
+
+    fn materials(&self) -> &MaterialRegistry {
+        self.materials
+    }
+
+    fn sky_height(&self) -> Option<i32> {
+        Some(self.chunk_size.sky_height())
+    }
+}
+
+impl NeighbourhoodLight<'_> {
+    /// Index of the chunk a voxel is in and its position in that chunk.
+    fn locate(&self, position: IVec3) -> Option<(usize, IVec3)> {
+        let coordinates = self.chunk_size.coordinates(position);
+        let index = neighbourhood_index(coordinates.0 - self.coordinates.0)?;
+        Some((index, position - self.chunk_size.origin(coordinates)))
+    }
+
+    /// The chunk in the middle of the neighbourhood.
+    pub fn chunk(&mut self) -> &mut Chunk {
+        let index = neighbourhood_index(IVec3::ZERO).unwrap();
+        self.chunks[index].as_mut().unwrap()
+    }
+
+    /// Let light flow between the chunk and the chunks around it, remeshing displayed chunks whose light changed.
+    pub fn spread_across_borders(&mut self) {
+        let origin = self.chunk_size.origin(self.coordinates);
+        let size = self.chunk_size.as_ivec3();
+
+        // Voxels on both sides of every border
+        let mut queue = VecDeque::new();
+        for y in 0..size.y {
+            for z in 0..size.z {
+                queue.extend([IVec3::new(0, y, z), IVec3::new(-1, y, z)]);
+                queue.extend([IVec3::new(size.x - 1, y, z), IVec3::new(size.x, y, z)]);
+            }
+            for x in 0..size.x {
+                queue.extend([IVec3::new(x, y, 0), IVec3::new(x, y, -1)]);
+                queue.extend([IVec3::new(x, y, size.z - 1), IVec3::new(x, y, size.z)]);
+            }
+        }
+        for x in 0..size.x {
+            for z in 0..size.z {
+                queue.extend([IVec3::new(x, 0, z), IVec3::new(x, -1, z)]);
+                queue.extend([IVec3::new(x, size.y - 1, z), IVec3::new(x, size.y, z)]);
+            }
+        }
+        queue.iter_mut().for_each(|position| *position += origin);
+
+        for channel in CHANNELS {
+            self.spread(channel, queue.clone());
+        }
+
+        for (index, chunk) in self.chunks.iter_mut().enumerate() {
+            let Some(chunk) = chunk else { continue };
+            if self.changed_chunks & (1 << index) != 0 && chunk.state == chunk::State::Meshed {
+                chunk.dirty = true;
+            }
+        }
+    }
+}
+
+impl Terrain {
+    /// Light a freshly generated chunk on its own, as if it was surrounded by darkness. Only chunks of the top layer
+    /// are lit by the sky, the ones below get it from above once they are loaded.
+    pub fn compute_light(&mut self, materials: &MaterialRegistry, under_sky: bool) {
+        let size = self.size.as_ivec3();
+        let mut light = TerrainLight {
+            terrain: self,
+            materials,
+            under_sky,
+        };
+        let mut sky_queue = VecDeque::new();
+        let mut block_queue = VecDeque::new();
+
+        for x in 0..size.x {
+            for z in 0..size.z {
+                for y in 0..size.y {
+                    let position = IVec3::new(x, y, z);
+                    if light.is_sky(position) {
+                        light.set_light(position, LightChannel::Sky, MAX_LIGHT_LEVEL);
+                        sky_queue.push_back(position);
+                    }
+                    let emission = light.emission(position);
+                    if emission > 0 {
+                        light.set_light(position, LightChannel::Block, emission);
+                        block_queue.push_back(position);
+                    }
+                }
+            }
+        }
+
+        light.spread(LightChannel::Sky, sky_queue);
+        light.spread(LightChannel::Block, block_queue);
+    }
+}
+
+impl World {
+    pub fn get_light(&self, position: IVec3) -> Option<LightLevel> {
+        let chunk = self.get_chunk_at_pos(position)?;
+        let chunk = chunk.read();
+        let relative_position = chunk.get_relative_position(position);
+        chunk
+            .terrain
+            .as_ref()?
+            .light_at_pos(relative_position.as_ivec3())
+    }
+
+    /// The chunk at `coordinates` and the loaded chunks around it, if it is loaded.
+    pub fn get_chunk_neighbourhood(
+        &self,
+        coordinates: chunk::Coordinates,
+    ) -> Option<ChunkNeighbourhood> {
+        self.get_chunk(coordinates)?;
+        let mut chunks = vec![None; 27];
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let offset = IVec3::new(x, y, z);
+                    chunks[neighbourhood_index(offset).unwrap()] =
+                        self.get_chunk(coordinates + chunk::Coordinates(offset));
+                }
+            }
+        }
+        Some(ChunkNeighbourhood {
+            chunk_size: self.chunk_size,
+            coordinates,
+            chunks,
+        })
+    }
+
+    /// Light chunks and their neighbours from scratch, for edits too large to be relit voxel by voxel. Light doesn't
+    /// go further than the neighbours of the chunks it comes from.
+    pub fn relight_chunks(
+        &self,
+        coordinates: &HashSet<chunk::Coordinates>,
+        materials: &MaterialRegistry,
+    ) {
+        let relit: HashSet<chunk::Coordinates> = coordinates
+            .iter()
+            .flat_map(|coordinates| {
+                [
+                    IVec3::ZERO,
+                    IVec3::X,
+                    IVec3::NEG_X,
+                    IVec3::Y,
+                    IVec3::NEG_Y,
+                    IVec3::Z,
+                    IVec3::NEG_Z,
+                ]
+                .map(|offset| *coordinates + chunk::Coordinates(offset))
+            })
+            .collect();
+
+        for coordinates in relit.iter() {
+            let Some(chunk) = self.get_chunk(*coordinates) else { continue };
+            let mut chunk = chunk.write();
+            let Some(terrain) = chunk.terrain.as_mut() else { continue };
+            terrain.light.fill(LightLevel::default());
+            terrain.compute_light(materials, coordinates.0.y == self.chunk_size.layers() - 1);
+            if chunk.state == chunk::State::Meshed {
+                chunk.dirty = true;
+            }
+        }
+        for coordinates in relit {
+            let Some(neighbourhood) = self.get_chunk_neighbourhood(coordinates) else { continue };
+            neighbourhood.lock(materials).spread_across_borders();
+        }
+    }
+
+    /// Update light around a voxel that was just edited, remeshing every chunk it changed.
+    pub fn update_light(&self, position: IVec3, materials: &MaterialRegistry) {
+        let mut light = WorldLight {
+            world: self,
+            materials,
+            changed_chunks: HashSet::new(),
+        };
+        light.relight(position);
+        light.mark_changed_chunks_dirty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, IVec3, UVec3};
+
+    use crate::world::{
+        chunk::{ChunkSize, Coordinates, Terrain},
+        voxel::{
+            material::{
+                registry::{MaterialProperties, MaterialRegistry, Opacity},
+                FaceTextures, Material,
+            },
+            shape::Shape,
+            VoxelDescriptor,
+        },
+        World,
+    };
+
+    use super::{LightChannel, LightVolume, TerrainLight, MAX_LIGHT_LEVEL};
+
+    const LAMP: Material = Material { id: 0 };
+
+    /// A single material shining at full level.
+    fn materials() -> MaterialRegistry {
+        MaterialRegistry::new(vec![MaterialProperties {
+            name: "lamp".to_string(),
+            color: [1.0; 4],
+            textures: FaceTextures::default(),
+            roughness: 1.0,
+            emissive: [1.0; 3],
+            hardness: 0.0,
+            transparency: 0.0,
+            opacity: Opacity::Opaque,
+        }])
+    }
+
+    fn lamp() -> Option<VoxelDescriptor> {
+        Some(VoxelDescriptor {
+            shape: Shape::FULL,
+            material: LAMP,
+        })
+    }
+
+    fn air() -> Option<VoxelDescriptor> {
+        Some(VoxelDescriptor {
+            shape: Shape::EMPTY,
+            material: LAMP,
+        })
+    }
+
+    /// Chunks of air lit on their own, below the sky.
+    fn world(chunks: &[IVec3], materials: &MaterialRegistry) -> World {
+        let mut world = World::new(ChunkSize::new(UVec3::splat(8)));
+        for coordinates in chunks {
+            let coordinates = Coordinates(*coordinates);
+            world.spawn_chunk(Entity::PLACEHOLDER, coordinates);
+            let mut terrain = Terrain::new(*world.chunk_size);
+            terrain.compute_light(materials, false);
+            world.get_chunk(coordinates).unwrap().write().terrain = Some(terrain);
+        }
+        world
+    }
+
+    fn block_light(world: &World, position: IVec3) -> u8 {
+        world.get_light(position).unwrap().get(LightChannel::Block)
+    }
+
+    #[test]
+    fn light_fades_around_placed_lamp_and_goes_out_once_removed() {
+        let materials = materials();
+        let mut terrain = Terrain::new(UVec3::splat(8));
+        let mut light = TerrainLight {
+            terrain: &mut terrain,
+            materials: &materials,
+            under_sky: false,
+        };
+        let position = IVec3::new(4, 4, 4);
+
+        *light.terrain.voxel_at_pos_mut(position) = lamp();
+        light.relight(position);
+        let level = |light: &TerrainLight, position| {
+            light.light(position).unwrap().get(LightChannel::Block)
+        };
+        assert_eq!(level(&light, position), MAX_LIGHT_LEVEL);
+        assert_eq!(level(&light, IVec3::new(6, 4, 4)), MAX_LIGHT_LEVEL - 2);
+        assert_eq!(level(&light, IVec3::new(5, 5, 5)), MAX_LIGHT_LEVEL - 3);
+        assert_eq!(level(&light, IVec3::ZERO), MAX_LIGHT_LEVEL - 12);
+
+        *light.terrain.voxel_at_pos_mut(position) = air();
+        light.relight(position);
+        assert!(terrain
+            .light
+            .iter()
+            .all(|light| *light == Default::default()));
+    }
+
+    #[test]
+    fn light_flows_into_chunks_lit_on_their_own() {
+        let materials = materials();
+        let position = IVec3::new(6, 4, 4);
+        // Either chunk can be the one lit across its borders, as the last one to be generated
+        for chunk in [IVec3::ZERO, IVec3::X] {
+            let world = world(&[IVec3::ZERO, IVec3::X], &materials);
+            {
+                let lamp_chunk = world.get_chunk(Coordinates(IVec3::ZERO)).unwrap();
+                let mut lamp_chunk = lamp_chunk.write();
+                let terrain = lamp_chunk.terrain.as_mut().unwrap();
+                *terrain.voxel_at_pos_mut(position) = lamp();
+                terrain.compute_light(&materials, false);
+            }
+            assert_eq!(block_light(&world, IVec3::new(8, 4, 4)), 0);
+
+            let neighbourhood = world.get_chunk_neighbourhood(Coordinates(chunk)).unwrap();
+            neighbourhood.lock(&materials).spread_across_borders();
+            assert_eq!(
+                block_light(&world, IVec3::new(8, 4, 4)),
+                MAX_LIGHT_LEVEL - 2
+            );
+            assert_eq!(
+                block_light(&world, IVec3::new(15, 4, 4)),
+                MAX_LIGHT_LEVEL - 9
+            );
+        }
+    }
+
+    #[test]
+    fn removed_lamp_darkens_chunks_around() {
+        let materials = materials();
+        let world = world(&[IVec3::ZERO, IVec3::X], &materials);
+        let position = IVec3::new(7, 4, 4);
+
+        let set_voxel = |voxel| {
+            let chunk = world.get_chunk(Coordinates(IVec3::ZERO)).unwrap();
+            *chunk
+                .write()
+                .terrain
+                .as_mut()
+                .unwrap()
+                .voxel_at_pos_mut(position) = voxel;
+            world.update_light(position, &materials);
+        };
+        set_voxel(lamp());
+        assert_eq!(
+            block_light(&world, IVec3::new(8, 4, 4)),
+            MAX_LIGHT_LEVEL - 1
+        );
+        assert_eq!(
+            block_light(&world, IVec3::new(0, 4, 4)),
+            MAX_LIGHT_LEVEL - 7
+        );
+
+        set_voxel(air());
+        for x in 0..16 {
+            assert_eq!(block_light(&world, IVec3::new(x, 4, 4)), 0, "{x}");
+        }
+    }
+}
@@ -1,13 +1,24 @@
 use bevy::{prelude::*, utils::HashMap};
 use parking_lot::RwLock;
+
+use crate::debug::app::DebugApp;
 use std::sync::Arc;
 
 use self::{
-    chunk::{Chunk, CHUNK_SIZE},
-    voxel::{Voxel, VoxelDescriptor},
+    blueprint::BlueprintPlugin,
+    chunk::{generator::TerrainGeneratorKind, mesh::occlusion::AmbientOcclusion, Chunk, ChunkSize},
+    voxel::{
+        material::registry::{MaterialRegistryPlugin, MATERIAL_REGISTRY_PATH},
+        Voxel, VoxelDescriptor,
+    },
 };
 
+pub mod biome;
+pub mod blueprint;
 pub mod chunk;
+pub mod edit;
+pub mod light;
+pub mod query;
 pub mod raycast;
 pub mod voxel;
 
@@ -21,11 +32,35 @@ pub struct WorldSimulationSystemSet;
 #[derive(SystemSet, Hash, Debug, Clone, Eq, PartialEq)]
 pub struct WorldTasksSystemSet;
 
-pub struct WorldPlugin;
+/// Voxel world, its materials and blueprints. Chunks are loaded around the `ChunkLoaderSource` by the
+/// `ChunkLoaderPlugin`.
+pub struct WorldPlugin {
+    pub chunk_size: ChunkSize,
+    pub generator: TerrainGeneratorKind,
+    /// Material registry file, relative to the assets folder.
+    pub materials: String,
+}
+
+impl Default for WorldPlugin {
+    fn default() -> Self {
+        Self {
+            chunk_size: ChunkSize::default(),
+            generator: TerrainGeneratorKind::default(),
+            materials: MATERIAL_REGISTRY_PATH.to_string(),
+        }
+    }
+}
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.init_resource::<World>()
+        let materials = MaterialRegistryPlugin {
+            path: self.materials.clone(),
+        };
+        app.add_plugins((materials, BlueprintPlugin))
+            .insert_resource(World::new(self.chunk_size))
+            .insert_resource(self.generator.clone())
+            .init_resource::<AmbientOcclusion>()
+            .debug_resource::<AmbientOcclusion>()
             .configure_sets(Update, WorldTasksSystemSet.after(WorldSimulationSystemSet));
     }
 }
@@ -35,6 +70,7 @@ pub type WorldChunk = Arc<RwLock<Chunk>>;
 #[derive(Resource, Default)]
 pub struct World {
     pub chunks: HashMap<chunk::Coordinates, WorldChunk>,
+    pub chunk_size: ChunkSize,
 }
 
 impl From<Chunk> for WorldChunk {
@@ -44,6 +80,13 @@ impl From<Chunk> for WorldChunk {
 }
 
 impl World {
+    pub fn new(chunk_size: ChunkSize) -> Self {
+        Self {
+            chunks: default(),
+            chunk_size,
+        }
+    }
+
     pub fn spawn_chunk(&mut self, entity: Entity, coordinates: chunk::Coordinates) {
         self.chunks.insert(
             coordinates,
@@ -51,15 +94,26 @@ impl World {
                 entity,
                 state: chunk::State::Spawned,
                 coordinates,
-                absolute_position: coordinates.0 * CHUNK_SIZE.as_ivec3(),
+                absolute_position: self.chunk_size.origin(coordinates),
                 grid: None,
                 terrain: None,
+                visibility: None,
                 dirty: false,
             }
             .into(),
         );
     }
 
+    /// Queue every displayed chunk for meshing again, for changes baked in the meshes.
+    pub fn mark_meshed_chunks_dirty(&self) {
+        for chunk in self.chunks.values() {
+            let mut chunk = chunk.write();
+            if chunk.state == chunk::State::Meshed {
+                chunk.dirty = true;
+            }
+        }
+    }
+
     pub fn remove_chunk(&mut self, coordinates: chunk::Coordinates) {
         self.chunks.remove(&coordinates);
     }
@@ -69,7 +123,7 @@ impl World {
     }
 
     pub fn get_chunk_at_pos(&self, position: IVec3) -> Option<WorldChunk> {
-        self.get_chunk(Self::position_to_chunk_coordinates(position))
+        self.get_chunk(self.position_to_chunk_coordinates(position))
     }
 
     pub fn get_chunk_by_entity(&self, entity: Entity) -> Option<WorldChunk> {
@@ -80,18 +134,14 @@ impl World {
     }
 
     pub fn get_voxel(&self, position: IVec3) -> Option<Voxel> {
-        let chunk_coordinates = Self::position_to_chunk_coordinates(position);
+        let chunk_coordinates = self.position_to_chunk_coordinates(position);
         let chunk = self.get_chunk(chunk_coordinates)?;
-        let relative_position = (position - chunk_coordinates.0 * CHUNK_SIZE.as_ivec3()).as_uvec3();
+        let relative_position = (position - self.chunk_size.origin(chunk_coordinates)).as_uvec3();
         let voxel = chunk.read().get_voxel(relative_position)?;
         Some(voxel)
     }
 
-    pub fn position_to_chunk_coordinates(position: IVec3) -> chunk::Coordinates {
-        chunk::Coordinates(
-            (position.as_vec3() / CHUNK_SIZE.as_vec3())
-                .floor()
-                .as_ivec3(),
-        )
+    pub fn position_to_chunk_coordinates(&self, position: IVec3) -> chunk::Coordinates {
+        self.chunk_size.coordinates(position)
     }
 }
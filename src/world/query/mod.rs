@@ -0,0 +1,267 @@
+use bevy::math::{IVec3, Vec3};
+
+use super::{
+    voxel::{
+        collision::{Aabb, ConvexPart},
+        shape::{ShapeDescriptor, Volume},
+        Voxel,
+    },
+    World,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SweepHit {
+    pub voxel: Voxel,
+    /// Fraction of the motion travelled before the hit, between 0.0 and 1.0.
+    pub time: f32,
+    /// Normal of the face hit, facing the motion.
+    pub normal: Vec3,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ClosestPoint {
+    pub voxel: Voxel,
+    pub point: Vec3,
+    pub distance: f32,
+}
+
+fn convex_parts(voxel: &Voxel) -> &'static [ConvexPart] {
+    ShapeDescriptor::from(voxel.shape).convex_parts()
+}
+
+// Spatial queries against the voxel shapes. They only read the `World` resource, unloaded voxels being empty.
+impl World {
+    /// Loaded non empty voxels from `min` included to `max` excluded.
+    pub fn voxels_in(&self, min: IVec3, max: IVec3) -> impl Iterator<Item = Voxel> + '_ {
+        (min.x..max.x)
+            .flat_map(move |x| {
+                (min.y..max.y).flat_map(move |y| (min.z..max.z).map(move |z| IVec3::new(x, y, z)))
+            })
+            .filter_map(|position| self.get_voxel(position))
+            .filter(|voxel| voxel.shape.volume != Volume::ZeroSixth)
+    }
+
+    /// First voxel a box moving by `motion` hits.
+    pub fn sweep_aabb(&self, aabb: &Aabb, motion: Vec3) -> Option<SweepHit> {
+        let min = aabb.min.min(aabb.min + motion).floor().as_ivec3();
+        let max = aabb.max.max(aabb.max + motion).ceil().as_ivec3();
+
+        self.voxels_in(min, max)
+            .flat_map(|voxel| {
+                convex_parts(&voxel).iter().filter_map(move |part| {
+                    let (time, normal) = part.sweep(aabb, voxel.position.as_vec3(), motion)?;
+                    Some(SweepHit {
+                        voxel,
+                        time,
+                        normal,
+                    })
+                })
+            })
+            .min_by(|a, b| a.time.total_cmp(&b.time))
+    }
+
+    /// Voxels whose shape overlaps a box.
+    pub fn overlap_aabb(&self, aabb: &Aabb) -> Vec<Voxel> {
+        self.voxels_in(aabb.min.floor().as_ivec3(), aabb.max.ceil().as_ivec3())
+            .filter(|voxel| {
+                convex_parts(voxel)
+                    .iter()
+                    .any(|part| part.penetration(aabb, voxel.position.as_vec3()).is_some())
+            })
+            .collect()
+    }
+
+    /// Voxels whose shape overlaps a sphere.
+    pub fn overlap_sphere(&self, center: Vec3, radius: f32) -> Vec<Voxel> {
+        self.voxels_in(
+            (center - radius).floor().as_ivec3(),
+            (center + radius).ceil().as_ivec3(),
+        )
+        .filter(|voxel| {
+            let origin = voxel.position.as_vec3();
+            convex_parts(voxel).iter().any(|part| {
+                (part.closest_point(center - origin) + origin).distance_squared(center)
+                    <= radius * radius
+            })
+        })
+        .collect()
+    }
+
+    /// Point of the terrain surface closest to `point`, looking no further than `max_distance`. Points inside the
+    /// terrain are their own closest point.
+    pub fn closest_point(&self, point: Vec3, max_distance: f32) -> Option<ClosestPoint> {
+        self.voxels_in(
+            (point - max_distance).floor().as_ivec3(),
+            (point + max_distance).ceil().as_ivec3(),
+        )
+        .flat_map(|voxel| {
+            let origin = voxel.position.as_vec3();
+            convex_parts(&voxel).iter().map(move |part| {
+                let closest = part.closest_point(point - origin) + origin;
+                ClosestPoint {
+                    voxel,
+                    point: closest,
+                    distance: closest.distance(point),
+                }
+            })
+        })
+        .filter(|closest| closest.distance <= max_distance)
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, IVec3, UVec3, Vec3};
+
+    use crate::world::{
+        chunk::{ChunkSize, Coordinates, Terrain},
+        voxel::{
+            collision::Aabb,
+            material::STONE,
+            shape::{Rotation, Shape, Volume},
+            VoxelDescriptor,
+        },
+        World,
+    };
+
+    /// A single chunk of air with the given voxels.
+    fn world(voxels: &[(IVec3, Shape)]) -> World {
+        let chunk_size = ChunkSize::new(UVec3::splat(4));
+        let mut world = World::new(chunk_size);
+        let coordinates = Coordinates(IVec3::ZERO);
+        world.spawn_chunk(Entity::PLACEHOLDER, coordinates);
+        let mut terrain = Terrain::new(*chunk_size);
+        for (position, shape) in voxels {
+            *terrain.voxel_at_pos_mut(*position) = Some(VoxelDescriptor {
+                shape: *shape,
+                material: STONE,
+            });
+        }
+        world.get_chunk(coordinates).unwrap().write().terrain = Some(terrain);
+        world
+    }
+
+    fn cube(min: Vec3, size: f32) -> Aabb {
+        Aabb {
+            min,
+            max: min + size,
+        }
+    }
+
+    #[test]
+    fn sweep_stops_on_full_voxel() {
+        let world = world(&[(IVec3::new(2, 1, 1), Shape::FULL)]);
+        let aabb = cube(Vec3::new(0.5, 1.25, 1.25), 0.5);
+
+        let hit = world.sweep_aabb(&aabb, Vec3::new(2.0, 0.0, 0.0)).unwrap();
+        assert_eq!(hit.voxel.position, IVec3::new(2, 1, 1));
+        assert!((hit.time - 0.5).abs() < 1e-5, "{}", hit.time);
+        assert_eq!(hit.normal, Vec3::NEG_X);
+
+        // Passing next to the voxel
+        let above = aabb.translated(Vec3::Y);
+        assert!(world.sweep_aabb(&above, Vec3::new(2.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn sweep_slides_along_one_sixth_slope() {
+        // Corner of the voxel under the x + y + z = 1 slope
+        let slope = Shape::new(Rotation::FacingNorth0Degrees, Volume::OneSixth);
+        let world = world(&[(IVec3::ONE, slope)]);
+        let normal = Vec3::ONE.normalize();
+        let aabb = cube(Vec3::new(1.41, 1.3, 1.3), 0.2);
+
+        // Moving along the slope never touches it
+        assert!(world.sweep_aabb(&aabb, Vec3::new(0.3, -0.3, 0.0)).is_none());
+
+        // Moving into it stops on the slope, whose normal the motion can slide along
+        let hit = world.sweep_aabb(&aabb, -normal).unwrap();
+        assert!((hit.time - 0.01 / 3f32.sqrt()).abs() < 1e-4, "{}", hit.time);
+        assert!(hit.normal.abs_diff_eq(normal, 1e-5), "{}", hit.normal);
+        let slide = -normal - hit.normal * (-normal).dot(hit.normal);
+        assert!(slide.length() < 1e-5);
+    }
+
+    #[test]
+    fn sweep_without_motion_only_hits_overlapping_voxels() {
+        let world = world(&[(IVec3::new(2, 1, 1), Shape::FULL)]);
+
+        let inside = cube(Vec3::new(1.75, 1.25, 1.25), 0.5);
+        let hit = world.sweep_aabb(&inside, Vec3::ZERO).unwrap();
+        assert_eq!(hit.time, 0.0);
+
+        let outside = cube(Vec3::new(1.25, 1.25, 1.25), 0.5);
+        assert!(world.sweep_aabb(&outside, Vec3::ZERO).is_none());
+    }
+
+    #[test]
+    fn overlap_follows_voxel_shapes() {
+        let slope = Shape::new(Rotation::FacingNorth0Degrees, Volume::OneSixth);
+        let world = world(&[(IVec3::ONE, slope), (IVec3::new(2, 1, 1), Shape::FULL)]);
+
+        // In the voxel of the slope but above it
+        let above_slope = cube(Vec3::new(1.5, 1.5, 1.5), 0.25);
+        assert!(world.overlap_aabb(&above_slope).is_empty());
+
+        let both = cube(Vec3::new(1.1, 1.1, 1.1), 1.0);
+        let mut positions: Vec<IVec3> = world
+            .overlap_aabb(&both)
+            .iter()
+            .map(|voxel| voxel.position)
+            .collect();
+        positions.sort_by_key(|position| position.x);
+        assert_eq!(positions, [IVec3::ONE, IVec3::new(2, 1, 1)]);
+    }
+
+    #[test]
+    fn overlap_sphere_follows_voxel_shapes() {
+        let slope = Shape::new(Rotation::FacingNorth0Degrees, Volume::OneSixth);
+        let world = world(&[(IVec3::ONE, slope), (IVec3::new(3, 1, 1), Shape::FULL)]);
+
+        // Above the slope, about 0.46 away from it
+        let center = Vec3::splat(1.6);
+        assert!(world.overlap_sphere(center, 0.3).is_empty());
+        let positions: Vec<IVec3> = world
+            .overlap_sphere(center, 0.5)
+            .iter()
+            .map(|voxel| voxel.position)
+            .collect();
+        assert_eq!(positions, [IVec3::ONE]);
+
+        // Half a voxel away from the full voxel, further from the slope
+        let positions: Vec<IVec3> = world
+            .overlap_sphere(Vec3::new(2.5, 1.5, 1.5), 0.6)
+            .iter()
+            .map(|voxel| voxel.position)
+            .collect();
+        assert_eq!(positions, [IVec3::new(3, 1, 1)]);
+    }
+
+    #[test]
+    fn closest_point_lies_on_voxel_shapes() {
+        let slope = Shape::new(Rotation::FacingNorth0Degrees, Volume::OneSixth);
+        let world = world(&[(IVec3::ONE, slope), (IVec3::new(3, 1, 1), Shape::FULL)]);
+
+        // Projected on the slope rather than on the full voxel further away
+        let closest = world.closest_point(Vec3::splat(1.5), 1.0).unwrap();
+        assert_eq!(closest.voxel.position, IVec3::ONE);
+        assert!(
+            closest.point.distance(Vec3::splat(4.0 / 3.0)) < 1e-5,
+            "{}",
+            closest.point
+        );
+        assert!(
+            (closest.distance - 0.5 / 3.0_f32.sqrt()).abs() < 1e-5,
+            "{}",
+            closest.distance
+        );
+
+        let inside = Vec3::new(3.5, 1.5, 1.5);
+        let closest = world.closest_point(inside, 1.0).unwrap();
+        assert_eq!(closest.voxel.position, IVec3::new(3, 1, 1));
+        assert_eq!((closest.point, closest.distance), (inside, 0.0));
+
+        assert!(world.closest_point(Vec3::new(1.5, 3.5, 1.5), 0.5).is_none());
+    }
+}
@@ -0,0 +1,399 @@
+use std::sync::LazyLock;
+
+use bevy::math::Vec3;
+
+use crate::world::World;
+
+use super::shape::{
+    ShapeDescriptor, SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP,
+    SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP, VERTEX_LIST,
+};
+
+const EPSILON: f32 = 1e-4;
+
+/// Points `p` behind the plane satisfy `normal.dot(p) <= distance`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+impl Plane {
+    /// Counter clockwise triangles face outwards, like the interior triangles of the shapes.
+    fn from_triangle([a, b, c]: [Vec3; 3]) -> Self {
+        let normal = (b - a).cross(c - a).normalize();
+        Self {
+            normal,
+            distance: normal.dot(a),
+        }
+    }
+
+    fn signed_distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) - self.distance
+    }
+}
+
+const CUBE_PLANES: [Plane; 6] = [
+    Plane {
+        normal: Vec3::X,
+        distance: 1.0,
+    },
+    Plane {
+        normal: Vec3::NEG_X,
+        distance: 0.0,
+    },
+    Plane {
+        normal: Vec3::Y,
+        distance: 1.0,
+    },
+    Plane {
+        normal: Vec3::NEG_Y,
+        distance: 0.0,
+    },
+    Plane {
+        normal: Vec3::Z,
+        distance: 1.0,
+    },
+    Plane {
+        normal: Vec3::NEG_Z,
+        distance: 0.0,
+    },
+];
+
+/// Convex piece of a voxel shape, in voxel space between 0.0 and 1.0 on every axis.
+#[derive(Clone, Debug, Default)]
+pub struct ConvexPart {
+    pub points: Vec<Vec3>,
+    pub planes: Vec<Plane>,
+}
+
+impl ConvexPart {
+    /// The unit cube cut by slopes. Slopes go through cube corners, so the part's points are corners too.
+    fn from_slopes(slopes: Vec<Plane>) -> Self {
+        let points: Vec<Vec3> = VERTEX_LIST
+            .iter()
+            .map(|vertex| vertex.as_vec3())
+            .filter(|point| {
+                slopes
+                    .iter()
+                    .all(|slope| slope.signed_distance(*point) <= EPSILON)
+            })
+            .collect();
+        let cube_faces = CUBE_PLANES.iter().filter(|plane| {
+            points
+                .iter()
+                .filter(|point| plane.signed_distance(**point).abs() <= EPSILON)
+                .count()
+                >= 3
+        });
+        let planes = slopes.iter().chain(cube_faces).copied().collect();
+        Self { points, planes }
+    }
+
+    fn project(&self, axis: Vec3) -> (f32, f32) {
+        self.points
+            .iter()
+            .map(|point| axis.dot(*point))
+            .fold((f32::MAX, f32::MIN), |(min, max), projection| {
+                (min.min(projection), max.max(projection))
+            })
+    }
+
+    /// Separating axis test against a box, `origin` being the position of the voxel the part belongs to.
+    pub fn penetration(&self, aabb: &Aabb, origin: Vec3) -> Option<Contact> {
+        let aabb = aabb.translated(-origin);
+        let mut contact: Option<Contact> = None;
+        let mut lift = f32::MAX;
+
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z]
+            .into_iter()
+            .chain(self.planes.iter().map(|plane| plane.normal))
+        {
+            let (part_min, part_max) = self.project(axis);
+            let (aabb_min, aabb_max) = aabb.project(axis);
+            let (forwards, backwards) = (part_max - aabb_min, aabb_max - part_min);
+            if forwards <= 0.0 || backwards <= 0.0 {
+                return None;
+            }
+
+            let (normal, depth) = if forwards < backwards {
+                (axis, forwards)
+            } else {
+                (-axis, backwards)
+            };
+            if contact.map_or(true, |contact| depth < contact.depth) {
+                contact = Some(Contact {
+                    normal,
+                    depth,
+                    lift: 0.0,
+                });
+            }
+            if axis.y > EPSILON {
+                lift = lift.min(forwards / axis.y);
+            } else if axis.y < -EPSILON {
+                lift = lift.min(backwards / -axis.y);
+            }
+        }
+
+        contact.map(|contact| Contact { lift, ..contact })
+    }
+
+    /// Pairs of points sharing two planes.
+    fn edges(&self) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+        self.points
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, a)| self.points.iter().skip(i + 1).map(move |b| (*a, *b)))
+            .filter(|(a, b)| {
+                self.planes
+                    .iter()
+                    .filter(|plane| {
+                        plane.signed_distance(*a).abs() <= EPSILON
+                            && plane.signed_distance(*b).abs() <= EPSILON
+                    })
+                    .count()
+                    >= 2
+            })
+    }
+
+    /// Every axis a box and the part can be separated along. On top of the face normals, the box axes crossed with
+    /// the part's edges keep sweeps from stopping early next to sloped edges.
+    fn separating_axes(&self) -> Vec<Vec3> {
+        let box_axes = [Vec3::X, Vec3::Y, Vec3::Z];
+        let edge_axes = self
+            .edges()
+            .flat_map(|(a, b)| box_axes.map(|axis| axis.cross(b - a)));
+
+        let mut axes = box_axes.to_vec();
+        for axis in self
+            .planes
+            .iter()
+            .map(|plane| plane.normal)
+            .chain(edge_axes)
+        {
+            let Some(axis) = axis.try_normalize() else { continue };
+            if !axes
+                .iter()
+                .any(|other| other.abs_diff_eq(axis, EPSILON) || other.abs_diff_eq(-axis, EPSILON))
+            {
+                axes.push(axis);
+            }
+        }
+        axes
+    }
+
+    /// Fraction of `motion` a box travels before hitting the part, and the normal of the face it hits. Boxes already
+    /// overlapping the part hit it right away.
+    pub fn sweep(&self, aabb: &Aabb, origin: Vec3, motion: Vec3) -> Option<(f32, Vec3)> {
+        let aabb = aabb.translated(-origin);
+        let (mut enter, mut exit) = (f32::MIN, f32::MAX);
+        let mut normal = Vec3::ZERO;
+
+        for axis in self.separating_axes() {
+            let (part_min, part_max) = self.project(axis);
+            let (aabb_min, aabb_max) = aabb.project(axis);
+            let speed = axis.dot(motion);
+            if speed.abs() <= f32::EPSILON {
+                if aabb_max <= part_min || aabb_min >= part_max {
+                    return None;
+                }
+                continue;
+            }
+
+            // Times the projections start and stop overlapping on this axis
+            let (start, end) = ((part_min - aabb_max) / speed, (part_max - aabb_min) / speed);
+            let (axis_enter, axis_exit) = (start.min(end), start.max(end));
+            if axis_enter > enter {
+                enter = axis_enter;
+                normal = -axis * speed.signum();
+            }
+            exit = exit.min(axis_exit);
+            if enter >= exit {
+                return None;
+            }
+        }
+
+        (enter <= 1.0 && exit > 0.0).then_some((enter.max(0.0), normal))
+    }
+
+    /// Point of the part closest to `point`, both relative to the voxel the part belongs to.
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        let inside = |point: Vec3| {
+            self.planes
+                .iter()
+                .all(|plane| plane.signed_distance(point) <= EPSILON)
+        };
+        if inside(point) {
+            return point;
+        }
+
+        let faces = self
+            .planes
+            .iter()
+            .map(|plane| point - plane.normal * plane.signed_distance(point))
+            .filter(|projection| inside(*projection));
+        let edges = self.edges().map(|(a, b)| {
+            let edge = b - a;
+            a + edge * ((point - a).dot(edge) / edge.length_squared()).clamp(0.0, 1.0)
+        });
+        faces
+            .chain(edges)
+            .chain(self.points.iter().copied())
+            .min_by(|a, b| {
+                a.distance_squared(point)
+                    .total_cmp(&b.distance_squared(point))
+            })
+            .unwrap_or(point)
+    }
+}
+
+/// Corners of a shape and the planes around them, the exact shape for every shape but the four sixth one.
+pub static SHAPE_DESCRIPTOR_TO_CONVEX_HULL_MAP: LazyLock<[ConvexPart; 256]> = LazyLock::new(|| {
+    let mut map: [ConvexPart; 256] = [(); 256].map(|_| ConvexPart::default());
+
+    for (index, hull) in map.iter_mut().enumerate() {
+        let mask = SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP[index];
+        // Every cube corner is an extreme point, any set of them is its own hull
+        let points: Vec<Vec3> = VERTEX_LIST
+            .iter()
+            .enumerate()
+            .filter(|(corner, _)| mask & (1 << corner) != 0)
+            .map(|(_, vertex)| vertex.as_vec3())
+            .collect();
+
+        let mut planes: Vec<Plane> = vec![];
+        for (i, a) in points.iter().enumerate() {
+            for (j, b) in points.iter().enumerate().skip(i + 1) {
+                for c in points.iter().skip(j + 1) {
+                    if (*b - *a).cross(*c - *a).length_squared() <= EPSILON {
+                        continue;
+                    }
+                    let plane = Plane::from_triangle([*a, *b, *c]);
+                    let flipped = Plane {
+                        normal: -plane.normal,
+                        distance: -plane.distance,
+                    };
+                    for candidate in [plane, flipped] {
+                        let bounds = points
+                            .iter()
+                            .all(|point| candidate.signed_distance(*point) <= EPSILON);
+                        let known = planes.iter().any(|other| {
+                            other.normal.abs_diff_eq(candidate.normal, EPSILON)
+                                && (other.distance - candidate.distance).abs() <= EPSILON
+                        });
+                        if bounds && !known {
+                            planes.push(candidate);
+                        }
+                    }
+                }
+            }
+        }
+        *hull = ConvexPart { points, planes };
+    }
+
+    map
+});
+
+/// Voxel shapes as unions of convex parts. Every shape is convex but the four sixth one, whose two slopes form a
+/// valley and make a part each.
+pub static SHAPE_DESCRIPTOR_TO_CONVEX_PARTS_MAP: LazyLock<[Vec<ConvexPart>; 256]> =
+    LazyLock::new(|| {
+        let mut map: [Vec<ConvexPart>; 256] = [(); 256].map(|_| vec![]);
+
+        for (index, parts) in map.iter_mut().enumerate() {
+            let mask = SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP[index];
+            if mask == 0 {
+                continue;
+            }
+            let corners: Vec<Vec3> = VERTEX_LIST
+                .iter()
+                .enumerate()
+                .filter(|(corner, _)| mask & (1 << corner) != 0)
+                .map(|(_, vertex)| vertex.as_vec3())
+                .collect();
+
+            let mut slopes: Vec<Plane> = vec![];
+            for triangle in SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP[index].iter() {
+                let slope = Plane::from_triangle(triangle.map(|vertex| vertex.as_vec3()));
+                if !slopes.iter().any(|other| {
+                    other.normal.abs_diff_eq(slope.normal, EPSILON)
+                        && (other.distance - slope.distance).abs() <= EPSILON
+                }) {
+                    slopes.push(slope);
+                }
+            }
+
+            let convex = slopes.iter().all(|slope| {
+                corners
+                    .iter()
+                    .all(|corner| slope.signed_distance(*corner) <= EPSILON)
+            });
+            *parts = if convex {
+                vec![ConvexPart::from_slopes(slopes)]
+            } else {
+                slopes
+                    .into_iter()
+                    .map(|slope| ConvexPart::from_slopes(vec![slope]))
+                    .collect()
+            };
+        }
+
+        map
+    });
+
+impl ShapeDescriptor {
+    /// Convex hull of the shape, a superset of the shape for concave ones.
+    pub fn convex_hull(&self) -> &'static ConvexPart {
+        &SHAPE_DESCRIPTOR_TO_CONVEX_HULL_MAP[self.0 as usize]
+    }
+
+    /// Convex parts whose union is exactly the shape, for compound colliders.
+    pub fn convex_parts(&self) -> &'static [ConvexPart] {
+        &SHAPE_DESCRIPTOR_TO_CONVEX_PARTS_MAP[self.0 as usize]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn translated(&self, offset: Vec3) -> Self {
+        Self {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    fn project(&self, axis: Vec3) -> (f32, f32) {
+        let center = axis.dot((self.min + self.max) / 2.0);
+        let radius = axis.abs().dot((self.max - self.min) / 2.0);
+        (center - radius, center + radius)
+    }
+}
+
+/// Smallest translation pushing a box out of a collider.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    /// Direction the box is pushed towards.
+    pub normal: Vec3,
+    pub depth: f32,
+    /// Smallest upward translation pushing the box out, to step over edges.
+    pub lift: f32,
+}
+
+impl World {
+    /// Contacts of a box with every voxel it overlaps. Unloaded voxels are empty.
+    pub fn aabb_contacts(&self, aabb: &Aabb) -> Vec<Contact> {
+        self.voxels_in(aabb.min.floor().as_ivec3(), aabb.max.ceil().as_ivec3())
+            .flat_map(|voxel| {
+                ShapeDescriptor::from(voxel.shape)
+                    .convex_parts()
+                    .iter()
+                    .filter_map(move |part| part.penetration(aabb, voxel.position.as_vec3()))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+pub mod registry;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Material {
+    pub id: u32,
+}
+
+// Materials used by terrain generation, they have to be the first entries of the material registry
+pub const GRASS: Material = Material { id: 0 };
+pub const DIRT: Material = Material { id: 1 };
+pub const STONE: Material = Material { id: 2 };
+
+/// Number of square tiles stacked vertically in the terrain atlas.
+pub const ATLAS_TILE_COUNT: u32 = 8;
+
+/// Atlas tiles of a material, depending on which way a face is pointing.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+pub struct FaceTextures {
+    pub top: u32,
+    pub side: u32,
+    pub bottom: u32,
+}
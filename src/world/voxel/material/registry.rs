@@ -0,0 +1,219 @@
+use std::{fmt::Display, sync::Arc};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::world::light::MAX_LIGHT_LEVEL;
+
+use super::{FaceTextures, Material};
+
+pub const MATERIAL_REGISTRY_PATH: &str = "blocks/materials.ron";
+
+/// Properties shared by every voxel of a given material.
+#[derive(Deserialize, Clone, Debug)]
+pub struct MaterialProperties {
+    pub name: String,
+    /// Linear RGBA color multiplied with the atlas texture.
+    #[serde(default = "MaterialProperties::default_color")]
+    pub color: [f32; 4],
+    pub textures: FaceTextures,
+    #[serde(default = "MaterialProperties::default_roughness")]
+    pub roughness: f32,
+    /// Linear RGB emitted light. Its brightest component also sets the block light level the voxel emits.
+    #[serde(default)]
+    pub emissive: [f32; 3],
+    /// Resistance to being broken, 0.0 being instantly breakable.
+    #[serde(default)]
+    pub hardness: f32,
+    /// 0.0 is fully opaque, 1.0 fully transparent. Only used by translucent materials.
+    #[serde(default)]
+    pub transparency: f32,
+    #[serde(default)]
+    pub opacity: Opacity,
+}
+
+/// How a material lets what's behind it show through.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Opacity {
+    #[default]
+    Opaque,
+    /// Texels with a low alpha are discarded, like leaves.
+    Cutout,
+    /// Blended with what's behind it depending on its transparency, like glass or ice. Meshed separately.
+    Translucent,
+}
+
+impl MaterialProperties {
+    fn default_color() -> [f32; 4] {
+        [1.0, 1.0, 1.0, 1.0]
+    }
+
+    fn default_roughness() -> f32 {
+        1.0
+    }
+}
+
+/// Material list as written in the registry file. Each material id is its index in the list.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct MaterialRegistryAsset {
+    pub materials: Vec<MaterialProperties>,
+}
+
+#[derive(Default)]
+pub struct MaterialRegistryLoader;
+
+#[derive(Debug)]
+pub enum MaterialRegistryLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl Display for MaterialRegistryLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read material registry: {error}"),
+            Self::Ron(error) => write!(f, "could not parse material registry: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for MaterialRegistryLoaderError {}
+
+impl From<std::io::Error> for MaterialRegistryLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for MaterialRegistryLoaderError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Ron(error)
+    }
+}
+
+impl AssetLoader for MaterialRegistryLoader {
+    type Asset = MaterialRegistryAsset;
+    type Settings = ();
+    type Error = MaterialRegistryLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["materials.ron"]
+    }
+}
+
+/// Loaded materials, indexed by `Material::id`. Cheap to clone so that it can be sent to meshing tasks.
+#[derive(Resource, Clone, Default)]
+pub struct MaterialRegistry(Arc<Vec<MaterialProperties>>);
+
+impl MaterialRegistry {
+    pub fn new(materials: Vec<MaterialProperties>) -> Self {
+        Self(Arc::new(materials))
+    }
+
+    pub fn get(&self, material: Material) -> Option<&MaterialProperties> {
+        self.0.get(material.id as usize)
+    }
+
+    pub fn find(&self, name: &str) -> Option<Material> {
+        self.0
+            .iter()
+            .position(|properties| properties.name == name)
+            .map(|id| Material { id: id as u32 })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Material, &MaterialProperties)> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(id, properties)| (Material { id: id as u32 }, properties))
+    }
+
+    pub fn opacity(&self, material: Material) -> Opacity {
+        self.get(material)
+            .map(|properties| properties.opacity)
+            .unwrap_or_default()
+    }
+
+    /// Whether `occluder` hides the faces of `material` it's touching. Voxels can be seen behind anything but opaque
+    /// materials, except when both are of the same material so that glass panes or leaves merge together.
+    pub fn occludes(&self, occluder: Material, material: Material) -> bool {
+        occluder == material || self.opacity(occluder) == Opacity::Opaque
+    }
+
+    /// Block light level emitted by voxels of this material.
+    pub fn light_emission(&self, material: Material) -> u8 {
+        let Some(properties) = self.get(material) else { return 0 };
+        let brightness = properties.emissive.iter().copied().fold(0.0, f32::max);
+        (brightness.clamp(0.0, 1.0) * MAX_LIGHT_LEVEL as f32).round() as u8
+    }
+
+    pub fn textures(&self, material: Material) -> FaceTextures {
+        self.get(material)
+            .map(|properties| properties.textures)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Resource)]
+struct MaterialRegistryHandle(Handle<MaterialRegistryAsset>);
+
+#[derive(Resource)]
+struct MaterialRegistryPath(String);
+
+/// Loads the material registry file, and keeps the `MaterialRegistry` resource up to date with it.
+pub struct MaterialRegistryPlugin {
+    /// Registry file, relative to the assets folder.
+    pub path: String,
+}
+
+impl Plugin for MaterialRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<MaterialRegistryAsset>()
+            .init_asset_loader::<MaterialRegistryLoader>()
+            .insert_resource(MaterialRegistryPath(self.path.clone()))
+            .add_systems(Startup, Self::load_registry)
+            .add_systems(Update, Self::update_registry);
+    }
+}
+
+impl MaterialRegistryPlugin {
+    fn load_registry(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        path: Res<MaterialRegistryPath>,
+    ) {
+        commands.insert_resource(MaterialRegistryHandle(asset_server.load(&path.0)));
+    }
+
+    fn update_registry(
+        mut commands: Commands,
+        mut events: EventReader<AssetEvent<MaterialRegistryAsset>>,
+        handle: Res<MaterialRegistryHandle>,
+        assets: Res<Assets<MaterialRegistryAsset>>,
+    ) {
+        for event in events.read() {
+            if !event.is_loaded_with_dependencies(&handle.0) && !event.is_modified(&handle.0) {
+                continue;
+            }
+            let Some(registry) = assets.get(&handle.0) else { continue };
+            commands.insert_resource(MaterialRegistry::new(registry.materials.clone()));
+        }
+    }
+}
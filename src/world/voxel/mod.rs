@@ -2,11 +2,12 @@ use bevy::{math::UVec3, prelude::IVec3};
 
 use self::{material::Material, shape::Shape};
 
+pub mod collision;
 pub mod material;
 pub mod shape;
 
 // TODO: Maybe split into two structs, a voxel that represent any voxel (shape + material), and a world voxel (absolute position, properties, and shape + material)
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct VoxelDescriptor {
     pub shape: Shape,
     pub material: Material,
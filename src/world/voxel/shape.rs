@@ -1,6 +1,6 @@
 use std::sync::LazyLock;
 
-use bevy::prelude::{EulerRot, Mat4, UVec3, Vec3};
+use bevy::prelude::{EulerRot, Mat3, Mat4, UVec3, Vec3};
 
 use crate::world::chunk::VoxelIndex;
 
@@ -183,6 +183,59 @@ impl Shape {
     pub fn new(rotation: Rotation, volume: Volume) -> Self {
         Self { rotation, volume }
     }
+
+    /// Shape of the voxel once turned or mirrored by `matrix`, which has to map each axis onto an axis. Every volume
+    /// is symmetrical, so mirrored shapes are rotations of the same volume too. `None` when no rotation of the volume
+    /// matches, as happens with matrices that don't map axes onto axes.
+    pub fn transformed(self, matrix: Mat3) -> Option<Self> {
+        let transform = |vertex: UVec3| {
+            let center_at_origin = vertex.as_vec3() - Vec3::new(0.5, 0.5, 0.5);
+            (matrix * center_at_origin + Vec3::new(0.5, 0.5, 0.5))
+                .round()
+                .as_uvec3()
+        };
+        // Corners of the shape and the planes of its interior triangles, as the corners lying on them. Planes
+        // rather than triangles as mirroring changes the diagonal a square is split along.
+        let signature = |mask: u8, triangles: &mut dyn Iterator<Item = [UVec3; 3]>| {
+            let mut planes = triangles
+                .map(|[a, b, c]| {
+                    let (a, b, c) = (a.as_ivec3(), b.as_ivec3(), c.as_ivec3());
+                    let normal = (b - a).cross(c - a);
+                    (0..8)
+                        .filter(|bit| normal.dot(VERTEX_LIST[*bit].as_ivec3() - a) == 0)
+                        .fold(0u8, |acc, bit| acc | (1 << bit))
+                })
+                .collect::<Vec<_>>();
+            planes.sort();
+            planes.dedup();
+            (mask, planes)
+        };
+
+        let index = ShapeDescriptor::from(self).0 as usize;
+        let mask = (0..8)
+            .filter(|bit| SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP[index] & (1 << bit) != 0)
+            .fold(0, |acc, bit| {
+                acc | (1 << vertex_to_index(transform(VERTEX_LIST[bit])))
+            });
+        let target = signature(
+            mask,
+            &mut SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP[index]
+                .iter()
+                .map(|triangle| triangle.map(transform)),
+        );
+
+        (0..24)
+            .map(|rotation: u8| Shape::new(rotation.into(), self.volume))
+            .find(|shape| {
+                let index = ShapeDescriptor::from(*shape).0 as usize;
+                signature(
+                    SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP[index],
+                    &mut SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP[index]
+                        .iter()
+                        .copied(),
+                ) == target
+            })
+    }
 }
 
 pub struct ShapeDescriptor(pub u8);
@@ -193,6 +246,12 @@ impl From<Shape> for ShapeDescriptor {
     }
 }
 
+impl From<ShapeDescriptor> for Shape {
+    fn from(descriptor: ShapeDescriptor) -> Self {
+        Self::new((descriptor.0 & 0b1_1111).into(), (descriptor.0 >> 5).into())
+    }
+}
+
 pub static VERTEX_LIST: [UVec3; 8] = [
     UVec3::new(0, 0, 0),
     UVec3::new(1, 0, 0),
@@ -339,24 +398,47 @@ pub fn vertex_to_index(vertex: UVec3) -> usize {
     VERTEX_LIST.iter().position(|&v| v == vertex).unwrap()
 }
 
+/// Rotation matrix of every `Rotation`: the facing direction, then the rotation of that face around its own axis.
+pub static ROTATION_MAP: LazyLock<[Mat4; 24]> = LazyLock::new(|| {
+    let mut map = [Mat4::IDENTITY; 24];
+    let facing_rotations = [
+        Vec3::new(0.0, 0.0, 0.0),                     // North
+        Vec3::new(0.0, -90.0_f32.to_radians(), 0.0),  // East
+        Vec3::new(0.0, -180.0_f32.to_radians(), 0.0), // South
+        Vec3::new(0.0, -270.0_f32.to_radians(), 0.0), // West
+        Vec3::new(0.0, 0.0, 90.0_f32.to_radians()),   // Top
+        Vec3::new(0.0, 0.0, -90.0_f32.to_radians()),  // Bottom
+    ];
+    // Angles are negative as the angle describes the angle seen when facing the cube from the outside, not the inside
+    let face_rotations = [
+        Vec3::new(0.0, 0.0, 0.0),                     // 0 degrees
+        Vec3::new(-90.0_f32.to_radians(), 0.0, 0.0),  // 90 degrees
+        Vec3::new(-180.0_f32.to_radians(), 0.0, 0.0), // 180 degrees
+        Vec3::new(-270.0_f32.to_radians(), 0.0, 0.0), // 270 degrees
+    ];
+
+    for (facing_rotation_index, facing_rotation) in facing_rotations.iter().enumerate() {
+        for (face_rotation_index, face_rotation) in face_rotations.iter().enumerate() {
+            let rotation = *facing_rotation + *face_rotation;
+            map[facing_rotation_index * 4 + face_rotation_index] =
+                Mat4::from_euler(EulerRot::XYZ, rotation.x, rotation.y, rotation.z);
+        }
+    }
+
+    map
+});
+
+/// Cube vertex once turned around the center of the cube.
+pub fn rotate_vertex(rotation: &Mat4, vertex: UVec3) -> UVec3 {
+    let center_at_origin = vertex.as_vec3() - Vec3::new(0.5, 0.5, 0.5);
+    (rotation.transform_vector3(center_at_origin) + Vec3::new(0.5, 0.5, 0.5))
+        .round()
+        .as_uvec3()
+}
+
 pub static SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP: LazyLock<[Vec<[UVec3; 3]>; 256]> =
     LazyLock::new(|| {
         let mut map: [Vec<[UVec3; 3]>; 256] = [(); 256].map(|_| vec![]);
-        let facing_rotations = [
-            Vec3::new(0.0, 0.0, 0.0),                     // North
-            Vec3::new(0.0, -90.0_f32.to_radians(), 0.0),  // East
-            Vec3::new(0.0, -180.0_f32.to_radians(), 0.0), // South
-            Vec3::new(0.0, -270.0_f32.to_radians(), 0.0), // West
-            Vec3::new(0.0, 0.0, 90.0_f32.to_radians()),   // Top
-            Vec3::new(0.0, 0.0, -90.0_f32.to_radians()),  // Bottom
-        ];
-        // Angles are negative as the angle describes the angle seen when facing the cube from the outside, not the inside
-        let face_rotations = [
-            Vec3::new(0.0, 0.0, 0.0),                     // 0 degrees
-            Vec3::new(-90.0_f32.to_radians(), 0.0, 0.0),  // 90 degrees
-            Vec3::new(-180.0_f32.to_radians(), 0.0, 0.0), // 180 degrees
-            Vec3::new(-270.0_f32.to_radians(), 0.0, 0.0), // 270 degrees
-        ];
 
         for (shape_index, shape) in [
             &ZERO_SIXTH_INTERIOR_VERTICES,
@@ -369,31 +451,21 @@ pub static SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP: LazyLock<[Vec<[UVec3; 3]>;
         .iter()
         .enumerate()
         {
-            for (facing_rotation_index, facing_rotation) in facing_rotations.iter().enumerate() {
-                for (face_rotation_index, face_rotation) in face_rotations.iter().enumerate() {
-                    let rotation = *facing_rotation + *face_rotation;
-                    let rot = Mat4::from_euler(EulerRot::XYZ, rotation.x, rotation.y, rotation.z);
-                    let rotated_vertices = shape
-                        .iter()
-                        .map(|triangle| {
-                            triangle
-                                .iter()
-                                .map(|vertex| {
-                                    let center_at_origin =
-                                        vertex.as_vec3() - Vec3::new(0.5, 0.5, 0.5);
-                                    let rotated = rot.transform_vector3(center_at_origin);
-                                    (rotated + Vec3::new(0.5, 0.5, 0.5)).round().as_uvec3()
-                                })
-                                .collect::<Vec<UVec3>>()
-                                .try_into()
-                                .unwrap()
-                        })
-                        .collect::<Vec<[UVec3; 3]>>();
-
-                    let index =
-                        (facing_rotation_index * 4 + face_rotation_index) | (shape_index << 5);
-                    map[index] = rotated_vertices;
-                }
+            for (rotation_index, rot) in ROTATION_MAP.iter().enumerate() {
+                let rotated_vertices = shape
+                    .iter()
+                    .map(|triangle| {
+                        triangle
+                            .iter()
+                            .map(|vertex| rotate_vertex(rot, *vertex))
+                            .collect::<Vec<UVec3>>()
+                            .try_into()
+                            .unwrap()
+                    })
+                    .collect::<Vec<[UVec3; 3]>>();
+
+                let index = rotation_index | (shape_index << 5);
+                map[index] = rotated_vertices;
             }
         }
 
@@ -402,21 +474,6 @@ pub static SHAPE_DESCRIPTOR_TO_INTERIOR_VERTICES_MAP: LazyLock<[Vec<[UVec3; 3]>;
 
 pub static SHAPE_DESCRIPTOR_TO_FACE_FLAGS_MAP: LazyLock<[u32; 256]> = LazyLock::new(|| {
     let mut map: [u32; 256] = [1; 256];
-    let facing_rotations = [
-        Vec3::new(0.0, 0.0, 0.0),                     // North
-        Vec3::new(0.0, -90.0_f32.to_radians(), 0.0),  // East
-        Vec3::new(0.0, -180.0_f32.to_radians(), 0.0), // South
-        Vec3::new(0.0, -270.0_f32.to_radians(), 0.0), // West
-        Vec3::new(0.0, 0.0, 90.0_f32.to_radians()),   // Top
-        Vec3::new(0.0, 0.0, -90.0_f32.to_radians()),  // Bottom
-    ];
-    // Angles are negative as the angle describes the angle seen when facing the cube from the outside, not the inside
-    let face_rotations = [
-        Vec3::new(0.0, 0.0, 0.0),                     // 0 degrees
-        Vec3::new(-90.0_f32.to_radians(), 0.0, 0.0),  // 90 degrees
-        Vec3::new(-180.0_f32.to_radians(), 0.0, 0.0), // 180 degrees
-        Vec3::new(-270.0_f32.to_radians(), 0.0, 0.0), // 270 degrees
-    ];
 
     for (shape_index, shape) in [
         &ZERO_SIXTH_VERTEX_LIST,
@@ -430,107 +487,195 @@ pub static SHAPE_DESCRIPTOR_TO_FACE_FLAGS_MAP: LazyLock<[u32; 256]> = LazyLock::
     .iter()
     .enumerate()
     {
-        if shape_index == 4 {
-            continue;
-        } // sinful shape
-
-        for (facing_rotation_index, facing_rotation) in facing_rotations.iter().enumerate() {
-            for (face_rotation_index, face_rotation) in face_rotations.iter().enumerate() {
-                let rotation = *facing_rotation + *face_rotation;
-                let rot = Mat4::from_euler(EulerRot::XYZ, rotation.x, rotation.y, rotation.z);
-                let rotated_vertices = shape
-                    .iter()
-                    .map(|vertex| {
-                        let center_at_origin = vertex.as_vec3() - Vec3::new(0.5, 0.5, 0.5);
-                        let rotated = rot.transform_vector3(center_at_origin);
-                        (rotated + Vec3::new(0.5, 0.5, 0.5)).round().as_uvec3()
-                    })
-                    .collect::<Vec<UVec3>>();
-                let voxel_index: VoxelIndex = rotated_vertices
-                    .iter()
-                    .fold(0, |acc, vertex| acc | (1 << vertex_to_index(*vertex)));
-
-                // NORTH
-                // 0b1100_1100
-                let north_index = voxel_index & NORTH_FACE_MASK;
-                let north_face_flag: u32 = match north_index {
-                    0b0100_1100 => 0b0001,
-                    0b1000_1100 => 0b0010,
-                    0b1100_1000 => 0b0100,
-                    0b1100_0100 => 0b1000,
-                    0b1100_1100 => 0b1111,
-                    _ => 0,
+        for (rotation_index, rot) in ROTATION_MAP.iter().enumerate() {
+            let rotated_vertices = shape
+                .iter()
+                .map(|vertex| rotate_vertex(rot, *vertex))
+                .collect::<Vec<UVec3>>();
+            let voxel_index: VoxelIndex = rotated_vertices
+                .iter()
+                .fold(0, |acc, vertex| acc | (1 << vertex_to_index(*vertex)));
+
+            // NORTH
+            // 0b1100_1100
+            let north_index = voxel_index & NORTH_FACE_MASK;
+            let north_face_flag: u32 = match north_index {
+                0b0100_1100 => 0b0001,
+                0b1000_1100 => 0b0010,
+                0b1100_1000 => 0b0100,
+                0b1100_0100 => 0b1000,
+                0b1100_1100 => 0b1111,
+                _ => 0,
+            };
+
+            // EAST
+            // 0b1001_1001
+            let east_index = voxel_index & EAST_FACE_MASK;
+            let east_face_flag: u32 = match east_index {
+                0b0001_0101 => 0b0001,
+                0b0101_0001 => 0b0010,
+                0b0101_0100 => 0b0100,
+                0b0100_0101 => 0b1000,
+                0b0101_0101 => 0b1111,
+                _ => 0,
+            };
+
+            // SOUTH
+            // 0b0011_0011
+            let south_index = voxel_index & SOUTH_FACE_MASK;
+            let south_face_flag: u32 = match south_index {
+                0b0001_0011 => 0b0001,
+                0b0010_0011 => 0b0010,
+                0b0011_0010 => 0b0100,
+                0b0011_0001 => 0b1000,
+                0b0011_0011 => 0b1111,
+                _ => 0,
+            };
+
+            // WEST
+            // 0b0110_0110
+            let west_index = voxel_index & WEST_FACE_MASK;
+            let west_face_flag: u32 = match west_index {
+                0b0010_1010 => 0b0001,
+                0b1010_0010 => 0b0010,
+                0b1010_1000 => 0b0100,
+                0b1000_1010 => 0b1000,
+                0b1010_1010 => 0b1111,
+                _ => 0,
+            };
+
+            // TOP
+            // 0b1111_0000
+            let top_index = voxel_index & TOP_FACE_MASK;
+            let top_face_flag: u32 = match top_index {
+                0b0111_0000 => 0b0001,
+                0b1101_0000 => 0b0010,
+                0b1110_0000 => 0b0100,
+                0b1011_0000 => 0b1000,
+                0b1111_0000 => 0b1111,
+                _ => 0,
+            };
+
+            // BOTTOM
+            // 0b0000_1111
+            let bottom_index = voxel_index & BOTTOM_FACE_MASK;
+            let bottom_face_flag: u32 = match bottom_index {
+                0b0000_0111 => 0b0001,
+                0b0000_1101 => 0b0010,
+                0b0000_1110 => 0b0100,
+                0b0000_1011 => 0b1000,
+                0b0000_1111 => 0b1111,
+                _ => 0,
+            };
+
+            let mut result: u32 = bottom_face_flag
+                | top_face_flag << 4
+                | west_face_flag << 8
+                | south_face_flag << 12
+                | east_face_flag << 16
+                | north_face_flag << 20;
+
+            // Sinful shape: the 4/6 shares its vertices with the 5/6, but its interior surface folds over the
+            // triangle that would be its north face, which has to be removed once rotated.
+            if shape_index == 4 {
+                let shift = match rot.transform_vector3(Vec3::Z).round().as_ivec3().to_array() {
+                    [0, -1, 0] => 0,
+                    [0, 1, 0] => 4,
+                    [1, 0, 0] => 8,
+                    [0, 0, -1] => 12,
+                    [-1, 0, 0] => 16,
+                    _ => 20,
                 };
+                result &= !(0b1111 << shift);
+            }
 
-                // EAST
-                // 0b1001_1001
-                let east_index = voxel_index & EAST_FACE_MASK;
-                let east_face_flag: u32 = match east_index {
-                    0b0001_0101 => 0b0001,
-                    0b0101_0001 => 0b0010,
-                    0b0101_0100 => 0b0100,
-                    0b0100_0101 => 0b1000,
-                    0b0101_0101 => 0b1111,
-                    _ => 0,
-                };
+            let index = rotation_index | (shape_index << 5);
+            map[index] = result;
+        }
+    }
 
-                // SOUTH
-                // 0b0011_0011
-                let south_index = voxel_index & SOUTH_FACE_MASK;
-                let south_face_flag: u32 = match south_index {
-                    0b0001_0011 => 0b0001,
-                    0b0010_0011 => 0b0010,
-                    0b0011_0010 => 0b0100,
-                    0b0011_0001 => 0b1000,
-                    0b0011_0011 => 0b1111,
-                    _ => 0,
-                };
+    map
+});
 
-                // WEST
-                // 0b0110_0110
-                let west_index = voxel_index & WEST_FACE_MASK;
-                let west_face_flag: u32 = match west_index {
-                    0b0010_1010 => 0b0001,
-                    0b1010_0010 => 0b0010,
-                    0b1010_1000 => 0b0100,
-                    0b1000_1010 => 0b1000,
-                    0b1010_1010 => 0b1111,
-                    _ => 0,
-                };
+/// Face flags of a single side are either empty, full (0b1111) or one of four half-face triangles, each bit being the
+/// triangle that doesn't touch one of the face corners. Opposite sides share the same bit layout and bits follow the
+/// corners around the face, so a single table resolves the visible part of any side against any adjacent side.
+///
+/// Faces are split along both diagonals into four quarters, each half-face triangle covering two of them. Whatever
+/// isn't covered by the adjacent side stays visible, using the smallest triangle of the side that still covers it.
+pub static FACE_FLAGS_OCCLUSION_MAP: LazyLock<[[u8; 16]; 16]> = LazyLock::new(|| {
+    let quarters = |face_flags: u8| -> u8 {
+        (0..4)
+            .filter(|bit| face_flags & (1 << bit) != 0)
+            .fold(0, |acc, bit| {
+                acc | 1 << ((bit + 1) % 4) | 1 << ((bit + 2) % 4)
+            })
+    };
 
-                // TOP
-                // 0b1111_0000
-                let top_index = voxel_index & TOP_FACE_MASK;
-                let top_face_flag: u32 = match top_index {
-                    0b0111_0000 => 0b0001,
-                    0b1101_0000 => 0b0010,
-                    0b1110_0000 => 0b0100,
-                    0b1011_0000 => 0b1000,
-                    0b1111_0000 => 0b1111,
-                    _ => 0,
-                };
+    let mut map = [[0; 16]; 16];
+    for (face_flags, row) in map.iter_mut().enumerate() {
+        let face_flags = face_flags as u8;
+        let face_quarters = quarters(face_flags);
+
+        for (adjacent_face_flags, visible_face_flags) in row.iter_mut().enumerate() {
+            let visible_quarters = face_quarters & !quarters(adjacent_face_flags as u8);
+
+            *visible_face_flags = if visible_quarters == 0 {
+                0
+            } else if visible_quarters == face_quarters {
+                face_flags
+            } else {
+                (0..4)
+                    .map(|bit| 1 << bit)
+                    .find(|triangle| {
+                        let triangle_quarters = quarters(*triangle);
+                        triangle_quarters & visible_quarters == visible_quarters
+                            && triangle_quarters & face_quarters == triangle_quarters
+                    })
+                    .unwrap_or(face_flags)
+            };
+        }
+    }
 
-                // BOTTOM
-                // 0b0000_1111
-                let bottom_index = voxel_index & BOTTOM_FACE_MASK;
-                let bottom_face_flag: u32 = match bottom_index {
-                    0b0000_0111 => 0b0001,
-                    0b0000_1101 => 0b0010,
-                    0b0000_1110 => 0b0100,
-                    0b0000_1011 => 0b1000,
-                    0b0000_1111 => 0b1111,
-                    _ => 0,
-                };
+    map
+});
 
-                let result: u32 = bottom_face_flag
-                    | top_face_flag << 4
-                    | west_face_flag << 8
-                    | south_face_flag << 12
-                    | east_face_flag << 16
-                    | north_face_flag << 20;
+pub static VOXEL_INDEX_TO_SHAPE_MAP: LazyLock<[Shape; 256]> = LazyLock::new(|| {
+    let mut map: [Shape; 256] = [Shape::EMPTY; 256];
 
-                let index = (facing_rotation_index * 4 + face_rotation_index) | (shape_index << 5);
-                map[index] = result;
+    for (vertex_list_index, vertex_list) in [
+        &ZERO_SIXTH_VERTEX_LIST,
+        &ONE_SIXTH_VERTEX_LIST,
+        &TWO_SIXTH_VERTEX_LIST,
+        &THREE_SIXTH_VERTEX_LIST,
+        &FOUR_SIXTH_VERTEX_LIST,
+        &FIVE_SIXTH_VERTEX_LIST,
+        &SIX_SIXTH_VERTEX_LIST,
+    ]
+    .iter()
+    .enumerate()
+    {
+        for (rotation_index, rot) in ROTATION_MAP.iter().enumerate() {
+            let rotated_vertices = vertex_list
+                .iter()
+                .map(|vertex| rotate_vertex(rot, *vertex))
+                .collect::<Vec<UVec3>>();
+            let grid_index = rotated_vertices
+                .iter()
+                .fold(0, |acc, vertex| acc | (1 << vertex_to_index(*vertex)));
+
+            // Use more aesthetically pleasing shapes for natural generation. The 2/6 and 4/6 both look weird for slopes
+            let aesthetic_volume_index = match vertex_list_index {
+                2 => 1,
+                4 => 5,
+                x => x,
+            };
+
+            if map[grid_index].volume == Volume::ZeroSixth {
+                map[grid_index] = Shape::new(
+                    (rotation_index as u8).try_into().unwrap(),
+                    (aesthetic_volume_index as u8).try_into().unwrap(),
+                )
             }
         }
     }
@@ -538,23 +683,10 @@ pub static SHAPE_DESCRIPTOR_TO_FACE_FLAGS_MAP: LazyLock<[u32; 256]> = LazyLock::
     map
 });
 
-pub static VOXEL_INDEX_TO_SHAPE_MAP: LazyLock<[Shape; 256]> = LazyLock::new(|| {
-    let mut map: [Shape; 256] = [Shape::EMPTY; 256];
-    let facing_rotations = [
-        Vec3::new(0.0, 0.0, 0.0),                     // North
-        Vec3::new(0.0, -90.0_f32.to_radians(), 0.0),  // East
-        Vec3::new(0.0, -180.0_f32.to_radians(), 0.0), // South
-        Vec3::new(0.0, -270.0_f32.to_radians(), 0.0), // West
-        Vec3::new(0.0, 0.0, 90.0_f32.to_radians()),   // Top
-        Vec3::new(0.0, 0.0, -90.0_f32.to_radians()),  // Bottom
-    ];
-    // Angles are negative as the angle describes the angle seen when facing the cube from the outside, not the inside
-    let face_rotations = [
-        Vec3::new(0.0, 0.0, 0.0),                     // 0 degrees
-        Vec3::new(-90.0_f32.to_radians(), 0.0, 0.0),  // 90 degrees
-        Vec3::new(-180.0_f32.to_radians(), 0.0, 0.0), // 180 degrees
-        Vec3::new(-270.0_f32.to_radians(), 0.0, 0.0), // 270 degrees
-    ];
+/// Cube corners (as `VERTEX_LIST` bits) touched by every shape. Unlike `SHAPE_DESCRIPTOR_TO_VOXEL_INDEX_MAP` this keeps
+/// the 2/6 and 4/6 volumes as they are.
+pub static SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP: LazyLock<[u8; 256]> = LazyLock::new(|| {
+    let mut map: [u8; 256] = [0; 256];
 
     for (vertex_list_index, vertex_list) in [
         &ZERO_SIXTH_VERTEX_LIST,
@@ -568,38 +700,13 @@ pub static VOXEL_INDEX_TO_SHAPE_MAP: LazyLock<[Shape; 256]> = LazyLock::new(|| {
     .iter()
     .enumerate()
     {
-        for (facing_rotation_index, facing_rotation) in facing_rotations.iter().enumerate() {
-            for (face_rotation_index, face_rotation) in face_rotations.iter().enumerate() {
-                let rotation = *facing_rotation + *face_rotation;
-                let rot = Mat4::from_euler(EulerRot::XYZ, rotation.x, rotation.y, rotation.z);
-                let rotated_vertices = vertex_list
-                    .iter()
-                    .map(|vertex| {
-                        let center_at_origin = vertex.as_vec3() - Vec3::new(0.5, 0.5, 0.5);
-                        let rotated = rot.transform_vector3(center_at_origin);
-                        (rotated + Vec3::new(0.5, 0.5, 0.5)).round().as_uvec3()
-                    })
-                    .collect::<Vec<UVec3>>();
-                let grid_index = rotated_vertices
-                    .iter()
-                    .fold(0, |acc, vertex| acc | (1 << vertex_to_index(*vertex)));
-
-                // Use more aesthetically pleasing shapes for natural generation. The 2/6 and 4/6 both look weird for slopes
-                let aesthetic_volume_index = match vertex_list_index {
-                    2 => 1,
-                    4 => 5,
-                    x => x,
-                };
+        for (rotation_index, rot) in ROTATION_MAP.iter().enumerate() {
+            let mask = vertex_list.iter().fold(0, |acc, vertex| {
+                acc | (1 << vertex_to_index(rotate_vertex(rot, *vertex)))
+            });
 
-                if map[grid_index].volume == Volume::ZeroSixth {
-                    map[grid_index] = Shape::new(
-                        ((facing_rotation_index * 4 + face_rotation_index) as u8)
-                            .try_into()
-                            .unwrap(),
-                        (aesthetic_volume_index as u8).try_into().unwrap(),
-                    )
-                }
-            }
+            let index = rotation_index | (vertex_list_index << 5);
+            map[index] = mask;
         }
     }
 
@@ -617,3 +724,68 @@ pub static SHAPE_DESCRIPTOR_TO_VOXEL_INDEX_MAP: LazyLock<[VoxelIndex; 256]> = La
     }
     map
 });
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use bevy::prelude::{Mat3, Vec3};
+
+    use super::{
+        vertex_to_index, Shape, ShapeDescriptor, SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP, VERTEX_LIST,
+    };
+
+    /// Quarter turns around the vertical axis, mirrored along X or not.
+    fn transforms() -> impl Iterator<Item = Mat3> {
+        (0..8).map(|i| {
+            let mirror = if i >= 4 {
+                Mat3::from_diagonal(Vec3::new(-1.0, 1.0, 1.0))
+            } else {
+                Mat3::IDENTITY
+            };
+            let rotation = Mat3::from_rotation_y(FRAC_PI_2 * (i % 4) as f32);
+            Mat3::from_cols_array(&(rotation * mirror).to_cols_array().map(f32::round))
+        })
+    }
+
+    fn corner_mask(shape: Shape) -> u8 {
+        SHAPE_DESCRIPTOR_TO_CORNER_MASK_MAP[ShapeDescriptor::from(shape).0 as usize]
+    }
+
+    /// Corners of `mask` moved by `matrix` around the center of the voxel.
+    fn transformed_mask(mask: u8, matrix: Mat3) -> u8 {
+        (0..8)
+            .filter(|bit| mask & (1 << bit) != 0)
+            .fold(0, |acc, bit| {
+                let corner = matrix * (VERTEX_LIST[bit].as_vec3() - 0.5) + 0.5;
+                acc | (1 << vertex_to_index(corner.round().as_uvec3()))
+            })
+    }
+
+    #[test]
+    fn transformed_shapes_round_trip_their_corners() {
+        for volume in 1..=6u8 {
+            for rotation in 0..24u8 {
+                let shape = Shape::new(rotation.into(), volume.into());
+                for matrix in transforms() {
+                    let transformed = shape
+                        .transformed(matrix)
+                        .unwrap_or_else(|| panic!("{shape:?} has no shape once transformed"));
+                    assert_eq!(transformed.volume, shape.volume);
+                    assert_eq!(
+                        corner_mask(transformed),
+                        transformed_mask(corner_mask(shape), matrix),
+                        "{shape:?} transformed by {matrix}"
+                    );
+
+                    let back = transformed.transformed(matrix.inverse()).unwrap();
+                    assert_eq!(
+                        corner_mask(back),
+                        corner_mask(shape),
+                        "{shape:?} transformed back from {transformed:?}"
+                    );
+                }
+            }
+        }
+    }
+}